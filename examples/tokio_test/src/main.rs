@@ -5,35 +5,32 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::task::JoinHandle;
 
-use rs_observable::ChObservable;
+use rs_observable::{ChObservable, ObserverId};
 
 #[derive(Debug)]
 struct ObserverObj {
     pub v: Arc<Mutex<Option<String>>>,
-    observable: Arc<Mutex<ChObservable<String>>>,
-    pub id: Option<u32>,
+    observable: Arc<ChObservable<String>>,
+    pub id: Option<ObserverId>,
     h: Option<JoinHandle<()>>,
 }
 
 
 impl ObserverObj {
     pub fn new() -> Self {
-        let o = ObserverObj {
+        ObserverObj {
             v: Arc::new(Mutex::new(None)),
-            observable: Arc::new(Mutex::new(ChObservable::new())),
+            observable: Arc::new(ChObservable::new()),
             id: None,
             h: None,
-        };
-        o
+        }
     }
 
-    pub async fn observe(&mut self)-> (u32, Receiver<String>) {
-        let mut g = self.observable.lock().await;
-        let o: &mut ChObservable<String> = &mut g;
-        o.register().await
+    pub async fn observe(&mut self)-> (ObserverId, Receiver<String>) {
+        self.observable.register().await
     }
 
-    pub async fn register(&mut self, cho: &mut ChObservable<String>) {
+    pub async fn register(&mut self, cho: &ChObservable<String>) {
         let (id, mut rx) = cho.register().await;
         self.id = Some(id);
         let value = self.v.clone();
@@ -49,14 +46,8 @@ impl ObserverObj {
                             let v: &mut Option<String> = &mut g;
                             *v = Some(s.clone());
                         }
-                        {
-                            let x: &mut ChObservable<String>;
-                            debug!("[id={}]request lock, to inform about values ...", id);
-                            let mut og = o.lock().await;
-                            debug!("[id={}]got lock, to inform about values", id);
-                            x = &mut og;
-                            let _ = x.notify(&s).await;
-                        };
+                        debug!("[id={}]inform about values", id);
+                        let _ = o.notify(&s).await;
                     },
                     None => debug!("[id={}]received NONE value.", id),
                 };
@@ -70,13 +61,13 @@ impl ObserverObj {
 #[tokio::main]
 
 async fn main() {
-    async fn check_val(id: u32, ov: &Arc<Mutex<Option<String>>>, expected: &Option<String>) {
+    async fn check_val(id: ObserverId, ov: &Arc<Mutex<Option<String>>>, expected: &Option<String>) {
         let g = ov.lock().await;
         let v: &Option<String> = &g;
         println!("Observer [id={}], content: {:?}", id, v);
         assert_eq!(v, expected);
     }
-    async fn check_val2(id: u32, rx: &mut Receiver<String>, expected: &String) {
+    async fn check_val2(id: ObserverId, rx: &mut Receiver<String>, expected: &String) {
         debug!("[id2={}]i am waiting to get informed ...", id);
         match rx.recv().await {
             Some(v) => {
@@ -85,7 +76,7 @@ async fn main() {
             },
             None => {
                 debug!("[id2={}]i was informed 2", id);
-                assert!(false);
+                panic!("channel was closed");
             },
         };
     }
@@ -95,15 +86,15 @@ async fn main() {
 
     info!("'rs_observable tokio_test started");
 
-    let mut cho: ChObservable<String> = ChObservable::new();
+    let cho: ChObservable<String> = ChObservable::new();
     let mut o1: ObserverObj = ObserverObj::new();
-    o1.register(&mut cho).await;
+    o1.register(&cho).await;
     let (_, mut o1_rx) = o1.observe().await;
     let mut o2: ObserverObj = ObserverObj::new();
-    o2.register(&mut cho).await;
+    o2.register(&cho).await;
     let (_, mut o2_rx) = o2.observe().await;
     let mut o3: ObserverObj = ObserverObj::new();
-    o3.register(&mut cho).await;
+    o3.register(&cho).await;
     let (_, mut o3_rx) = o3.observe().await;
     let expected_none = None;
     check_val(o1.id.unwrap(), &o1.v, &expected_none).await;
@@ -111,8 +102,8 @@ async fn main() {
     check_val(o3.id.unwrap(), &o3.v, &expected_none).await;
     let t1 = "test-99".to_string();
     match cho.notify(&t1).await {
-        Ok(()) => (),
-        Err(_) => assert!(false, "receive error while notify"),
+        Ok(_) => (),
+        Err(_) => panic!("receive error while notify"),
     };
 
     let expected_1 = Some(t1.clone());
@@ -122,7 +113,7 @@ async fn main() {
     check_val2(o3.id.unwrap(), &mut o3_rx, &t1).await;
 
     let mut o4: ObserverObj = ObserverObj::new();
-    o4.register(&mut cho).await;
+    o4.register(&cho).await;
     let (_, mut o4_rx) = o4.observe().await;
     check_val(o1.id.unwrap(), &o1.v, &expected_1).await;
     check_val(o2.id.unwrap(), &o2.v, &expected_1).await;
@@ -131,8 +122,8 @@ async fn main() {
 
     let t2 = "test-999".to_string();
     match cho.notify(&t2).await {
-        Ok(()) => (),
-        Err(_) => assert!(false, "receive error while notify"),
+        Ok(_) => (),
+        Err(_) => panic!("receive error while notify"),
     };
     check_val2(o1.id.unwrap(), &mut o1_rx, &t2).await;
     check_val2(o2.id.unwrap(), &mut o2_rx, &t2).await;
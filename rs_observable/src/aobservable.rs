@@ -0,0 +1,258 @@
+//! Thread safe, blocking observer pattern implementation. It's the std
+//! thread counterpart of the tokio based `ChObservable`
+
+use log::debug;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc;
+
+use crate::error::Result;
+use crate::lock::{Mutex, RwLock};
+use crate::notified::Notified;
+use crate::observer_id::{next_tag, ObserverId};
+use crate::slab::Slab;
+
+/// Observer with its own lock, so notifications of other threads can call
+/// the other observers meanwhile
+type StoredObserver<T> = Mutex<Box<dyn FnMut(T) + Send>>;
+
+/// Observable that can be shared between threads, e.g. in an `Arc`. The
+/// observers are called within the notify call, on the notifying thread.
+/// Notifications of several threads run at the same time, but one
+/// observer is called by one of them at a time.
+pub struct AObservable<T: Clone> {
+    /// Registered observers. Notifications only read them, registrations
+    /// change them.
+    observers: RwLock<Slab<StoredObserver<T>>>,
+    /// Identifies this instance in the created observer IDs
+    tag: u64,
+    /// Next available observerId for registrations
+    next_id: AtomicU32,
+}
+
+impl<T: Clone> Debug for AObservable<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let ids: Vec<u32> = self.observers.read().ids().map(|id| id.value()).collect();
+        f.debug_struct("AObservable")
+            .field("observer_ids", &ids)
+            .field("next_id", &self.next_id.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl<T: Clone> Default for AObservable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> AObservable<T> {
+    /// Creates a new AObservable object
+    pub fn new() -> Self {
+        AObservable {
+            observers: RwLock::new(Slab::new()),
+            tag: next_tag(),
+            next_id: AtomicU32::new(1),
+        }
+    }
+
+    /// This function registers a new observer. It returns the ID of the
+    /// registered observer. The observer must not call the observable,
+    /// it's called while the observers are locked.
+    ///
+    /// ## Arguments
+    /// * `observer` - function that is called with every notified value
+    ///
+    pub fn register<F>(&self, observer: F) -> ObserverId
+    where
+        F: FnMut(T) + Send + 'static,
+    {
+        let id = ObserverId::new(self.tag, self.next_id.fetch_add(1, Ordering::Relaxed));
+        self.observers.write().insert(id, Mutex::new(Box::new(observer)));
+        id
+    }
+
+    /// This function unregisters an observer. It fails if the ID was
+    /// created by another observable.
+    ///
+    /// ## Arguments
+    /// * `observer_id` - ID returned after the registration of an observer
+    ///
+    pub fn unregister(&self, observer_id: ObserverId) -> Result<()> {
+        observer_id.check_tag(self.tag)?;
+        self.observers.write().remove(observer_id);
+        Ok(())
+    }
+
+    /// Triggers the notification of the registered observers, it returns
+    /// after all observers were called. It returns the number of called
+    /// observers. A panicking observer doesn't stop the notification and
+    /// doesn't make the observable unusable, the returned `Notified` lists
+    /// it instead.
+    ///
+    /// ## Arguments
+    /// * `data` - data that should be passed to the observers
+    pub fn notify(&self, data: &T) -> Notified {
+        let observers = self.observers.read();
+        let mut notified = Notified::default();
+        for (id, o) in observers.ids().zip(observers.iter()) {
+            notified.call(id, || {
+                (*o.lock())(data.clone());
+                Ok(())
+            });
+        }
+        notified
+    }
+
+    /// Removes all observers, e.g. for a reconfiguration. The channels of
+    /// observers from `register_channel` are closed. It returns the number
+    /// of removed observers.
+    pub fn clear(&self) -> usize {
+        let observers = std::mem::take(&mut *self.observers.write());
+        observers.len()
+    }
+
+    /// Returns the number of registered observers. Producers can use it
+    /// to skip building a value that nobody gets.
+    pub fn observer_count(&self) -> usize {
+        self.observers.read().len()
+    }
+
+    /// Returns the IDs of the registered observers in the order of
+    /// registration, e.g. for debug output
+    pub fn observer_ids(&self) -> Vec<ObserverId> {
+        self.observers.read().ids().collect()
+    }
+
+    /// Returns true if no observer is registered
+    pub fn is_empty(&self) -> bool {
+        self.observer_count() == 0
+    }
+}
+
+impl<T: Clone + Send + 'static> AObservable<T> {
+    /// This function registers a new observer, that gets the values over a
+    /// std channel. It returns the ID of the registered observer and the
+    /// receiver, that can be used on another thread.
+    pub fn register_channel(&self) -> (ObserverId, mpsc::Receiver<T>) {
+        let (tx, rx) = mpsc::channel();
+        let id = self.register(move |data| {
+            if tx.send(data).is_err() {
+                debug!("receiver of observer channel is gone, drop value");
+            }
+        });
+        (id, rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::aobservable::AObservable;
+    use crate::error::Error;
+
+    #[test]
+    fn test_aobservable() {
+        let o: Arc<AObservable<u32>> = Arc::new(AObservable::new());
+        let sum = Arc::new(AtomicU32::new(0));
+        let s = sum.clone();
+        let id = o.register(move |v| {
+            s.fetch_add(v, Ordering::SeqCst);
+        });
+        let (_, rx) = o.register_channel();
+        let consumer = thread::spawn(move || rx.iter().collect::<Vec<u32>>());
+
+        let producers: Vec<_> = (0..4)
+            .map(|i| {
+                let o = o.clone();
+                thread::spawn(move || o.notify(&i))
+            })
+            .collect();
+        for p in producers {
+            p.join().unwrap();
+        }
+        assert_eq!(sum.load(Ordering::SeqCst), 6);
+
+        assert_eq!(o.observer_count(), 2);
+        assert_eq!(o.observer_ids()[0], id);
+        o.unregister(id).unwrap();
+        assert_eq!(o.notify(&10).called, 1);
+        assert!(!o.is_empty());
+        assert_eq!(sum.load(Ordering::SeqCst), 6);
+        let other: AObservable<u32> = AObservable::new();
+        assert_eq!(other.unregister(id), Err(Error::ForeignObserverId(id)));
+
+        drop(o);
+        let mut received = consumer.join().unwrap();
+        received.sort();
+        assert_eq!(received, vec![0, 1, 2, 3, 10]);
+    }
+
+    #[test]
+    fn test_concurrent_notify() {
+        let o: Arc<AObservable<u32>> = Arc::new(AObservable::new());
+        let (seen_tx, seen) = mpsc::channel();
+        o.register(move |v| seen_tx.send(v).unwrap());
+        let (entered_tx, entered) = mpsc::channel();
+        let (release_tx, release) = mpsc::channel::<()>();
+        let release = Mutex::new(release);
+        o.register(move |v| {
+            if v == 1 {
+                entered_tx.send(()).unwrap();
+                release.lock().unwrap().recv().unwrap();
+            }
+        });
+
+        let o1 = o.clone();
+        let first = thread::spawn(move || o1.notify(&1));
+        entered.recv().unwrap();
+        // the first notification waits in the second observer, the next
+        // one reaches the first observer anyway
+        let o2 = o.clone();
+        let second = thread::spawn(move || o2.notify(&2));
+        assert_eq!(seen.recv_timeout(Duration::from_secs(5)), Ok(1));
+        assert_eq!(seen.recv_timeout(Duration::from_secs(5)), Ok(2));
+        release_tx.send(()).unwrap();
+        assert_eq!(first.join().unwrap().called, 2);
+        assert_eq!(second.join().unwrap().called, 2);
+    }
+
+    #[test]
+    fn test_concurrent_register() {
+        let o: Arc<AObservable<u32>> = Arc::new(AObservable::new());
+        let registrations: Vec<_> = (0..4)
+            .map(|_| {
+                let o = o.clone();
+                thread::spawn(move || (0..10).map(|_| o.register(|_| {})).collect::<Vec<_>>())
+            })
+            .collect();
+        let mut ids: Vec<u32> = registrations
+            .into_iter()
+            .flat_map(|r| r.join().unwrap())
+            .map(|id| id.value())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, (1..=40).collect::<Vec<_>>());
+        assert_eq!(o.notify(&1).called, 40);
+        assert_eq!(o.clear(), 40);
+        assert!(o.is_empty());
+    }
+
+    #[test]
+    fn test_panicking_observer() {
+        let o: AObservable<u32> = AObservable::new();
+        let id = o.register(|v| assert!(v > 1, "too small"));
+        let (_, rx) = o.register_channel();
+
+        let notified = o.notify(&1);
+        assert_eq!(notified.called, 2);
+        assert_eq!(notified.panicked, vec![id]);
+        // the observable and the panicked observer are still usable
+        assert!(o.notify(&2).is_complete());
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![1, 2]);
+    }
+}
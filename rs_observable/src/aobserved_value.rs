@@ -0,0 +1,294 @@
+//! A thread safe observable wrapper, put around a monitored value. It's
+//! the std thread counterpart of the tokio based `ChObservedValue`
+
+use std::fmt::{self, Debug, Formatter};
+use std::ops::{Add, Sub};
+use std::sync::{mpsc, Arc};
+
+use crate::aobservable::AObservable;
+use crate::error::Result;
+use crate::lock::Mutex;
+use crate::observer_id::ObserverId;
+
+/// Returns true if two values of an observed value are seen as equal
+type Comparator<T> = Arc<dyn Fn(&T, &T) -> bool + Send + Sync>;
+
+/// Object that holds the monitored value and its observers, it can be
+/// shared between threads, e.g. in an `Arc`
+pub struct AObservedValue<T: Clone> {
+    value: Mutex<Option<T>>,
+    observable: AObservable<Option<T>>,
+    /// Replaces `PartialEq` in `set_value_if_changed`, if it's set
+    comparator: Option<Comparator<T>>,
+}
+
+impl<T: Clone> Debug for AObservedValue<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AObservedValue")
+            .field("is_set", &self.value.lock().is_some())
+            .field("observable", &self.observable)
+            .finish()
+    }
+}
+
+impl<T: Clone> Default for AObservedValue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> AObservedValue<T> {
+    /// Create a new instance
+    pub fn new() -> Self {
+        AObservedValue {
+            value: Mutex::new(None),
+            observable: AObservable::new(),
+            comparator: None,
+        }
+    }
+
+    /// Sets the function, that decides in `set_value_if_changed` if a new
+    /// value equals the current one, e.g. to ignore small changes of a
+    /// float. It replaces the `PartialEq` implementation of the value.
+    /// Values without `PartialEq` pass the function to
+    /// `set_value_if_changed_with` instead.
+    ///
+    /// ## Arguments
+    /// * `eq` - returns true if both values are seen as equal
+    ///
+    pub fn set_comparator<F>(&mut self, eq: F)
+    where
+        F: Fn(&T, &T) -> bool + Send + Sync + 'static,
+    {
+        self.comparator = Some(Arc::new(eq));
+    }
+
+    /// Changes the value and notifies the observers, the value stays
+    /// locked until all observers were called. So the observers get the
+    /// changes in the order of the value changes, but they must not
+    /// access the value.
+    fn change(&self, v: Option<T>) {
+        let mut value = self.value.lock();
+        *value = v;
+        self.observable.notify(&value);
+    }
+
+    /// Set a new value to the object. All registered observers are
+    /// called to get notified.
+    ///
+    /// ## Arguments
+    /// * `v` - value to set
+    ///
+    pub fn set_value(&self, v: &T) {
+        self.change(Some(v.clone()));
+    }
+
+    /// Reset the value of the object. All registered observers are
+    /// called to get notified.
+    ///
+    pub fn reset_value(&self) {
+        self.change(None);
+    }
+
+    /// Sets a new value like `set_value`, but only if the given function
+    /// doesn't see it as equal to the current one. The comparison and the
+    /// change are done under the same lock. It needs no `PartialEq` and
+    /// ignores the function of `set_comparator`. It returns true if the
+    /// value was set and the observers were notified.
+    ///
+    /// ## Arguments
+    /// * `v` - value to set
+    /// * `eq` - returns true if both values are seen as equal
+    ///
+    pub fn set_value_if_changed_with<F: Fn(&T, &T) -> bool>(&self, v: &T, eq: F) -> bool {
+        let mut value = self.value.lock();
+        if value.as_ref().is_some_and(|c| eq(c, v)) {
+            return false;
+        }
+        *value = Some(v.clone());
+        self.observable.notify(&value);
+        true
+    }
+
+    /// Returns a copy of the current value
+    pub fn value(&self) -> Option<T> {
+        self.value.lock().clone()
+    }
+
+    /// This function registers a new observer. It returns the ID of the
+    /// registered observer.
+    ///
+    /// ## Arguments
+    /// * `observer` - function that is called with every change of the value
+    ///
+    pub fn register<F>(&self, observer: F) -> ObserverId
+    where
+        F: FnMut(Option<T>) + Send + 'static,
+    {
+        self.observable.register(observer)
+    }
+
+    /// This function unregisters an observer. It fails if the ID was
+    /// created by another observable.
+    ///
+    /// ## Arguments
+    /// * `observer_id` - ID returned after the registration of an observer
+    ///
+    pub fn unregister(&self, observer_id: ObserverId) -> Result<()> {
+        self.observable.unregister(observer_id)
+    }
+}
+
+impl<T: Clone + PartialEq> AObservedValue<T> {
+    /// Sets a new value like `set_value`, but only if it differs from the
+    /// current one, see `set_comparator`. It returns true if the value was
+    /// set and the observers were notified.
+    ///
+    /// ## Arguments
+    /// * `v` - value to set
+    ///
+    pub fn set_value_if_changed(&self, v: &T) -> bool {
+        match &self.comparator {
+            Some(eq) => self.set_value_if_changed_with(v, |a, b| eq(a, b)),
+            None => self.set_value_if_changed_with(v, PartialEq::eq),
+        }
+    }
+}
+
+impl<T: Clone + Default + Add<Output = T> + Sub<Output = T>> AObservedValue<T> {
+    /// Adds to the value and notifies the observers, for counters and
+    /// gauges. An unset value counts as the default value. It returns the
+    /// new value.
+    ///
+    /// ## Arguments
+    /// * `by` - amount to add
+    ///
+    pub fn increment(&self, by: T) -> T {
+        self.step(|v| v + by)
+    }
+
+    /// Subtracts from the value and notifies the observers, like
+    /// `increment`. It returns the new value.
+    ///
+    /// ## Arguments
+    /// * `by` - amount to subtract
+    ///
+    pub fn decrement(&self, by: T) -> T {
+        self.step(|v| v - by)
+    }
+
+    /// Changes the value under the lock, like `change`
+    fn step<F: FnOnce(T) -> T>(&self, f: F) -> T {
+        let mut value = self.value.lock();
+        let v = f(value.take().unwrap_or_default());
+        *value = Some(v.clone());
+        self.observable.notify(&value);
+        v
+    }
+}
+
+impl<T: Clone + Send + 'static> AObservedValue<T> {
+    /// This function registers a new observer, that gets the changes over
+    /// a std channel. It returns the ID of the registered observer and the
+    /// receiver.
+    pub fn register_channel(&self) -> (ObserverId, mpsc::Receiver<Option<T>>) {
+        self.observable.register_channel()
+    }
+
+    /// Returns the number of registered observers
+    pub fn observer_count(&self) -> usize {
+        self.observable.observer_count()
+    }
+
+    /// Returns the IDs of the registered observers in the order of
+    /// registration
+    pub fn observer_ids(&self) -> Vec<ObserverId> {
+        self.observable.observer_ids()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use crate::aobserved_value::AObservedValue;
+
+    #[test]
+    fn test_aobserved_value() {
+        let config: Arc<AObservedValue<String>> = Arc::new(AObservedValue::new());
+        assert_eq!(config.value(), None);
+        let (id, rx) = config.register_channel();
+
+        let c = config.clone();
+        thread::spawn(move || {
+            c.set_value(&"v1".to_string());
+            c.set_value(&"v2".to_string());
+            c.reset_value();
+        })
+        .join()
+        .unwrap();
+        assert_eq!(config.value(), None);
+        assert_eq!(rx.recv(), Ok(Some("v1".to_string())));
+        assert_eq!(rx.recv(), Ok(Some("v2".to_string())));
+        assert_eq!(rx.recv(), Ok(None));
+
+        config.unregister(id).unwrap();
+        config.set_value(&"v3".to_string());
+        assert_eq!(config.value(), Some("v3".to_string()));
+        assert!(rx.recv().is_err());
+    }
+
+    #[test]
+    fn test_increment() {
+        let counter: Arc<AObservedValue<u64>> = Arc::new(AObservedValue::new());
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let c = counter.clone();
+                thread::spawn(move || {
+                    for _ in 0..100 {
+                        c.increment(1);
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(counter.decrement(10), 390);
+    }
+
+    #[test]
+    fn test_set_value_if_changed() {
+        let value: AObservedValue<u32> = AObservedValue::new();
+        let (_, rx) = value.register_channel();
+        assert!(value.set_value_if_changed(&1));
+        assert!(!value.set_value_if_changed(&1));
+        assert!(value.set_value_if_changed(&2));
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![Some(1), Some(2)]);
+
+        let mut f: AObservedValue<f64> = AObservedValue::new();
+        f.set_comparator(|a, b| (a - b).abs() < 0.1);
+        assert!(f.set_value_if_changed(&1.0));
+        assert!(!f.set_value_if_changed(&1.05));
+        assert!(f.set_value_if_changed(&1.2));
+        assert_eq!(f.value(), Some(1.2));
+    }
+
+    #[test]
+    fn test_set_value_if_changed_with() {
+        // no PartialEq, only the name is compared
+        #[derive(Clone)]
+        struct Item {
+            name: &'static str,
+            _hits: u32,
+        }
+
+        let same_name = |a: &Item, b: &Item| a.name == b.name;
+        let value: AObservedValue<Item> = AObservedValue::new();
+        assert!(value.set_value_if_changed_with(&Item { name: "a", _hits: 1 }, same_name));
+        assert!(!value.set_value_if_changed_with(&Item { name: "a", _hits: 2 }, same_name));
+        assert!(value.set_value_if_changed_with(&Item { name: "b", _hits: 3 }, same_name));
+        assert_eq!(value.value().map(|i| i.name), Some("b"));
+    }
+}
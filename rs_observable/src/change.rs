@@ -0,0 +1,20 @@
+//! Notifications with the former and the new state of an observed value
+
+/// Change of an observed value, as it's passed to the change observers.
+/// `None` stands for an unset value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change<T> {
+    /// State before the change
+    pub old: Option<T>,
+    /// State after the change
+    pub new: Option<T>,
+}
+
+impl<T: Clone> Change<T> {
+    /// Keeps the new state in `last` and returns the change from the
+    /// former one
+    pub(crate) fn advance(last: &mut Option<T>, new: Option<T>) -> Self {
+        let old = std::mem::replace(last, new.clone());
+        Change { old, new }
+    }
+}
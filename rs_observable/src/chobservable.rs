@@ -1,102 +1,1511 @@
-/// Implementation of async, tokio based observers. The approach
-/// uses async channels instead of trait callbacks
+//! Implementation of async, tokio based observers. The approach
+//! uses async channels instead of trait callbacks
 
-use log::debug;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+mod async_fn;
+mod borrowed;
+#[cfg(feature = "single")]
+mod bridge;
+mod broadcast;
+mod adaptive;
+mod builder;
+mod bulk;
+mod changes;
+mod conditional;
+mod deadline;
+mod drain;
+mod errors;
+mod fair;
+mod flag;
+mod groups;
+mod hierarchy;
+mod idle;
+mod intercept;
+mod join;
+mod keyed;
+mod lease;
+mod lifecycle;
+mod map;
+mod meta;
+mod operators;
+mod overflow;
+mod permit;
+mod poison;
+mod pipeline;
+mod queue;
+mod quorum;
+mod route;
+mod sequence;
+mod snapshot;
+mod spawner;
+mod split;
+mod stream;
+mod subscription;
+mod take;
+mod topics;
+mod tracker;
+mod try_notify;
+mod value_history;
+mod value_init;
+mod wait;
+mod watch;
+mod watchdog;
+
+pub use adaptive::AdaptiveReceiver;
+pub use async_fn::AsyncFnHandle;
+#[cfg(feature = "single")]
+pub use bridge::SyncMirror;
+pub use builder::ChObservableBuilder;
+pub use flag::ChObservedFlag;
+pub use hierarchy::Propagation;
+pub use idle::IdleClose;
+pub use join::join;
+pub use keyed::ChKeyedObservable;
+pub use lease::ValueLease;
+pub use lifecycle::Lifecycle;
+pub use map::{ChObservableMap, MapChange, MapSnapshot, MapUpdate, SyncHandle};
+pub use permit::NotifyPermit;
+pub use pipeline::Pipeline;
+pub use poison::ValueState;
+pub use queue::QueueReceiver;
+pub use sequence::{SeqItem, Sequenced, SequencedReceiver};
+pub use spawner::Spawner;
+pub use split::{Notifier, SubscriberHandle};
+pub use stream::{Codec, ValueStream};
+pub use subscription::Subscription;
+pub use topics::{HasTopic, Topic, TopicReceiver};
+pub use tracker::ProcessedTracker;
+pub use value_init::ChObservedValueInit;
+pub use watchdog::{HealthEvent, Watchdog};
+#[doc(hidden)]
+pub use snapshot::{SnapshotSource, __read_many, __read_many_value};
+
+use log::{log, warn, Level};
+use std::any::Any;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+use crate::executor::{InlineExecutor, NotifyExecutor};
+use crate::history::History;
+use crate::observer_id::{next_tag, ObserverId};
+use crate::slab::Slab;
+use tokio::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use tokio::sync::mpsc;
-use tokio::sync::mpsc::error::SendError;
-use tokio::sync::mpsc::{Receiver, Sender};
-use std::fmt::{self, Debug, Formatter};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{Receiver, Sender, UnboundedReceiver, UnboundedSender};
+use tokio::time::{timeout_at, Instant};
+use std::fmt::{self, Debug, Display, Formatter};
+use std::ops::{Add, Sub};
+
+/// Capacity of the observer channels, if nothing else is configured
+const DEFAULT_CAPACITY: usize = 10;
+
+/// Max number of chars of a value summary in the log output
+const MAX_SUMMARY_LEN: usize = 120;
+
+/// Decides what happens if the channel of an observer is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// notify waits until the observer has free capacity again
+    #[default]
+    Block,
+    /// The new value is dropped for the observer with the full channel.
+    /// Observers that keep the latest values instead are registered with
+    /// `ChObservable::register_drop_oldest`.
+    DropNewest,
+    /// notify fails with `Error::Overflow`
+    Error,
+    /// Observers with a full channel are served by their weight. The ones
+    /// with the highest weight get free capacity first and notify waits
+    /// for them, the others get only a part of the values, in proportion
+    /// to their weight.
+    WeightedFair,
+}
+
+/// Result of a notification, that tells which observers were reached
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DeliveryReport {
+    /// Observers that got the value
+    pub delivered: Vec<ObserverId>,
+    /// Observers that didn't get the value, e.g. because the deadline
+    /// was reached or the value was dropped for a full channel
+    pub skipped: Vec<ObserverId>,
+    /// Observers that failed, because the receiver was dropped or the
+    /// channel was full with `OverflowPolicy::Error`
+    pub failed: Vec<ObserverId>,
+    /// Names of the observers in the report, that were registered with
+    /// `ChObservable::register_named`
+    pub names: HashMap<ObserverId, String>,
+}
+
+impl DeliveryReport {
+    /// Returns true if all observers got the value
+    pub fn is_complete(&self) -> bool {
+        self.skipped.is_empty() && self.failed.is_empty()
+    }
+
+    /// Returns the name of an observer in the report, if it has one
+    ///
+    /// ## Arguments
+    /// * `observer_id` - ID of an observer in the report
+    ///
+    pub fn name(&self, observer_id: ObserverId) -> Option<&str> {
+        self.names.get(&observer_id).map(String::as_str)
+    }
+
+    /// Adds the observers of another report
+    fn append(&mut self, other: DeliveryReport) {
+        self.delivered.extend(other.delivered);
+        self.skipped.extend(other.skipped);
+        self.failed.extend(other.failed);
+        self.names.extend(other.names);
+    }
+
+    fn push_delivered<T>(&mut self, o: &StoredObserver<T>) {
+        self.delivered.push(o.id);
+        self.add_name(o);
+    }
+
+    fn push_skipped<T>(&mut self, o: &StoredObserver<T>) {
+        self.skipped.push(o.id);
+        self.add_name(o);
+    }
+
+    fn push_failed<T>(&mut self, o: &StoredObserver<T>) {
+        self.failed.push(o.id);
+        self.add_name(o);
+    }
+
+    /// Adds an observer with the result of `ChObservable::deliver`
+    fn push_result<T>(&mut self, o: &StoredObserver<T>, res: &Result<bool>) {
+        match res {
+            Ok(true) => self.push_delivered(o),
+            Ok(false) => self.push_skipped(o),
+            Err(_) => self.push_failed(o),
+        }
+    }
+
+    fn add_name<T>(&mut self, o: &StoredObserver<T>) {
+        if let Some(name) = &o.name {
+            self.names.insert(o.id, name.to_string());
+        }
+    }
+}
+
+/// Receiving side of a registered observer
+enum Target<T> {
+    /// Observer that gets the values over a channel
+    Channel(Sender<T>),
+    /// Observer callback, the closure already contains the dispatch to
+    /// the configured executor
+    Callback(Box<dyn Fn(T) + Send + Sync>),
+    /// Observer that gets the values over an unbounded channel, it never
+    /// blocks the notification
+    Unbounded(UnboundedSender<T>),
+    /// Observer that gets the values over a queue of the observable, it
+    /// never blocks the notification
+    Queue(queue::QueueSender<T>),
+    /// Observer callback, that borrows the value within the notify call
+    Borrowed(Box<dyn Fn(&T) + Send + Sync>),
+    /// Observer that gets a projection of the values over a channel
+    Mapped(Box<dyn borrowed::Projection<T>>),
+}
+
+impl<T> Debug for Target<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Channel(tx) => f
+                .debug_struct("Channel")
+                .field("capacity", &tx.max_capacity())
+                .field("queued", &(tx.max_capacity() - tx.capacity()))
+                .field("closed", &tx.is_closed())
+                .finish(),
+            Target::Callback(_) => f.write_str("Callback"),
+            Target::Borrowed(_) => f.write_str("Borrowed"),
+            Target::Mapped(tx) => f
+                .debug_struct("Mapped")
+                .field("capacity", &tx.max_capacity())
+                .field("closed", &tx.is_closed())
+                .finish(),
+            Target::Unbounded(tx) => f
+                .debug_struct("Unbounded")
+                .field("closed", &tx.is_closed())
+                .finish(),
+            Target::Queue(tx) => tx.fmt(f),
+        }
+    }
+}
+
+/// Decides how `ChObservable::deliver` passes a value into the channel of
+/// an observer, the other targets never wait. Sync code uses
+/// `ChObservable::try_deliver`, that never waits at all.
+#[derive(Clone, Copy, Debug)]
+enum SendStrategy {
+    /// Waits for free capacity, if the overflow policy says so
+    Wait,
+    /// Waits like `Wait`, but not after the deadline. The value is dropped
+    /// for an observer that didn't take it in time.
+    Until(Instant),
+    /// Waits like `Until`, with a deadline per observer
+    Timeout(Duration),
+}
+
+/// Drops the value of a failed send, so the error can be handled
+/// independent of the item type
+fn discard<V>(e: TrySendError<V>) -> TrySendError<()> {
+    match e {
+        TrySendError::Full(_) => TrySendError::Full(()),
+        TrySendError::Closed(_) => TrySendError::Closed(()),
+    }
+}
+
+/// User data attached to a registration
+type Meta = Box<dyn Any + Send + Sync>;
+
+/// Returns the current value for new observers
+type LatestFn<T> = Box<dyn Fn() -> Option<T> + Send + Sync>;
+
+/// Decides which values are passed to an observer
+type Filter<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
+
+/// Selects the observers of one pass of a notification
+type Select<'a, T> = dyn Fn(&StoredObserver<T>) -> bool + Send + Sync + 'a;
+
+/// Returns true after the receiver behind a callback was dropped
+type ClosedFn = Box<dyn Fn() -> bool + Send + Sync>;
+
+/// Returns true if two values of an observed value are seen as equal
+type Comparator<T> = Arc<dyn Fn(&T, &T) -> bool + Send + Sync>;
 
-#[derive(Debug)]
 struct StoredObserver<T> {
-    tx: Sender<T>,
-    id: u32,
+    target: Target<T>,
+    id: ObserverId,
+    /// Readable name for log output, reports and Debug dumps
+    name: Option<Arc<str>>,
+    /// User data of the registration, it can be replaced while a
+    /// notification passes a value to the observer
+    meta: std::sync::Mutex<Option<Meta>>,
+    /// Group the observer belongs to
+    group: Option<String>,
+    /// Share of the delivery to a full channel, with `OverflowPolicy::WeightedFair`
+    weight: u32,
+    /// Collected weight since the last delivery to the full channel
+    credit: AtomicU64,
+    /// Counters of the delivered and processed values, for observers
+    /// with a `ProcessedTracker`
+    progress: Option<Arc<tracker::Progress>>,
+    /// Only values that pass the filter are passed to the observer
+    filter: Option<Filter<T>>,
+    /// Consumer task of the observer, it's aborted with the removal
+    task: Option<async_fn::AbortOnDrop>,
+    /// Overflow policy of the registration, instead of the one of the
+    /// observable
+    overflow_policy: Option<OverflowPolicy>,
+    /// Resize settings, for observers with adaptive capacity
+    adaptive: Option<adaptive::Adaptive<T>>,
+    /// Check for callbacks that pass the values to a receiver, so they
+    /// are pruned like channels
+    closed: Option<ClosedFn>,
+    /// Max number of replayed values at the registration, `None` for all
+    /// buffered ones
+    replay_limit: Option<usize>,
+    /// Number of values the observer still gets, it's removed after the
+    /// last one. `None` for no limit.
+    remaining: Option<AtomicUsize>,
+}
+
+impl<T> StoredObserver<T> {
+    pub fn new(id: ObserverId, target: Target<T>) -> Self {
+        StoredObserver {
+            target,
+            id,
+            name: None,
+            meta: std::sync::Mutex::new(None),
+            group: None,
+            weight: 1,
+            credit: AtomicU64::new(0),
+            progress: None,
+            filter: None,
+            task: None,
+            overflow_policy: None,
+            adaptive: None,
+            closed: None,
+            replay_limit: None,
+            remaining: None,
+        }
+    }
+
+    /// Returns true if the value passes the filter of the observer
+    fn accepts(&self, data: &T) -> bool {
+        match &self.filter {
+            Some(f) => f(data),
+            None => true,
+        }
+    }
+
+    /// Returns true if the observer is called within the notify call,
+    /// instead of getting the values over a channel
+    fn is_in_place(&self) -> bool {
+        matches!(self.target, Target::Callback(_) | Target::Borrowed(_))
+    }
+
+    /// Returns true if the receiver of the observer was dropped
+    fn is_closed(&self) -> bool {
+        match &self.target {
+            Target::Channel(tx) => tx.is_closed(),
+            Target::Mapped(tx) => tx.is_closed(),
+            Target::Unbounded(tx) => tx.is_closed(),
+            Target::Queue(tx) => tx.is_closed(),
+            Target::Callback(_) | Target::Borrowed(_) => self.is_closed_callback(),
+        }
+    }
+
+    /// Returns true if the observer got all values of its limit
+    fn is_exhausted(&self) -> bool {
+        self.remaining.as_ref().is_some_and(|r| r.load(Ordering::Relaxed) == 0)
+    }
+
+    /// Returns the ID together with the name for log output
+    fn label(&self) -> Label<'_> {
+        Label(self.id, self.name.as_deref())
+    }
+
+    /// Returns true if the observer is a callback, whose receiver was
+    /// dropped
+    fn is_closed_callback(&self) -> bool {
+        self.closed.as_ref().is_some_and(|closed| closed())
+    }
+
+    /// Returns the user data of the registration
+    fn meta(&self) -> std::sync::MutexGuard<'_, Option<Meta>> {
+        self.meta.lock().unwrap_or_else(|e| e.into_inner())
+    }
 }
 
-impl<T> StoredObserver<T> {
-    pub fn new(id: u32, tx: Sender<T>) -> Self {
-        StoredObserver { tx, id }
+impl<T> Debug for StoredObserver<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut s = f.debug_struct("Observer");
+        s.field("id", &self.id.value());
+        if let Some(name) = &self.name {
+            s.field("name", name);
+        }
+        if let Some(group) = &self.group {
+            s.field("group", group);
+        }
+        s.field("target", &self.target).finish()
+    }
+}
+
+/// ID and optional name of an observer, printed like `3 (exporter)`
+struct Label<'a>(ObserverId, Option<&'a str>);
+
+impl Display for Label<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.1 {
+            Some(name) => write!(f, "{} ({})", self.0, name),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+/// Counters about the notifications of an observable
+#[derive(Default)]
+struct Stats {
+    /// Number of notify calls
+    notifications: AtomicU64,
+    /// Number of values that were passed to observers
+    delivered: AtomicU64,
+    /// Number of values that could not be passed to observers
+    failed: AtomicU64,
+    /// Number of values that were dropped because of full channels
+    dropped: AtomicU64,
+}
+
+impl Debug for Stats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Stats")
+            .field("notifications", &self.notifications.load(Ordering::Relaxed))
+            .field("delivered", &self.delivered.load(Ordering::Relaxed))
+            .field("failed", &self.failed.load(Ordering::Relaxed))
+            .field("dropped", &self.dropped.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+/// Settings of an observable, they can't be changed after the creation
+#[derive(Debug)]
+struct Config {
+    /// Name used in log output and Debug dumps
+    name: Option<String>,
+    /// Capacity of the observer channels
+    capacity: usize,
+    /// Behavior for observers with a full channel
+    overflow_policy: OverflowPolicy,
+    /// Number of the latest values that are passed to new observers
+    replay_depth: usize,
+    /// Age after that replay values are no longer passed to new observers
+    replay_max_age: Option<Duration>,
+    /// Level of the log output
+    log_level: Level,
+    /// Place where the internal tasks are spawned
+    spawner: Spawner,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            name: None,
+            capacity: DEFAULT_CAPACITY,
+            overflow_policy: OverflowPolicy::default(),
+            replay_depth: 0,
+            replay_max_age: None,
+            log_level: Level::Debug,
+            spawner: Spawner::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Returns the prefix for log messages
+    fn log_prefix(&self) -> String {
+        match &self.name {
+            Some(n) => format!("{}: ", n),
+            None => String::new(),
+        }
+    }
+}
+
+/// Mutable part of an observable. Everything is behind one read-write
+/// lock. Notifications share it, registrations and other changes take it
+/// exclusively. A notification only holds it to select the observers, it's
+/// released before the values are sent, so a full channel doesn't block
+/// the registrations.
+struct State<T> {
+    /// Registered observers, a notification keeps the selected ones while
+    /// it passes a value to them
+    observers: Slab<Arc<StoredObserver<T>>>,
+    /// Latest notified values that are replayed to new observers, with
+    /// the time of their notification. It has its own lock, because it's
+    /// changed by the notifications.
+    replay: std::sync::Mutex<VecDeque<(Instant, T)>>,
+    /// Groups whose observers get no notifications at the moment
+    paused_groups: HashSet<String>,
+    /// True after the observable stopped to accept notifications
+    closed: bool,
+    /// Parent that gets the notified values
+    parent: Option<hierarchy::Link<T>>,
+    /// Children that get the notified values
+    children: Vec<hierarchy::Link<T>>,
+    /// Sender of the broadcast observers, after the first one registered
+    broadcast: Option<tokio::sync::broadcast::Sender<T>>,
+    /// Is asked before every delivery to an observer and can veto it
+    interceptor: Option<intercept::Interceptor<T>>,
+}
+
+impl<T> State<T> {
+    /// Returns true if the observer belongs to a paused group
+    fn is_paused(&self, o: &StoredObserver<T>) -> bool {
+        match &o.group {
+            Some(g) => self.paused_groups.contains(g),
+            None => false,
+        }
+    }
+
+    /// Returns true if the value should be passed to the observer now
+    fn wants(&self, o: &StoredObserver<T>, data: &T) -> bool {
+        !self.is_paused(o) && !o.is_closed_callback() && !o.is_exhausted() && o.accepts(data)
+    }
+
+    /// Returns the replay buffer, that is shared by the notifications
+    fn replay(&self) -> std::sync::MutexGuard<'_, VecDeque<(Instant, T)>> {
+        self.replay.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns true if an observer must be removed after a notification,
+    /// because its receiver was dropped or it got all values of its limit
+    fn needs_prune(&self, missed: &[ObserverId]) -> bool {
+        !missed.is_empty()
+            || self.observers.iter().any(|o| o.is_exhausted() || o.is_closed_callback())
+    }
+}
+
+impl<T> Debug for State<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("State")
+            .field("observers", &self.observers)
+            .field("replay_len", &self.replay().len())
+            .field("paused_groups", &self.paused_groups)
+            .field("closed", &self.closed)
+            .field("children", &self.children.len())
+            .finish()
+    }
+}
+
+/// Helper to print the content of an async mutex without waiting for it
+struct TryLocked<'a, T>(&'a Mutex<T>);
+
+impl<T: Debug> Debug for TryLocked<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0.try_lock() {
+            Ok(g) => g.fmt(f),
+            Err(_) => f.write_str("<locked>"),
+        }
+    }
+}
+
+/// Helper to print the content of an async read-write lock without
+/// waiting for it
+struct TryRead<'a, T>(&'a RwLock<T>);
+
+impl<T: Debug> Debug for TryRead<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0.try_read() {
+            Ok(g) => g.fmt(f),
+            Err(_) => f.write_str("<locked>"),
+        }
+    }
+}
+
+/// Async, multithreading-ready Observale that use channels instead of callbacks
+pub struct ChObservable<T: Clone> {
+    /// Registered observers and replay buffer
+    state: Arc<RwLock<State<T>>>,
+    /// Identifies this instance in the created observer IDs
+    tag: u64,
+    /// Next available observerId for registrations, it's shared by all
+    /// objects that use the same observers
+    next_id: Arc<AtomicU32>,
+    /// Settings of the observable
+    config: Arc<Config>,
+    /// Prefix of the log messages, contains the name
+    log_prefix: String,
+    /// Notification counters
+    stats: Arc<Stats>,
+    /// Observable of the registration changes, created on demand. It's
+    /// shared with the guards of `register_guarded`.
+    lifecycle: Arc<OnceLock<ChObservable<Lifecycle>>>,
+    /// True after `Lifecycle::Closed` was passed by `close`, so it isn't
+    /// passed again when the object is dropped
+    closed_event: AtomicBool,
+    /// Observable of the delivery errors, created on demand. It's shared
+    /// with the tasks that report errors while it's locked.
+    errors: Arc<OnceLock<ChObservable<Error>>>,
+    /// Returns the current value for new observers, if nothing is replayed
+    latest_fn: Option<LatestFn<T>>,
+    /// Describes the notified values in the log output
+    summarizer: Option<fn(&T) -> String>,
+    /// Last value, that is passed to the observers when the observable
+    /// is closed or dropped
+    completion: Option<T>,
+    /// True for the object that owns the observers, it closes them when
+    /// it's dropped. Objects that share the observers don't.
+    owner: bool,
+}
+
+impl<T: Clone> Debug for ChObservable<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChObservable")
+            .field("name", &self.config.name)
+            .field("capacity", &self.config.capacity)
+            .field("overflow_policy", &self.config.overflow_policy)
+            .field("replay_depth", &self.config.replay_depth)
+            .field("replay_max_age", &self.config.replay_max_age)
+            .field("state", &TryRead(&self.state))
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+impl<T: Clone> Default for ChObservable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> ChObservable<T> {
+    /// creates a new object
+    pub fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    /// Returns a builder to configure the observable before its creation
+    pub fn builder() -> ChObservableBuilder<T> {
+        ChObservableBuilder::new()
+    }
+
+    fn with_config(config: Config) -> Self {
+        let state = Arc::new(RwLock::new(State {
+            observers: Slab::new(),
+            replay: std::sync::Mutex::new(VecDeque::new()),
+            paused_groups: HashSet::new(),
+            closed: false,
+            parent: None,
+            children: Vec::new(),
+            broadcast: None,
+            interceptor: None,
+        }));
+        let mut o = Self::with_shared(
+            state,
+            Arc::new(config),
+            Arc::new(Stats::default()),
+            next_tag(),
+            Arc::new(AtomicU32::new(1)),
+        );
+        o.owner = true;
+        o
+    }
+
+    /// Creates an object that uses the observers of an existing one
+    fn with_shared(
+        state: Arc<RwLock<State<T>>>,
+        config: Arc<Config>,
+        stats: Arc<Stats>,
+        tag: u64,
+        next_id: Arc<AtomicU32>,
+    ) -> Self {
+        ChObservable {
+            state,
+            tag,
+            next_id,
+            log_prefix: config.log_prefix(),
+            config,
+            stats,
+            lifecycle: Arc::new(OnceLock::new()),
+            closed_event: AtomicBool::new(false),
+            errors: Arc::new(OnceLock::new()),
+            latest_fn: None,
+            summarizer: None,
+            completion: None,
+            owner: false,
+        }
+    }
+
+    /// Writes a log message with the configured level and the name of
+    /// the observable
+    fn log(&self, args: fmt::Arguments<'_>) {
+        log!(self.config.log_level, "{}{}", self.log_prefix, args);
+    }
+
+    /// Returns the description of a value from the summarizer, it's cut
+    /// to `MAX_SUMMARY_LEN` chars
+    fn summary(&self, data: &T) -> Option<String> {
+        let mut s = (self.summarizer?)(data);
+        if let Some((i, _)) = s.char_indices().nth(MAX_SUMMARY_LEN) {
+            s.truncate(i);
+            s.push_str("...");
+        }
+        Some(s)
+    }
+
+    /// Writes a log message about a value, with the summary of the value
+    /// if a summarizer is configured
+    fn log_value(&self, args: fmt::Arguments<'_>, data: &T) {
+        if !log::log_enabled!(self.config.log_level) {
+            return;
+        }
+        match self.summary(data) {
+            Some(s) => self.log(format_args!("{}: value={}", args, s)),
+            None => self.log(args),
+        }
+    }
+
+    async fn add_observer(&self, target: Target<T>) -> ObserverId {
+        self.add_observer_with(target, |_| ()).await
+    }
+
+    /// Registers a new observer, `configure` sets the optional properties
+    /// of the registration
+    async fn add_observer_with<F>(&self, target: Target<T>, configure: F) -> ObserverId
+    where
+        F: FnOnce(&mut StoredObserver<T>),
+    {
+        let id = self.next_observer_id();
+        let mut o = StoredObserver::new(id, target);
+        configure(&mut o);
+        let name = o.name.clone();
+        let attached = {
+            let mut g = self.state.write().await;
+            self.attach(&mut g, o)
+        };
+        if !attached {
+            self.log(format_args!("end observer at the registration: id={}", id));
+            return id;
+        }
+        self.log(format_args!("register observer: id={}", Label(id, name.as_deref())));
+        self.emit_lifecycle(Lifecycle::Subscribed(id)).await;
+        id
+    }
+
+    /// Registers a callback, that passes the values to a receiver, e.g.
+    /// a channel with another item type. `closed` returns true after the
+    /// receiver was dropped, the observer gets no values anymore then and
+    /// is pruned like an observer with a channel.
+    async fn add_forwarder<F, C>(&self, callback: F, closed: C) -> ObserverId
+    where
+        F: Fn(T) + Send + Sync + 'static,
+        C: Fn() -> bool + Send + Sync + 'static,
+    {
+        self.add_observer_with(Target::Callback(Box::new(callback)), |o| {
+            o.closed = Some(Box::new(closed))
+        })
+        .await
+    }
+
+    /// Returns a new observer ID, it doesn't need the lock of the state
+    fn next_observer_id(&self) -> ObserverId {
+        ObserverId::new(self.tag, self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Passes the replay values to a new observer and adds it to the
+    /// observers of the locked state. A closed observable gets no new
+    /// observers, it drops the observer after the replay, so its receiver
+    /// ends. The same happens to an observer, that got all values of its
+    /// limit with the replay. It returns false in these cases.
+    fn attach(&self, state: &mut State<T>, o: StoredObserver<T>) -> bool {
+        let replay = state.replay.get_mut().unwrap_or_else(|e| e.into_inner());
+        self.expire_replay(replay);
+        let latest = match &self.latest_fn {
+            Some(f) if replay.is_empty() => f(),
+            _ => None,
+        };
+        let values = replay.iter().map(|(_, v)| v).chain(latest.as_ref());
+        let replayed = Self::replay(values, &o);
+        if let Some(p) = &o.progress {
+            p.add_delivered(replayed);
+        }
+        if let Some(r) = &o.remaining {
+            r.fetch_sub(replayed as usize, Ordering::Relaxed);
+        }
+        if state.closed || o.is_exhausted() {
+            return false;
+        }
+        state.observers.insert(o.id, Arc::new(o));
+        true
+    }
+
+    /// Passes the buffered values to a new observer and returns the number
+    /// of passed values
+    fn replay<'a>(values: impl Iterator<Item = &'a T>, o: &StoredObserver<T>) -> u64
+    where
+        T: 'a,
+    {
+        let mut n = 0;
+        let mut values: Vec<&T> = values.filter(|v| o.accepts(v)).collect();
+        if let Some(limit) = o.replay_limit {
+            values.drain(..values.len().saturating_sub(limit));
+        }
+        if let Some(r) = &o.remaining {
+            values.truncate(r.load(Ordering::Relaxed));
+        }
+        match &o.target {
+            Target::Channel(tx) => {
+                let skip = values.len().saturating_sub(tx.max_capacity());
+                for &v in values.iter().skip(skip) {
+                    if tx.try_send(v.clone()).is_ok() {
+                        n += 1;
+                    }
+                }
+            }
+            Target::Callback(callback) => {
+                for &v in values.iter() {
+                    callback(v.clone());
+                    n += 1;
+                }
+            }
+            Target::Borrowed(callback) => {
+                for &v in values.iter() {
+                    callback(v);
+                    n += 1;
+                }
+            }
+            Target::Mapped(tx) => {
+                let skip = values.len().saturating_sub(tx.max_capacity());
+                for &v in values.iter().skip(skip) {
+                    if tx.try_send(v).is_ok() {
+                        n += 1;
+                    }
+                }
+            }
+            Target::Unbounded(tx) => {
+                for &v in values.iter() {
+                    if tx.send(v.clone()).is_ok() {
+                        n += 1;
+                    }
+                }
+            }
+            Target::Queue(tx) => {
+                for &v in values.iter() {
+                    if tx.send(v.clone()).is_ok() {
+                        n += 1;
+                    }
+                }
+            }
+        }
+        n
+    }
+
+    /// This function registers a new observer. It returns the ID of the registered
+    /// observer and a channel receiver to get the new values
+    ///
+    pub async fn register(&self) -> (ObserverId, Receiver<T>) {
+        let (tx, rx): (Sender<T>, Receiver<T>) = mpsc::channel(self.config.capacity);
+        let id = self.add_observer(Target::Channel(tx)).await;
+        (id, rx)
+    }
+
+    /// This function registers a new observer like `register`, that gets
+    /// at most the latest `n` values of the replay buffer before the live
+    /// values. The buffer keeps the number of values that is set by
+    /// `ChObservableBuilder::replay`, so a subscriber that starts late
+    /// isn't cold.
+    ///
+    /// ## Arguments
+    /// * `n` - max number of buffered values to pass
+    ///
+    pub async fn register_with_replay(&self, n: usize) -> (ObserverId, Receiver<T>) {
+        let (tx, rx) = mpsc::channel(self.config.capacity);
+        let id = self
+            .add_observer_with(Target::Channel(tx), |o| o.replay_limit = Some(n))
+            .await;
+        (id, rx)
+    }
+
+    /// This function registers a new observer like `register`, with a
+    /// name. The name is shown beside the ID in the log output, the
+    /// delivery reports and the Debug output.
+    ///
+    /// ## Arguments
+    /// * `name` - readable name of the observer, e.g. "metrics-exporter"
+    ///
+    pub async fn register_named(&self, name: &str) -> (ObserverId, Receiver<T>) {
+        let (tx, rx) = mpsc::channel(self.config.capacity);
+        let id = self
+            .add_observer_with(Target::Channel(tx), |o| o.name = Some(name.into()))
+            .await;
+        (id, rx)
+    }
+
+    /// This function registers a new observer, that gets only the values
+    /// that pass the predicate. The predicate is called by `notify`, so the
+    /// other values aren't cloned or sent for this observer. Replayed
+    /// values are filtered as well. It returns the ID of the registered
+    /// observer and a channel receiver to get the passed values.
+    ///
+    /// ## Arguments
+    /// * `pred` - returns true for the values the observer gets
+    ///
+    pub async fn register_filtered<F>(&self, pred: F) -> (ObserverId, Receiver<T>)
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        let (tx, rx) = mpsc::channel(self.config.capacity);
+        let id = self
+            .add_observer_with(Target::Channel(tx), |o| o.filter = Some(Box::new(pred)))
+            .await;
+        (id, rx)
+    }
+
+    /// This function registers a new observer with its own channel
+    /// capacity, e.g. for a subscriber with a high throughput. The default
+    /// capacity of the other observers is set by
+    /// `ChObservableBuilder::capacity`. It fails with `Error::Validation`
+    /// if the capacity is 0.
+    ///
+    /// ## Arguments
+    /// * `capacity` - max number of values that are buffered for the observer
+    ///
+    pub async fn register_with_capacity(
+        &self,
+        capacity: usize,
+    ) -> Result<(ObserverId, Receiver<T>)> {
+        if capacity == 0 {
+            return Err(Error::Validation(
+                "channel capacity must be greater than 0".to_string(),
+            ));
+        }
+        let (tx, rx) = mpsc::channel(capacity);
+        let id = self.add_observer(Target::Channel(tx)).await;
+        Ok((id, rx))
+    }
+
+    /// This function registers a new observer with an unbounded channel,
+    /// for bursty observers that must never block the notification. The
+    /// values are buffered without a limit, until they are received. The
+    /// values in the channel are not waited for by `drain`.
+    pub async fn register_unbounded(&self) -> (ObserverId, UnboundedReceiver<T>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let id = self.add_observer(Target::Unbounded(tx)).await;
+        (id, rx)
+    }
+
+    /// This function unregisters an observer. It fails if the ID was
+    /// created by another observable.
+    ///
+    /// ## Arguments
+    /// * `observer_id` - ID returned after the registration of an observer
+    ///
+    pub async fn unregister(&self, observer_id: ObserverId) -> Result<()> {
+        observer_id.check_tag(self.tag)?;
+        let mut g = self.state.write().await;
+        self.log(format_args!("receive unregister observer request: id={}", observer_id));
+        if let Some(o) = g.observers.remove(observer_id) {
+            self.log(format_args!("unregister observer request: id={}", o.label()));
+            drop(g);
+            self.emit_lifecycle(Lifecycle::Unsubscribed(observer_id)).await;
+        }
+        Ok(())
+    }
+
+    /// Removes all observers, whose receiver was dropped. Notify does this
+    /// only for the observers it tried to reach, this function allows
+    /// cleanups at fixed points. The lifecycle observers get
+    /// `Lifecycle::Pruned` for each of them, after the observers were
+    /// unlocked again. It returns the number of removed observers.
+    pub async fn prune_closed(&self) -> usize {
+        let g = self.state.write().await;
+        let ids: Vec<ObserverId> = g.observers.ids().collect();
+        let n = self.prune(g, &ids).await;
+        self.log(format_args!("pruned closed observers: {}", n));
+        n
+    }
+
+    /// Removes all observers, e.g. on shutdown or for a reconfiguration.
+    /// Their channels are closed, so the receivers get `None` after the
+    /// queued values. The broadcast channel is closed as well. The
+    /// observable keeps accepting registrations and notifications. It
+    /// returns the number of removed observers, without the broadcast
+    /// observers.
+    pub async fn clear(&self) -> usize {
+        let ids: Vec<ObserverId> = {
+            let mut g = self.state.write().await;
+            g.broadcast.take();
+            g.observers.drain().map(|(id, _)| id).collect()
+        };
+        self.log(format_args!("removed all observers: {}", ids.len()));
+        for id in &ids {
+            self.emit_lifecycle(Lifecycle::Unsubscribed(*id)).await;
+        }
+        ids.len()
+    }
+
+    /// Returns the number of registered observers. Producers can use it
+    /// to skip building a value that nobody gets. Observers whose receiver
+    /// was dropped are not counted, even before they are pruned.
+    pub async fn observer_count(&self) -> usize {
+        let g = self.state.read().await;
+        g.observers.iter().filter(|o| !o.is_closed()).count()
+    }
+
+    /// Returns the IDs of the registered observers in the order of
+    /// registration, e.g. for admin or debug tools. Like `observer_count`
+    /// it skips the observers whose receiver was dropped.
+    pub async fn observer_ids(&self) -> Vec<ObserverId> {
+        let g = self.state.read().await;
+        g.observers.iter().filter(|o| !o.is_closed()).map(|o| o.id).collect()
+    }
+
+    /// Returns the IDs of the registered observers like `observer_ids`,
+    /// together with the names of the ones from `register_named`
+    pub async fn observer_names(&self) -> Vec<(ObserverId, Option<String>)> {
+        let g = self.state.read().await;
+        g.observers
+            .iter()
+            .filter(|o| !o.is_closed())
+            .map(|o| (o.id, o.name.as_deref().map(String::from)))
+            .collect()
+    }
+
+    /// Returns true if no observer is registered, see `observer_count`
+    pub async fn is_empty(&self) -> bool {
+        self.observer_count().await == 0
+    }
+
+    /// Returns the overflow policy, that is used for an observer
+    fn policy(&self, o: &StoredObserver<T>) -> OverflowPolicy {
+        o.overflow_policy.unwrap_or(self.config.overflow_policy)
+    }
+
+    /// Passes a value into the channel of an observer, with respect to
+    /// the overflow policy. It returns false if the value was dropped.
+    /// Observers without a channel get the value without waiting.
+    async fn send(&self, o: &StoredObserver<T>, data: &T) -> Result<bool> {
+        if !matches!(self.policy(o), OverflowPolicy::Block | OverflowPolicy::WeightedFair) {
+            return self.try_deliver(o, data);
+        }
+        let res = match &o.target {
+            Target::Channel(tx) => tx.send(data.clone()).await.map_err(|_| ()),
+            Target::Mapped(tx) => tx.send(data).await,
+            _ => return self.try_deliver(o, data),
+        };
+        self.sent(o, res.map_err(|()| Error::Delivery { observer_id: o.id }))
+    }
+
+    /// Counts the result of a send without waiting. It returns false if
+    /// the value was dropped for a full channel, with
+    /// `OverflowPolicy::Error` a full channel fails.
+    fn try_sent(
+        &self,
+        o: &StoredObserver<T>,
+        res: std::result::Result<(), TrySendError<()>>,
+    ) -> Result<bool> {
+        let id = o.id;
+        let label = o.label();
+        let res = match res {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Closed(_)) => Err(Error::Delivery { observer_id: id }),
+            Err(TrySendError::Full(_)) if self.policy(o) == OverflowPolicy::Error => {
+                Err(Error::Overflow { observer_id: id })
+            }
+            Err(TrySendError::Full(_)) => {
+                self.log(format_args!("channel is full, drop value: id={}", label));
+                self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+                return Ok(false);
+            }
+        };
+        self.sent(o, res)
+    }
+
+    /// Counts the result of a send and passes errors to the error receivers
+    fn sent(&self, o: &StoredObserver<T>, res: Result<()>) -> Result<bool> {
+        match &res {
+            Ok(()) => self.delivered(o),
+            Err(e) => {
+                self.stats.failed.fetch_add(1, Ordering::Relaxed);
+                self.report_error(e);
+            }
+        };
+        res.map(|()| true)
+    }
+
+    /// Triggers the notification of the restistered observers. All
+    /// observers are tried, also after the delivery to one of them failed.
+    /// The returned report lists the observers whose receiver was dropped
+    /// or whose channel was full with `OverflowPolicy::Error` as failed,
+    /// the errors are passed to the `errors` receivers as well. Observers
+    /// with a dropped receiver are unregistered afterwards. It fails with
+    /// `Error::Closed` after `drain` was called.
+    ///
+    /// ## Arguments
+    /// * `data` - data that should be passed to the observers
+    pub async fn notify(&self, data: &T) -> Result<DeliveryReport> {
+        self.notify_in_passes(data, &[&|_| true]).await
+    }
+
+    /// Notifies the observers in one pass per selection, the selections
+    /// are served in the given order
+    async fn notify_in_passes(
+        &self,
+        data: &T,
+        passes: &[&Select<'_, T>],
+    ) -> Result<DeliveryReport> {
+        self.log_value(format_args!("received notify request"), data);
+        let state = self.lock_for_notify().await;
+        if state.closed {
+            return Err(Error::Closed);
+        }
+        Ok(self.notify_locked(state, data, passes, SendStrategy::Wait).await)
+    }
+
+    /// Notifies the observers, that are selected under the given lock, all
+    /// notify functions use it. The observers are served pass by pass, the strategy decides
+    /// how long a full channel is waited for. Observers with a dropped
+    /// receiver are removed afterwards, then the linked observables get the
+    /// value, with `SendStrategy::Until` until the deadline. It doesn't check
+    /// if the observable is closed.
+    async fn notify_locked(
+        &self,
+        state: RwLockReadGuard<'_, State<T>>,
+        data: &T,
+        passes: &[&Select<'_, T>],
+        strategy: SendStrategy,
+    ) -> DeliveryReport {
+        let (report, parent, children) = self.deliver_passes(state, data, passes, strategy).await;
+        if parent.is_some() || !children.is_empty() {
+            let propagated = self.propagate(data, parent, children);
+            match strategy {
+                SendStrategy::Until(deadline) => {
+                    if timeout_at(deadline, propagated).await.is_err() {
+                        self.log(format_args!("deadline reached before the linked observables"));
+                    }
+                }
+                SendStrategy::Wait | SendStrategy::Timeout(_) => propagated.await,
+            }
+        }
+        self.log(format_args!(
+            "notified: delivered={}, skipped={}, failed={}",
+            report.delivered.len(),
+            report.skipped.len(),
+            report.failed.len()
+        ));
+        report
+    }
+
+    /// Passes a value to the observers of the passes, without the linked
+    /// observables, that are returned. The observers are selected under the
+    /// given lock, it's released before the first value is sent.
+    async fn deliver_passes(
+        &self,
+        state: RwLockReadGuard<'_, State<T>>,
+        data: &T,
+        passes: &[&Select<'_, T>],
+        strategy: SendStrategy,
+    ) -> (DeliveryReport, Option<hierarchy::Link<T>>, Vec<hierarchy::Link<T>>) {
+        let (parent, children) = self.start_notify(&state, data);
+        let selected: Vec<Vec<Arc<StoredObserver<T>>>> = passes
+            .iter()
+            .map(|select| self.select(&state, data, select))
+            .collect();
+        drop(state);
+        let mut report = DeliveryReport::default();
+        for targets in selected {
+            report.append(self.deliver_selected(&targets, data, strategy).await);
+        }
+        self.prune_after(&report.failed).await;
+        (report, parent, children)
+    }
+
+    /// Starts a notification under the lock. It's counted, the value is
+    /// kept for replay and passed to the broadcast observers. It returns
+    /// the linked observables, that get the value after the observers.
+    fn start_notify(
+        &self,
+        state: &State<T>,
+        data: &T,
+    ) -> (Option<hierarchy::Link<T>>, Vec<hierarchy::Link<T>>) {
+        self.log(format_args!("start to notify ..."));
+        self.stats.notifications.fetch_add(1, Ordering::Relaxed);
+        self.push_replay(state, data);
+        self.broadcast(state, data);
+        (state.parent.clone(), state.children.clone())
+    }
+
+    /// Notifies the observers like `notify_locked`, but without waiting.
+    /// It returns the linked observables, that get the value afterwards.
+    fn notify_now_locked(
+        &self,
+        state: &mut State<T>,
+        data: &T,
+    ) -> (DeliveryReport, Option<hierarchy::Link<T>>, Vec<hierarchy::Link<T>>) {
+        let (parent, children) = self.start_notify(state, data);
+        self.adapt(state);
+        (self.deliver_now(state, data), parent, children)
+    }
+
+    /// Locks the observers for a notification. Notifications share the
+    /// lock, only the resize of adaptive channels needs it exclusively
+    /// before the delivery.
+    async fn lock_for_notify(&self) -> RwLockReadGuard<'_, State<T>> {
+        let state = self.state.read().await;
+        if !state.observers.iter().any(|o| o.adaptive.is_some()) {
+            return state;
+        }
+        drop(state);
+        let mut state = self.state.write().await;
+        self.adapt(&mut state);
+        state.downgrade()
+    }
+
+    /// Returns the selected observers, that aren't paused and should get
+    /// the value
+    fn select(
+        &self,
+        state: &State<T>,
+        data: &T,
+        select: &Select<'_, T>,
+    ) -> Vec<Arc<StoredObserver<T>>> {
+        state
+            .observers
+            .iter()
+            .filter(|o| select(o) && self.admits(state, o, data))
+            .cloned()
+            .collect()
+    }
+
+    /// Passes a value to the selected observers. With `SendStrategy::Until`
+    /// the observers are skipped after the deadline. Only
+    /// `SendStrategy::Wait` serves the observers by their weights with
+    /// `OverflowPolicy::WeightedFair`, the others keep the order of
+    /// registration.
+    async fn deliver_selected(
+        &self,
+        targets: &[Arc<StoredObserver<T>>],
+        data: &T,
+        strategy: SendStrategy,
+    ) -> DeliveryReport {
+        if matches!(strategy, SendStrategy::Wait)
+            && self.config.overflow_policy == OverflowPolicy::WeightedFair
+        {
+            return self.deliver_fair(targets, data).await;
+        }
+        let mut report = DeliveryReport::default();
+        for o in targets {
+            if let SendStrategy::Until(deadline) = strategy {
+                if Instant::now() >= deadline {
+                    report.push_skipped(o);
+                    continue;
+                }
+            }
+            report.push_result(o, &self.deliver(o, data, strategy).await);
+        }
+        report
+    }
+
+    /// Removes the observers after a notification, see `prune`. The lock
+    /// is only taken exclusively, if there is something to remove.
+    async fn prune_after(&self, missed: &[ObserverId]) {
+        if !self.state.read().await.needs_prune(missed) {
+            return;
+        }
+        self.prune(self.state.write().await, missed).await;
+    }
+
+    /// Removes the observers, whose receiver was dropped, after a value
+    /// couldn't be passed to them. The lifecycle observers get
+    /// `Lifecycle::Pruned` for each of them, after the lock was released.
+    /// It returns the number of removed observers.
+    async fn prune(
+        &self,
+        mut state: RwLockWriteGuard<'_, State<T>>,
+        missed: &[ObserverId],
+    ) -> usize {
+        let pruned = self.remove_closed(&mut state, missed);
+        let exhausted = self.remove_exhausted(&mut state);
+        drop(state);
+        for &id in pruned.iter() {
+            // boxed, because the lifecycle observable is notified by the
+            // same code
+            Box::pin(self.emit_lifecycle(Lifecycle::Pruned(id))).await;
+        }
+        for id in exhausted {
+            Box::pin(self.emit_lifecycle(Lifecycle::Unsubscribed(id))).await;
+        }
+        pruned.len()
+    }
+
+    /// Removes the observers like `prune`, but passes the lifecycle events
+    /// without waiting
+    fn prune_now(&self, state: &mut State<T>, missed: &[ObserverId]) -> usize {
+        let pruned = self.remove_closed(state, missed);
+        let exhausted = self.remove_exhausted(state);
+        if let Some(l) = self.lifecycle.get() {
+            for &id in pruned.iter() {
+                l.notify_now(&Lifecycle::Pruned(id));
+            }
+            for id in exhausted {
+                l.notify_now(&Lifecycle::Unsubscribed(id));
+            }
+        }
+        pruned.len()
+    }
+
+    /// Removes the observers with a dropped receiver out of the given ones
+    /// and returns their IDs. Callbacks with a closed check are checked in
+    /// any case, because they are skipped by the delivery.
+    fn remove_closed(&self, state: &mut State<T>, missed: &[ObserverId]) -> Vec<ObserverId> {
+        let mut pruned = Vec::new();
+        if missed.is_empty() && state.observers.iter().all(|o| o.closed.is_none()) {
+            return pruned;
+        }
+        state.observers.retain(|o| {
+            let closed = (missed.contains(&o.id) || o.closed.is_some()) && o.is_closed();
+            if closed {
+                self.log(format_args!("prune observer with dropped receiver: id={}", o.label()));
+                self.report_error(&Error::Pruned { observer_id: o.id });
+                pruned.push(o.id);
+            }
+            !closed
+        });
+        pruned
+    }
+
+    /// Passes a value to one observer, the strategy decides how long it
+    /// waits for a full channel. It returns false if the value was dropped.
+    /// The value is counted in the stats, failures are passed to the error
+    /// receivers.
+    async fn deliver(
+        &self,
+        o: &StoredObserver<T>,
+        data: &T,
+        strategy: SendStrategy,
+    ) -> Result<bool> {
+        let deadline = match strategy {
+            SendStrategy::Wait => return self.send(o, data).await,
+            SendStrategy::Until(deadline) => deadline,
+            SendStrategy::Timeout(timeout) => Instant::now() + timeout,
+        };
+        match timeout_at(deadline, self.send(o, data)).await {
+            Ok(res) => res,
+            Err(_) => {
+                self.log(format_args!("send timed out, skip observer: id={}", o.label()));
+                self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+                self.report_error(&Error::SendTimeout { observer_id: o.id });
+                Ok(false)
+            }
+        }
+    }
+
+    /// Passes a value to one observer without waiting, see `deliver`. A
+    /// full channel is handled like with `OverflowPolicy::DropNewest`, with
+    /// `OverflowPolicy::Error` it fails.
+    fn try_deliver(&self, o: &StoredObserver<T>, data: &T) -> Result<bool> {
+        match &o.target {
+            Target::Channel(tx) => self.try_sent(o, tx.try_send(data.clone()).map_err(discard)),
+            Target::Mapped(tx) => self.try_sent(o, tx.try_send(data)),
+            Target::Callback(callback) => {
+                callback(data.clone());
+                self.delivered(o);
+                Ok(true)
+            }
+            Target::Borrowed(callback) => {
+                callback(data);
+                self.delivered(o);
+                Ok(true)
+            }
+            Target::Unbounded(tx) => self.send_unbounded(o, tx, data).map(|()| true),
+            Target::Queue(tx) => self.send_queue(o, tx, data).map(|()| true),
+        }
+    }
+
+    /// Passes a value into the unbounded channel of an observer
+    fn send_unbounded(
+        &self,
+        o: &StoredObserver<T>,
+        tx: &UnboundedSender<T>,
+        data: &T,
+    ) -> Result<()> {
+        if tx.send(data.clone()).is_err() {
+            let e = Error::Delivery { observer_id: o.id };
+            self.stats.failed.fetch_add(1, Ordering::Relaxed);
+            self.report_error(&e);
+            return Err(e);
+        }
+        self.delivered(o);
+        Ok(())
+    }
+
+    /// Passes a value into the queue of an observer, a value that is
+    /// dropped for it is counted. It fails if the receiver was dropped.
+    fn push_queue(&self, tx: &queue::QueueSender<T>, data: &T) -> std::result::Result<(), ()> {
+        if tx.send(data.clone()).map_err(|_| ())? {
+            self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    /// Passes a value into the queue of an observer and counts the result
+    fn send_queue(
+        &self,
+        o: &StoredObserver<T>,
+        tx: &queue::QueueSender<T>,
+        data: &T,
+    ) -> Result<()> {
+        if self.push_queue(tx, data).is_err() {
+            let e = Error::Delivery { observer_id: o.id };
+            self.stats.failed.fetch_add(1, Ordering::Relaxed);
+            self.report_error(&e);
+            return Err(e);
+        }
+        self.delivered(o);
+        Ok(())
     }
-}
 
-/// Async, multithreading-ready Observale that use channels instead of callbacks
-pub struct ChObservable<T: Clone> {
-    /// Registered bservers
-    observers: Arc<Mutex<Vec<StoredObserver<T>>>>,
-    /// Next available observerId for registrations
-    next_id: u32,
-}
+    /// Counts a value that was passed to an observer
+    fn delivered(&self, o: &StoredObserver<T>) {
+        self.stats.delivered.fetch_add(1, Ordering::Relaxed);
+        if let Some(p) = &o.progress {
+            p.add_delivered(1);
+        }
+        if let Some(r) = &o.remaining {
+            let _ = r.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+        }
+    }
 
-impl<T: Clone + Debug> Debug for ChObservable<T> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.debug_struct("ChObservable")
-            .field("observers", &self.observers)
-            .field("next_id", &self.next_id)
-            .finish()
+    /// Keeps the value for observers that are registered later
+    fn push_replay(&self, state: &State<T>, data: &T) {
+        if self.config.replay_depth > 0 {
+            let mut replay = state.replay();
+            if replay.len() == self.config.replay_depth {
+                replay.pop_front();
+            }
+            replay.push_back((Instant::now(), data.clone()));
+            self.expire_replay(&mut replay);
+        }
     }
-}
 
-impl<T: Clone> ChObservable<T> {
-    pub fn new() -> Self {
-        /// creates a new object
-        ChObservable {
-            observers: Arc::new(Mutex::new(Vec::new())),
-            next_id: 1,
+    /// Removes the replay values that are older than the configured max age
+    fn expire_replay(&self, replay: &mut VecDeque<(Instant, T)>) {
+        if let Some(max_age) = self.config.replay_max_age {
+            let now = Instant::now();
+            while replay.front().is_some_and(|(t, _)| now - *t > max_age) {
+                replay.pop_front();
+            }
         }
     }
 
-    /// This function registers a new observer. It returns the ID of the registered
-    /// observer and a channel receiver to get the new values
-    ///
-    pub async fn register(&mut self) -> (u32, Receiver<T>) {
-        let mut g = self.observers.lock().await;
-        let observers: &mut Vec<StoredObserver<T>> = &mut g;
-        let id = self.next_id;
-        self.next_id += 1;
-        let (tx, rx): (Sender<T>, Receiver<T>) = mpsc::channel(10);
-        observers.push(StoredObserver::new(id, tx));
-        debug!("register observer: id={}", id);
-        (id, rx)
+    /// Passes a value to the observers without waiting, like `try_notify`,
+    /// it's used for the side channels. Nothing is passed if the observers
+    /// are locked at the moment.
+    fn notify_now(&self, data: &T) {
+        let _ = self.try_notify(data);
     }
+}
 
-    /// This function unregisters an observer.
-    ///
-    /// ## Arguments
-    /// * `observer_id` - ID returned after the registration of an observer
-    ///
-    pub async fn unregister(&mut self, observer_id: u32) {
-        let mut g = self.observers.lock().await;
-        let observers: &mut Vec<StoredObserver<T>> = &mut g;
-        let mut found: Option<usize> = None;
-        debug!("receive unregister observer request: id={}", observer_id);
-        for (i, e) in observers.iter().enumerate() {
-            if e.id == observer_id {
-                found = Some(i);
-                break;
+/// Closes the observers of the owning object, so every receiver ends
+/// after its queued values and the completion value. If the observers are
+/// locked by a notification of a linked observable at the moment, they
+/// end with the last reference to them instead.
+impl<T: Clone> Drop for ChObservable<T> {
+    fn drop(&mut self) {
+        if self.owner {
+            if let Ok(mut g) = self.state.try_write() {
+                if let Some(v) = self.completion.as_ref().filter(|_| !g.closed) {
+                    self.deliver_now(&mut g, v);
+                }
+                g.closed = true;
+                g.observers.drain().for_each(drop);
             }
         }
-        if let Some(index_to_remove) = found {
-            debug!("unregister observer request: id={}", observer_id);
-            observers.remove(index_to_remove);
+        if let Some(l) = self.lifecycle.get().filter(|_| !*self.closed_event.get_mut()) {
+            l.notify_now(&Lifecycle::Closed);
         }
     }
+}
 
-    /// Triggers the notification of the restistered observers.
+impl<T: Clone + Send + 'static> ChObservable<T> {
+    /// This function registers a callback as observer, that is called inline
+    /// within the notify call. It returns the ID of the registered observer.
     ///
     /// ## Arguments
-    /// * `data` - data that should be passed to the observers
-    pub async fn notify(&self, data: &T) -> Result<(), SendError<T>> {
-        debug!("received notify request");
-        let mut g = self.observers.lock().await;
-        let observers: &mut Vec<StoredObserver<T>> = &mut g;
-        debug!("start to notify ...");
-        for o in observers {
-            o.tx.send(data.clone()).await?;
-        }
-        debug!("notified.");
-        Ok(())
+    /// * `callback` - function that is called with every notified value
+    ///
+    pub async fn register_callback<F>(&self, callback: F) -> ObserverId
+    where
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        self.register_callback_on(InlineExecutor, callback).await
+    }
+
+    /// This function registers a callback as observer, that is called by the
+    /// given executor. It returns the ID of the registered observer.
+    ///
+    /// ## Arguments
+    /// * `executor` - decides where the callback is executed, e.g. inline, as
+    ///   tokio task or in the queue of the UI thread
+    /// * `callback` - function that is called with every notified value
+    ///
+    pub async fn register_callback_on<E, F>(&self, executor: E, callback: F) -> ObserverId
+    where
+        E: NotifyExecutor + 'static,
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        let callback = Arc::new(callback);
+        let dispatch = move |data: T| {
+            let callback = callback.clone();
+            executor.execute(Box::new(move || callback(data)));
+        };
+        self.add_observer(Target::Callback(Box::new(dispatch))).await
     }
 }
 
@@ -106,6 +1515,32 @@ pub struct ChObservedValue<T: Clone> {
     value: Arc<Mutex<Option<T>>>,
     /// Observable implementation
     observable: Arc<Mutex<ChObservable<Option<T>>>>,
+    /// Timed changes of the value, if the history is enabled
+    history: Option<Arc<std::sync::Mutex<History<Instant, T>>>>,
+    /// Poison marker and observers of the state
+    status: Arc<poison::Status<T>>,
+    /// Replaces `PartialEq` in `set_value_if_changed`, if it's set
+    comparator: Option<Comparator<T>>,
+}
+
+impl<T: Clone> Debug for ChObservedValue<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let is_set: &dyn Debug = match self.value.try_lock() {
+            Ok(v) if v.is_some() => &true,
+            Ok(_) => &false,
+            Err(_) => &"<locked>",
+        };
+        f.debug_struct("ChObservedValue")
+            .field("is_set", is_set)
+            .field("observable", &TryLocked(&self.observable))
+            .finish()
+    }
+}
+
+impl<T: Clone> Default for ChObservedValue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T: Clone> ChObservedValue<T> {
@@ -114,73 +1549,326 @@ impl<T: Clone> ChObservedValue<T> {
         ChObservedValue {
             observable: Arc::new(Mutex::new(ChObservable::<Option<T>>::new())),
             value: Arc::new(Mutex::new(None)),
+            history: None,
+            status: Arc::new(poison::Status::new()),
+            comparator: None,
         }
     }
 
+    /// Creates an new object, that starts with a value. No observer is
+    /// notified.
+    ///
+    /// ## Arguments
+    /// * `v` - initial value
+    ///
+    pub fn with_value(v: T) -> Self {
+        ChObservedValue {
+            value: Arc::new(Mutex::new(Some(v))),
+            ..Self::new()
+        }
+    }
 
-    async fn set_value_impl(&mut self, v: Option<T>) {
-        let mut g = self.value.lock().await;
-        let o: &mut Option<T> = &mut g;
-        *o = v;
+    /// Returns a second handle to the same value, it doesn't keep the
+    /// history
+    #[cfg(any(test, feature = "single"))]
+    fn handle(&self) -> Self {
+        ChObservedValue {
+            value: self.value.clone(),
+            observable: self.observable.clone(),
+            history: None,
+            status: self.status.clone(),
+            comparator: self.comparator.clone(),
+        }
     }
 
-    async fn notify_impl(&mut self, v: &Option<T>) {
-        let mut g = self.observable.lock().await;
-        let o: &mut ChObservable<Option<T>> = &mut g;
-        let _ = o.notify(v).await;
+    /// Sets the function, that decides in `set_value_if_changed` and
+    /// `compare_and_set` if two values are equal, e.g. to ignore small
+    /// changes of a float. It replaces the `PartialEq` implementation of
+    /// the value. Values without `PartialEq` pass the function to
+    /// `set_value_if_changed_with` and `compare_and_set_with` instead.
+    ///
+    /// ## Arguments
+    /// * `eq` - returns true if both values are seen as equal
+    ///
+    pub fn set_comparator<F>(&mut self, eq: F)
+    where
+        F: Fn(&T, &T) -> bool + Send + Sync + 'static,
+    {
+        self.comparator = Some(Arc::new(eq));
+    }
+
+
+    /// Locks the observers before the value is unlocked. The value is
+    /// always locked first, so a registration that reads the value either
+    /// sees it before a change and gets notified about it, or sees it
+    /// after the change and doesn't get it twice.
+    async fn hand_over(
+        &self,
+        value: MutexGuard<'_, Option<T>>,
+    ) -> MutexGuard<'_, ChObservable<Option<T>>> {
+        let observable = self.observable.lock().await;
+        drop(value);
+        observable
+    }
+
+    /// Returns the current value together with the locked observers, see
+    /// `hand_over`
+    async fn lock_current(&self) -> (Option<T>, MutexGuard<'_, ChObservable<Option<T>>>) {
+        let value = self.value.lock().await;
+        let current = value.clone();
+        (current, self.hand_over(value).await)
+    }
+
+    async fn set_value_impl(&self, v: Option<T>) -> MutexGuard<'_, ChObservable<Option<T>>> {
+        let mut value = self.value.lock().await;
+        *value = v;
+        self.hand_over(value).await
     }
 
     /// Set a new value to the object. All registered observers are
-    /// called to get notified.
+    /// called to get notified. The value is set in any case, the
+    /// notification fails like `ChObservable::notify`.
     ///
     /// ## Arguments
     /// * `v` - value to set
     ///
-    pub async fn set_value(&mut self, v: &T) {
+    pub async fn set_value(&mut self, v: &T) -> Result<DeliveryReport> {
         let new_v = Some(v.clone());
-        self.set_value_impl(new_v.clone()).await;
-        self.notify_impl(&new_v).await;
+        let report = self.set_value_impl(new_v.clone()).await.notify(&new_v).await?;
+        self.restore(ValueState::Set(v.clone())).await?;
+        Ok(report)
     }
 
     /// Reset the value of the object. All registered observers are
-    /// called to get notified.
+    /// called to get notified. The value is reset in any case, the
+    /// notification fails like `ChObservable::notify`.
     ///
-    pub async fn reset_value(&mut self) {
+    pub async fn reset_value(&mut self) -> Result<DeliveryReport> {
         let new_v = None;
-        self.set_value_impl(None).await;
-        self.notify_impl(&new_v).await;
+        let report = self.set_value_impl(None).await.notify(&new_v).await?;
+        self.restore(ValueState::Unset).await?;
+        Ok(report)
+    }
+
+    /// Changes the value with a closure and notifies the observers with
+    /// the result. The closure runs under the value lock, so no other
+    /// change can happen between the read and the write. The value can
+    /// also be set or reset by the closure. The value is changed in any
+    /// case, the notification fails like `ChObservable::notify`.
+    ///
+    /// ## Arguments
+    /// * `f` - closure that changes the current value
+    ///
+    pub async fn update<F: FnOnce(&mut Option<T>)>(&mut self, f: F) -> Result<DeliveryReport> {
+        let mut current = self.value.lock().await;
+        f(&mut current);
+        let new_v = current.clone();
+        let report = self.hand_over(current).await.notify(&new_v).await?;
+        self.restore(new_v.map_or(ValueState::Unset, ValueState::Set)).await?;
+        Ok(report)
+    }
+
+    /// Sets a new value like `set_value_if_changed`, but compares the values
+    /// with the given function. It needs no `PartialEq` and ignores the
+    /// function of `set_comparator`.
+    ///
+    /// ## Arguments
+    /// * `v` - value to set
+    /// * `eq` - returns true if both values are seen as equal
+    ///
+    pub async fn set_value_if_changed_with<F>(&mut self, v: &T, eq: F) -> Result<bool>
+    where
+        F: Fn(&T, &T) -> bool,
+    {
+        let mut current = self.value.lock().await;
+        if current.as_ref().is_some_and(|c| eq(c, v)) {
+            return Ok(false);
+        }
+        *current = Some(v.clone());
+        self.hand_over(current).await.notify(&Some(v.clone())).await?;
+        self.restore(ValueState::Set(v.clone())).await?;
+        Ok(true)
+    }
+
+    /// Sets a new value like `compare_and_set`, but compares the values
+    /// with the given function. It needs no `PartialEq` and ignores the
+    /// function of `set_comparator`.
+    ///
+    /// ## Arguments
+    /// * `expected` - value that must be set currently
+    /// * `new` - value to set
+    /// * `eq` - returns true if both values are seen as equal
+    ///
+    pub async fn compare_and_set_with<F>(
+        &mut self,
+        expected: &T,
+        new: T,
+        eq: F,
+    ) -> std::result::Result<(), T>
+    where
+        F: Fn(&T, &T) -> bool,
+    {
+        let mut current = self.value.lock().await;
+        if !current.as_ref().is_some_and(|c| eq(c, expected)) {
+            return Err(new);
+        }
+        *current = Some(new.clone());
+        let res = match self.hand_over(current).await.notify(&Some(new.clone())).await {
+            Ok(_) => self.restore(ValueState::Set(new)).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = res {
+            warn!("notification after compare_and_set failed: {}", e);
+        }
+        Ok(())
     }
 
     /// This function registers a new observer. It returns the ID of the registered
     /// observer and a channel receiver to get the new values
     ///
-    pub async fn register(&mut self) -> (u32, Receiver<Option<T>>) {
+    pub async fn register(&mut self) -> (ObserverId, Receiver<Option<T>>) {
         let mut g = self.observable.lock().await;
         let o: &mut ChObservable<Option<T>> = &mut g;
         o.register().await
     }
 
-    /// This function unregisters an observer.
+    /// This function registers a new observer, whose receiver gets the
+    /// current value as first message, `None` if the value isn't set. The
+    /// value is read before the observers are locked, in the same lock
+    /// order as `set_value`, so a change is either in the first message or
+    /// notified after it, but never both.
+    pub async fn register_with_current(&self) -> (ObserverId, Receiver<Option<T>>) {
+        let (current, observable) = self.lock_current().await;
+        let (tx, rx) = mpsc::channel(observable.config.capacity);
+        let _ = tx.try_send(current);
+        let id = observable.add_observer(Target::Channel(tx)).await;
+        (id, rx)
+    }
+
+    /// This function unregisters an observer. It fails if the ID was
+    /// created by another observable.
     ///
     /// ## Arguments
     /// * `observer_id` - ID returned after the registration of an observer
     ///
-    pub async fn unregister(&mut self, observer_id: u32) {
+    pub async fn unregister(&mut self, observer_id: ObserverId) -> Result<()> {
         let mut g = self.observable.lock().await;
         let o: &mut ChObservable<Option<T>> = &mut g;
-        o.unregister(observer_id).await;
+        o.unregister(observer_id).await
     }
 
-    /// Returns a reference to the contained value
-    pub fn value_ref(&self) -> &Arc<Mutex<Option<T>>> {
-        &self.value
+    /// Returns the number of registered observers, see
+    /// `ChObservable::observer_count`
+    pub async fn observer_count(&self) -> usize {
+        self.observable.lock().await.observer_count().await
     }
 
-    /// Returns a mutable reference to the contained value
-    pub fn value_mutref(&mut self) -> &mut Arc<Mutex<Option<T>>> {
-        &mut self.value
+    /// Returns the IDs of the registered observers, see
+    /// `ChObservable::observer_ids`
+    pub async fn observer_ids(&self) -> Vec<ObserverId> {
+        self.observable.lock().await.observer_ids().await
+    }
+
+    /// Returns a copy of the current value, `None` if it isn't set. Other
+    /// than `value` it doesn't check the poison marker.
+    pub async fn get(&self) -> Option<T> {
+        self.value.lock().await.clone()
+    }
+
+    /// Returns true if a value is set
+    pub async fn is_set(&self) -> bool {
+        self.value.lock().await.is_some()
+    }
+
+    /// Returns a reference to the contained value. It fails with
+    /// `Error::Poisoned` if the value is marked as invalid. To read the
+    /// value, `get` doesn't need to handle the lock.
+    pub fn value_ref(&self) -> Result<&Arc<Mutex<Option<T>>>> {
+        self.check_poison()?;
+        Ok(&self.value)
+    }
+
+    /// Returns a mutable reference to the contained value. It fails with
+    /// `Error::Poisoned` if the value is marked as invalid.
+    pub fn value_mutref(&mut self) -> Result<&mut Arc<Mutex<Option<T>>>> {
+        self.check_poison()?;
+        Ok(&mut self.value)
+    }
+
+}
+
+impl<T: Clone + Default + Add<Output = T> + Sub<Output = T>> ChObservedValue<T> {
+    /// Adds to the value and notifies the observers like `update`, for counters and
+    /// gauges. An unset value counts as the default value. It returns the
+    /// new value.
+    ///
+    /// ## Arguments
+    /// * `by` - amount to add
+    ///
+    pub async fn increment(&mut self, by: T) -> Result<T> {
+        self.step(|v| v + by).await
+    }
+
+    /// Subtracts from the value and notifies the observers, like
+    /// `increment`. It returns the new value.
+    ///
+    /// ## Arguments
+    /// * `by` - amount to subtract
+    ///
+    pub async fn decrement(&mut self, by: T) -> Result<T> {
+        self.step(|v| v - by).await
+    }
+
+    async fn step<F: FnOnce(T) -> T>(&mut self, f: F) -> Result<T> {
+        let mut new_v = None;
+        self.update(|v| {
+            let n = f(v.take().unwrap_or_default());
+            *v = Some(n.clone());
+            new_v = Some(n);
+        })
+        .await?;
+        Ok(new_v.expect("update runs the closure"))
+    }
+}
+
+impl<T: Clone + PartialEq> ChObservedValue<T> {
+    /// Returns the comparator, or `PartialEq` if none is set
+    fn equality(&self) -> impl Fn(&T, &T) -> bool {
+        let comparator = self.comparator.clone();
+        move |a, b| match &comparator {
+            Some(eq) => eq(a, b),
+            None => a == b,
+        }
+    }
+
+    /// Sets a new value like `set_value`, but only if it differs from the
+    /// current one, see `set_comparator`. The comparison and the change are
+    /// done under the same lock. It returns true if the value was set and the observers were
+    /// notified.
+    ///
+    /// ## Arguments
+    /// * `v` - value to set
+    ///
+    pub async fn set_value_if_changed(&mut self, v: &T) -> Result<bool> {
+        let eq = self.equality();
+        self.set_value_if_changed_with(v, eq).await
     }
 
+    /// Sets a new value only if the current value equals `expected`, so
+    /// several tasks can change the value without another lock. An unset
+    /// value matches no expected value. The values are compared like in
+    /// `set_value_if_changed`, see `set_comparator`. It returns the new
+    /// value back if the current one differs. A failed notification doesn't undo the
+    /// change, it's only logged.
+    ///
+    /// ## Arguments
+    /// * `expected` - value that must be set currently
+    /// * `new` - value to set
+    ///
+    pub async fn compare_and_set(&mut self, expected: &T, new: T) -> std::result::Result<(), T> {
+        let eq = self.equality();
+        self.compare_and_set_with(expected, new, eq).await
+    }
 }
 
 #[cfg(test)]
@@ -191,29 +1879,29 @@ mod tests {
     use tokio::task::JoinHandle;
     use tokio::sync::mpsc::Receiver;
 
-    use crate::chobservable::{ChObservable, ChObservedValue};
+    use crate::chobservable::{ChObservable, ChObservedValue, Lifecycle};
+    use crate::observer_id::ObserverId;
 
     #[derive(Debug)]
     struct ObserverObj {
         pub v: Arc<Mutex<Option<String>>>,
         observable: Arc<Mutex<ChObservable<String>>>,
-        pub id: Option<u32>,
+        pub id: Option<ObserverId>,
         h: Option<JoinHandle<()>>,
     }
 
 
     impl ObserverObj {
         pub fn new() -> Self {
-            let o = ObserverObj {
+            ObserverObj {
                 v: Arc::new(Mutex::new(None)),
                 observable: Arc::new(Mutex::new(ChObservable::new())),
                 id: None,
                 h: None,
-            };
-            o
+            }
         }
 
-        pub async fn observe(&mut self)-> (u32, Receiver<String>) {
+        pub async fn observe(&mut self)-> (ObserverId, Receiver<String>) {
             let mut g = self.observable.lock().await;
             let o: &mut ChObservable<String> = &mut g;
             o.register().await
@@ -236,11 +1924,10 @@ mod tests {
                                 *v = Some(s.clone());
                             }
                             {
-                                let x: &mut ChObservable<String>;
                                 debug!("[id={}]request lock, to inform about values ...", id);
                                 let mut og = o.lock().await;
                                 debug!("[id={}]got lock, to inform about values", id);
-                                x = &mut og;
+                                let x: &mut ChObservable<String> = &mut og;
                                 let _ = x.notify(&s).await;
                             };
                         },
@@ -252,13 +1939,13 @@ mod tests {
         }
     }
 
-    async fn check_val(id: u32, ov: &Arc<Mutex<Option<String>>>, expected: &Option<String>) {
+    async fn check_val(id: ObserverId, ov: &Arc<Mutex<Option<String>>>, expected: &Option<String>) {
         let g = ov.lock().await;
         let v: &Option<String> = &g;
         println!("Observer [id={}], content: {:?}", id, v);
         assert_eq!(v, expected);
     }
-    async fn check_val2(id: u32, rx: &mut Receiver<String>, expected: &String) {
+    async fn check_val2(id: ObserverId, rx: &mut Receiver<String>, expected: &String) {
         debug!("[id2={}]i am waiting to get informed ...", id);
         match rx.recv().await {
             Some(v) => {
@@ -267,7 +1954,7 @@ mod tests {
             },
             None => {
                 debug!("[id2={}]i was informed 2", id);
-                assert!(false);
+                panic!("channel was closed");
             },
         };
     }
@@ -291,8 +1978,8 @@ mod tests {
         check_val(o3.id.unwrap(), &o3.v, &expected_none).await;
         let t1 = "test-99".to_string();
         match cho.notify(&t1).await {
-            Ok(()) => (),
-            Err(_) => assert!(false, "receive error while notify"),
+            Ok(_) => (),
+            Err(_) => panic!("receive error while notify"),
         };
     
         let expected_1 = Some(t1.clone());
@@ -311,8 +1998,8 @@ mod tests {
     
         let t2 = "test-999".to_string();
         match cho.notify(&t2).await {
-            Ok(()) => (),
-            Err(_) => assert!(false, "receive error while notify"),
+            Ok(_) => (),
+            Err(_) => panic!("receive error while notify"),
         };
         check_val2(o1.id.unwrap(), &mut o1_rx, &t2).await;
         check_val2(o2.id.unwrap(), &mut o2_rx, &t2).await;
@@ -338,8 +2025,8 @@ mod tests {
         check_val(o3.id.unwrap(), &o3.v, &expected_none).await;
         let t1 = "test-99".to_string();
         match cho.notify(&t1).await {
-            Ok(()) => (),
-            Err(_) => assert!(false, "receive error while notify"),
+            Ok(_) => (),
+            Err(_) => panic!("receive error while notify"),
         };
     
         let expected_1 = Some(t1.clone());
@@ -358,8 +2045,8 @@ mod tests {
     
         let t2 = "test-999".to_string();
         match cho.notify(&t2).await {
-            Ok(()) => (),
-            Err(_) => assert!(false, "receive error while notify"),
+            Ok(_) => (),
+            Err(_) => panic!("receive error while notify"),
         };
         check_val2(o1.id.unwrap(), &mut o1_rx, &t2).await;
         check_val2(o2.id.unwrap(), &mut o2_rx, &t2).await;
@@ -367,7 +2054,7 @@ mod tests {
         check_val2(o4.id.unwrap(), &mut o4_rx, &t2).await;
     }
 
-    async fn check_val3(id: u32, rx: &mut Receiver<Option<String>>, expected: &String) {
+    async fn check_val3(id: ObserverId, rx: &mut Receiver<Option<String>>, expected: &String) {
         debug!("[id2={}]i am waiting to get informed ...", id);
         match rx.recv().await {
             Some(v) => {
@@ -376,12 +2063,12 @@ mod tests {
             },
             None => {
                 debug!("[id2={}]i was informed 2", id);
-                assert!(false);
+                panic!("channel was closed");
             },
         };
     }
 
-    async fn check_val5(id: u32, rx: &mut Receiver<Option<String>>) {
+    async fn check_val5(id: ObserverId, rx: &mut Receiver<Option<String>>) {
         debug!("[id2={}]i am waiting to get informed ...", id);
         match rx.recv().await {
             Some(o) => {
@@ -390,13 +2077,13 @@ mod tests {
             },
             None => {
                 debug!("[id2={}]i was informed 2", id);
-                assert!(false);
+                panic!("channel was closed");
             },
         };
     }
 
     async fn check_val4(cho: &ChObservedValue<String>, expected: &Option<String>) {
-        let r = cho.value_ref();
+        let r = cho.value_ref().unwrap();
         let g = r.lock().await;
         let os: &Option<String> = &g;
         assert_eq!(*os, *expected);
@@ -412,7 +2099,7 @@ mod tests {
         check_val4(&cho, &Option::None).await;
 
         let t1 = "test-99".to_string();
-        cho.set_value(&t1).await;
+        cho.set_value(&t1).await.unwrap();
 
         let expected_1 = Some(t1.clone());
         // since notify is async we have to way until the value have changed
@@ -425,7 +2112,7 @@ mod tests {
         check_val4(&cho, &expected_1).await;
 
         let t2 = "test-999".to_string();
-        cho.set_value(&t2).await;
+        cho.set_value(&t2).await.unwrap();
 
         check_val3(id1, &mut rx1, &t2).await;
         check_val3(id2, &mut rx2, &t2).await;
@@ -435,7 +2122,7 @@ mod tests {
         let expected_2 = Some(t2);
         check_val4(&cho, &expected_2).await;
 
-        cho.reset_value().await;
+        cho.reset_value().await.unwrap();
 
         check_val5(id1, &mut rx1).await;
         check_val5(id2, &mut rx2).await;
@@ -443,4 +2130,533 @@ mod tests {
         check_val5(id4, &mut rx4).await;
     }
 
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_debug_without_debug_payload() {
+        #[derive(Clone)]
+        struct NoDebug;
+
+        let cho: ChObservable<NoDebug> = ChObservable::new();
+        let (_id1, _rx1) = cho.register().await;
+        cho.register_callback(|_| ()).await;
+        cho.notify(&NoDebug).await.unwrap();
+        let s = format!("{:?}", cho);
+        assert!(s.contains("id: 1"));
+        assert!(s.contains("capacity: 10, queued: 1, closed: false"));
+        assert!(s.contains("id: 2, target: Callback"));
+        assert!(s.contains("notifications: 1, delivered: 2, failed: 0"));
+
+        let mut chov: ChObservedValue<NoDebug> = ChObservedValue::new();
+        let _ = chov.register().await;
+        let s = format!("{:?}", chov);
+        assert!(s.starts_with("ChObservedValue { is_set: false"));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_unregister_foreign_id() {
+        use crate::error::Error;
+
+        let cho1: ChObservable<String> = ChObservable::new();
+        let cho2: ChObservable<String> = ChObservable::new();
+        let (id1, mut rx1) = cho1.register().await;
+        let (id2, mut rx2) = cho2.register().await;
+        assert_eq!(id1.value(), id2.value());
+
+        assert_eq!(cho2.unregister(id1).await, Err(Error::ForeignObserverId(id1)));
+        let t1 = "test-1".to_string();
+        cho2.notify(&t1).await.unwrap();
+        check_val2(id2, &mut rx2, &t1).await;
+
+        cho1.unregister(id1).await.unwrap();
+        cho1.notify(&t1).await.unwrap();
+        assert!(rx1.recv().await.is_none());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_notify_shares_lock() {
+        use std::time::Duration;
+
+        let cho: Arc<ChObservable<u32>> =
+            Arc::new(ChObservable::builder().capacity(1).build().unwrap());
+        let (_, mut rx) = cho.register().await;
+        cho.notify(&0).await.unwrap();
+        let first = tokio::spawn({
+            let cho = cho.clone();
+            async move { cho.notify(&1).await }
+        });
+        let second = tokio::spawn({
+            let cho = cho.clone();
+            async move { cho.notify(&2).await }
+        });
+        tokio::task::yield_now().await;
+        // both notifications wait for the full channel, the observers are
+        // still readable
+        assert!(!first.is_finished() && !second.is_finished());
+        let count = tokio::time::timeout(Duration::from_secs(1), cho.observer_count()).await;
+        assert_eq!(count, Ok(1));
+        let mut received = vec![rx.recv().await.unwrap()];
+        received.push(rx.recv().await.unwrap());
+        received.push(rx.recv().await.unwrap());
+        received[1..].sort();
+        assert_eq!(received, vec![0, 1, 2]);
+        assert!(first.await.unwrap().is_ok());
+        assert!(second.await.unwrap().is_ok());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_with_capacity() {
+        use crate::chobservable::OverflowPolicy;
+        use crate::error::Error;
+
+        let cho: ChObservable<u32> = ChObservable::builder()
+            .capacity(2)
+            .overflow_policy(OverflowPolicy::DropNewest)
+            .build()
+            .unwrap();
+        let (_, mut slow) = cho.register().await;
+        let (_, mut fast) = cho.register_with_capacity(100).await.unwrap();
+        for i in 0..50 {
+            cho.notify(&i).await.unwrap();
+        }
+        let mut received = Vec::new();
+        while let Ok(v) = fast.try_recv() {
+            received.push(v);
+        }
+        assert_eq!(received, (0..50).collect::<Vec<u32>>());
+        assert_eq!(slow.try_recv(), Ok(0));
+        assert_eq!(slow.try_recv(), Ok(1));
+        assert!(slow.try_recv().is_err());
+        assert!(matches!(
+            cho.register_with_capacity(0).await,
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_filtered() {
+        let cho: ChObservable<u32> = ChObservable::builder().replay(4).build().unwrap();
+        cho.notify(&1).await.unwrap();
+        cho.notify(&2).await.unwrap();
+        let (id, mut even) = cho.register_filtered(|v| v % 2 == 0).await;
+        let report = cho.notify(&3).await.unwrap();
+        assert!(!report.delivered.contains(&id));
+        cho.notify(&4).await.unwrap();
+        assert_eq!(even.recv().await, Some(2));
+        assert_eq!(even.recv().await, Some(4));
+        assert!(even.try_recv().is_err());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_unbounded() {
+        let cho: ChObservable<u32> = ChObservable::builder().capacity(1).build().unwrap();
+        let (id, mut rx) = cho.register_unbounded().await;
+        // more values than the capacity, without blocking
+        for i in 0..100 {
+            cho.notify(&i).await.unwrap();
+        }
+        for i in 0..100 {
+            assert_eq!(rx.recv().await, Some(i));
+        }
+        drop(rx);
+        assert_eq!(cho.notify(&100).await.unwrap().failed, vec![id]);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_notify_report() {
+        use crate::chobservable::OverflowPolicy;
+
+        let cho: ChObservable<u32> = ChObservable::builder()
+            .capacity(1)
+            .overflow_policy(OverflowPolicy::DropNewest)
+            .build()
+            .unwrap();
+        let (dead, rx1) = cho.register().await;
+        let (slow, _rx2) = cho.register().await;
+        let (alive, mut rx3) = cho.register().await;
+        drop(rx1);
+        let report = cho.notify(&1).await.unwrap();
+        assert_eq!(report.delivered, vec![slow, alive]);
+        assert_eq!(report.failed, vec![dead]);
+
+        // the dead observer doesn't stop the others and is removed
+        assert_eq!(rx3.recv().await, Some(1));
+        let report = cho.notify(&2).await.unwrap();
+        assert_eq!(report.delivered, vec![alive]);
+        assert_eq!(report.skipped, vec![slow]);
+        assert!(report.failed.is_empty());
+        assert!(!report.is_complete());
+        assert_eq!(rx3.recv().await, Some(2));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_prune_closed() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (_, rx1) = cho.register().await;
+        let (_, mut rx2) = cho.register().await;
+        let (_, rx3) = cho.register_unbounded().await;
+        cho.register_callback(|_| ()).await;
+        drop(rx1);
+        drop(rx3);
+        assert_eq!(cho.prune_closed().await, 2);
+        assert_eq!(cho.prune_closed().await, 0);
+
+        let report = cho.notify(&1).await.unwrap();
+        assert_eq!(report.delivered.len(), 2);
+        assert_eq!(rx2.recv().await, Some(1));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_prune_closed_lifecycle_full() {
+        use std::time::Duration;
+
+        let cho: Arc<ChObservable<u32>> = Arc::new(ChObservable::new());
+        let (_, mut events) = cho.lifecycle().register().await;
+        let ids: Vec<ObserverId> = {
+            let mut ids = Vec::new();
+            for _ in 0..super::DEFAULT_CAPACITY {
+                ids.push(cho.register().await.0);
+            }
+            ids
+        };
+        // the Pruned events wait for the full lifecycle channel, the
+        // observers aren't locked meanwhile
+        let pruning = tokio::spawn({
+            let cho = cho.clone();
+            async move { cho.prune_closed().await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!pruning.is_finished());
+        let count = tokio::time::timeout(Duration::from_secs(1), cho.observer_count()).await;
+        assert_eq!(count, Ok(0));
+        let mut pruned = Vec::new();
+        while pruned.len() < ids.len() {
+            if let Some(Lifecycle::Pruned(id)) = events.recv().await {
+                pruned.push(id);
+            }
+        }
+        assert_eq!(pruned, ids);
+        assert_eq!(pruning.await.unwrap(), ids.len());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_shared_registration() {
+        // the observable is shared without an extra mutex
+        let cho: Arc<ChObservable<u32>> = Arc::new(ChObservable::new());
+        let tasks: Vec<_> = (0..4)
+            .map(|_| {
+                let cho = cho.clone();
+                tokio::spawn(async move { cho.register().await })
+            })
+            .collect();
+        let mut receivers = Vec::new();
+        for t in tasks {
+            receivers.push(t.await.unwrap());
+        }
+        let mut ids: Vec<u32> = receivers.iter().map(|(id, _)| id.value()).collect();
+        ids.sort();
+        assert_eq!(ids, vec![1, 2, 3, 4]);
+
+        assert_eq!(cho.notify(&1).await.unwrap().delivered.len(), 4);
+        let (id, mut rx) = receivers.pop().unwrap();
+        assert_eq!(rx.recv().await, Some(1));
+        cho.unregister(id).await.unwrap();
+        assert_eq!(cho.notify(&2).await.unwrap().delivered.len(), 3);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_observer_count() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        assert!(cho.is_empty().await);
+        let (_, rx1) = cho.register().await;
+        let (id2, _rx2) = cho.register().await;
+        assert_eq!(cho.observer_count().await, 2);
+
+        // dropped receivers are not counted before they are pruned
+        drop(rx1);
+        assert_eq!(cho.observer_count().await, 1);
+        assert_eq!(cho.observer_ids().await, vec![id2]);
+        cho.unregister(id2).await.unwrap();
+        assert!(cho.is_empty().await);
+
+        let mut value: ChObservedValue<u32> = ChObservedValue::new();
+        let (_, _rx) = value.register().await;
+        assert_eq!(value.observer_count().await, 1);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_clear() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (_, mut events) = cho.lifecycle().register().await;
+        let (id1, mut rx1) = cho.register().await;
+        let (id2, mut rx2) = cho.register_unbounded().await;
+        cho.notify(&1).await.unwrap();
+        assert_eq!(cho.clear().await, 2);
+
+        // the queued values are received before the end of the channels
+        assert_eq!(rx1.recv().await, Some(1));
+        assert_eq!(rx1.recv().await, None);
+        assert_eq!(rx2.recv().await, Some(1));
+        assert_eq!(rx2.recv().await, None);
+        assert_eq!(events.recv().await, Some(Lifecycle::Subscribed(id1)));
+        assert_eq!(events.recv().await, Some(Lifecycle::Subscribed(id2)));
+        assert_eq!(events.recv().await, Some(Lifecycle::Unsubscribed(id1)));
+        assert_eq!(events.recv().await, Some(Lifecycle::Unsubscribed(id2)));
+
+        let (_, mut rx3) = cho.register().await;
+        cho.notify(&2).await.unwrap();
+        assert_eq!(rx3.recv().await, Some(2));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_while_notify_waits() {
+        use std::time::Duration;
+
+        let cho: Arc<ChObservable<u32>> =
+            Arc::new(ChObservable::builder().capacity(1).build().unwrap());
+        let (_, mut rx) = cho.register().await;
+        cho.notify(&1).await.unwrap();
+
+        // the second value waits for the full channel
+        let notifier = cho.clone();
+        let notify = tokio::spawn(async move { notifier.notify(&2).await });
+        tokio::task::yield_now().await;
+        assert!(!notify.is_finished());
+
+        // the waiting notification doesn't block the registrations
+        let registered = tokio::time::timeout(Duration::from_secs(1), cho.register()).await;
+        let (id, mut rx2) = registered.expect("register blocked by notify");
+        let unregistered = tokio::time::timeout(Duration::from_secs(1), cho.unregister(id)).await;
+        assert_eq!(unregistered.expect("unregister blocked by notify"), Ok(()));
+        assert_eq!(rx2.recv().await, None);
+
+        assert_eq!(rx.recv().await, Some(1));
+        assert_eq!(rx.recv().await, Some(2));
+        assert_eq!(notify.await.unwrap().unwrap().delivered.len(), 1);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_prune_lifecycle_full() {
+        use std::time::Duration;
+
+        let cho: Arc<ChObservable<u32>> = Arc::new(ChObservable::new());
+        let (_, mut events) = cho.lifecycle().register().await;
+        let (id, rx) = cho.register().await;
+        drop(rx);
+        for _ in 1..super::DEFAULT_CAPACITY {
+            cho.register_callback(|_| ()).await;
+        }
+        // the Pruned event waits for the full lifecycle channel
+        let notify = tokio::spawn({
+            let cho = cho.clone();
+            async move { cho.notify(&1).await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!notify.is_finished());
+        let count = tokio::time::timeout(Duration::from_secs(1), cho.observer_count()).await;
+        assert_eq!(count, Ok(super::DEFAULT_CAPACITY - 1));
+        for _ in 0..super::DEFAULT_CAPACITY {
+            events.recv().await.unwrap();
+        }
+        assert_eq!(events.recv().await, Some(Lifecycle::Pruned(id)));
+        assert!(notify.await.unwrap().is_ok());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_clear_broadcast() {
+        use tokio::sync::broadcast::error::RecvError;
+
+        let cho: ChObservable<u32> = ChObservable::new();
+        let mut rx = cho.register_broadcast().await;
+        cho.notify(&1).await.unwrap();
+        assert_eq!(cho.clear().await, 0);
+        assert_eq!(rx.recv().await, Ok(1));
+        assert_eq!(rx.recv().await, Err(RecvError::Closed));
+
+        // a new broadcast channel is created afterwards
+        let mut rx = cho.register_broadcast().await;
+        cho.notify(&2).await.unwrap();
+        assert_eq!(rx.recv().await, Ok(2));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_named() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (id1, rx1) = cho.register_named("metrics-exporter").await;
+        let (id2, _rx2) = cho.register().await;
+        assert_eq!(
+            cho.observer_names().await,
+            vec![(id1, Some("metrics-exporter".to_string())), (id2, None)]
+        );
+        assert!(format!("{:?}", cho).contains("name: \"metrics-exporter\""));
+
+        let report = cho.notify(&1).await.unwrap();
+        assert_eq!(report.name(id1), Some("metrics-exporter"));
+        assert_eq!(report.name(id2), None);
+        drop(rx1);
+        let report = cho.notify(&2).await.unwrap();
+        assert_eq!(report.failed, vec![id1]);
+        assert_eq!(report.name(id1), Some("metrics-exporter"));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_with_replay() {
+        let cho: ChObservable<u32> = ChObservable::builder().replay(5).build().unwrap();
+        for i in 1..=4 {
+            cho.notify(&i).await.unwrap();
+        }
+        let (_, mut rx) = cho.register_with_replay(2).await;
+        let (_, mut all) = cho.register().await;
+        let (_, mut none) = cho.register_with_replay(0).await;
+        cho.notify(&5).await.unwrap();
+
+        for v in [3, 4, 5] {
+            assert_eq!(rx.recv().await, Some(v));
+        }
+        for v in 1..=5 {
+            assert_eq!(all.recv().await, Some(v));
+        }
+        assert_eq!(none.recv().await, Some(5));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_with_current() {
+        let mut value: ChObservedValue<u32> = ChObservedValue::new();
+        let (_, mut unset) = value.register_with_current().await;
+        value.set_value(&1).await.unwrap();
+        let (_, mut rx) = value.register_with_current().await;
+        value.set_value(&2).await.unwrap();
+
+        assert_eq!(unset.recv().await, Some(None));
+        assert_eq!(unset.recv().await, Some(Some(1)));
+        assert_eq!(rx.recv().await, Some(Some(1)));
+        assert_eq!(rx.recv().await, Some(Some(2)));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_with_current_while_set() {
+        let mut value: ChObservedValue<u32> = ChObservedValue::new();
+        value.set_value(&1).await.unwrap();
+        let observers = value.observable.lock().await;
+
+        // the registration waits for the observers first, then the change
+        let registrar = value.handle();
+        let registered = tokio::spawn(async move { registrar.register_with_current().await });
+        tokio::task::yield_now().await;
+        let mut setter = value.handle();
+        let set = tokio::spawn(async move { setter.set_value(&2).await.map(|_| ()) });
+        tokio::task::yield_now().await;
+        drop(observers);
+
+        let (_, mut rx) = registered.await.unwrap();
+        set.await.unwrap().unwrap();
+        assert_eq!(rx.recv().await, Some(Some(1)));
+        assert_eq!(rx.recv().await, Some(Some(2)));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_set_value_if_changed() {
+        let mut value: ChObservedValue<u32> = ChObservedValue::new();
+        let (_, mut rx) = value.register().await;
+        assert_eq!(value.set_value_if_changed(&1).await, Ok(true));
+        assert_eq!(value.set_value_if_changed(&1).await, Ok(false));
+        assert_eq!(value.set_value_if_changed(&2).await, Ok(true));
+        assert_eq!(rx.recv().await, Some(Some(1)));
+        assert_eq!(rx.recv().await, Some(Some(2)));
+        assert!(rx.try_recv().is_err());
+
+        // only the first field is compared
+        let mut pair: ChObservedValue<(u32, u32)> = ChObservedValue::new();
+        pair.set_comparator(|a, b| a.0 == b.0);
+        assert_eq!(pair.set_value_if_changed(&(1, 1)).await, Ok(true));
+        assert_eq!(pair.set_value_if_changed(&(1, 2)).await, Ok(false));
+        assert_eq!(pair.value().await, Ok(Some((1, 1))));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_with_value() {
+        let value = ChObservedValue::with_value(1u32);
+        assert_eq!(value.get().await, Some(1));
+        let (_, mut rx) = value.register_with_current().await;
+        assert_eq!(rx.recv().await, Some(Some(1)));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_increment() {
+        let mut value: ChObservedValue<i32> = ChObservedValue::new();
+        let (_, mut rx) = value.register().await;
+        assert_eq!(value.increment(2).await, Ok(2));
+        assert_eq!(value.decrement(5).await, Ok(-3));
+        assert_eq!(rx.recv().await, Some(Some(2)));
+        assert_eq!(rx.recv().await, Some(Some(-3)));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_get() {
+        let mut value: ChObservedValue<u32> = ChObservedValue::new();
+        assert_eq!(value.get().await, None);
+        assert!(!value.is_set().await);
+        value.set_value(&1).await.unwrap();
+        assert_eq!(value.get().await, Some(1));
+        assert!(value.is_set().await);
+        value.reset_value().await.unwrap();
+        assert!(!value.is_set().await);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_update() {
+        let mut value: ChObservedValue<u32> = ChObservedValue::new();
+        let (_, mut rx) = value.register().await;
+        value.update(|v| *v = Some(v.unwrap_or(0) + 1)).await.unwrap();
+        value.update(|v| *v = Some(v.unwrap_or(0) + 1)).await.unwrap();
+        value.update(|v| *v = None).await.unwrap();
+        assert_eq!(rx.recv().await, Some(Some(1)));
+        assert_eq!(rx.recv().await, Some(Some(2)));
+        assert_eq!(rx.recv().await, Some(None));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_compare_and_set() {
+        let mut value: ChObservedValue<u32> = ChObservedValue::new();
+        let (_, mut rx) = value.register().await;
+        assert_eq!(value.compare_and_set(&0, 1).await, Err(1));
+        value.set_value(&1).await.unwrap();
+        assert_eq!(value.compare_and_set(&0, 2).await, Err(2));
+        assert_eq!(value.compare_and_set(&1, 2).await, Ok(()));
+        assert_eq!(value.get().await, Some(2));
+        assert_eq!(rx.recv().await, Some(Some(1)));
+        assert_eq!(rx.recv().await, Some(Some(2)));
+        assert!(rx.try_recv().is_err());
+
+        // only the first field is compared
+        let mut pair: ChObservedValue<(u32, u32)> = ChObservedValue::new();
+        pair.set_comparator(|a, b| a.0 == b.0);
+        pair.set_value(&(1, 1)).await.unwrap();
+        assert_eq!(pair.compare_and_set(&(2, 1), (3, 3)).await, Err((3, 3)));
+        assert_eq!(pair.compare_and_set(&(1, 5), (2, 2)).await, Ok(()));
+        assert_eq!(pair.get().await, Some((2, 2)));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_comparator_without_partial_eq() {
+        // no PartialEq, only the name is compared
+        #[derive(Clone, Debug)]
+        struct Item {
+            name: &'static str,
+            hits: u32,
+        }
+
+        let same_name = |a: &Item, b: &Item| a.name == b.name;
+        let mut value: ChObservedValue<Item> = ChObservedValue::new();
+        let (_, mut rx) = value.register().await;
+        let item = |name, hits| Item { name, hits };
+        assert_eq!(value.set_value_if_changed_with(&item("a", 1), same_name).await, Ok(true));
+        assert_eq!(value.set_value_if_changed_with(&item("a", 2), same_name).await, Ok(false));
+        let res = value.compare_and_set_with(&item("b", 0), item("c", 3), same_name).await;
+        assert_eq!(res.map_err(|i| i.hits), Err(3));
+        let res = value.compare_and_set_with(&item("a", 0), item("c", 4), same_name).await;
+        assert!(res.is_ok());
+        assert_eq!(rx.recv().await.flatten().map(|i| i.hits), Some(1));
+        assert_eq!(rx.recv().await.flatten().map(|i| i.hits), Some(4));
+        assert!(rx.try_recv().is_err());
+    }
 }
@@ -0,0 +1,169 @@
+//! Observer channels, whose capacity follows the load of the observer
+//!
+//! The capacity of a tokio channel is fixed, so a resize creates a new
+//! channel. The receiver gets the new channel after the buffered values of
+//! the old one, so the order of the values is kept.
+
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+use tokio::sync::mpsc::{self, Receiver, UnboundedReceiver, UnboundedSender};
+
+use super::{ChObservable, State, Target};
+use crate::observer_id::ObserverId;
+
+/// Number of notifications in a row, that find the channel full, before
+/// the capacity is doubled
+const GROW_AFTER_FULL: u32 = 2;
+
+/// Number of notifications in a row, that find the channel empty, before
+/// the capacity is halved
+const SHRINK_AFTER_IDLE: u32 = 16;
+
+/// Settings and counters of an adaptive observer
+pub(super) struct Adaptive<T> {
+    min: usize,
+    max: usize,
+    full: u32,
+    idle: u32,
+    /// Passes the resized channels to the receiver
+    relink: UnboundedSender<Receiver<T>>,
+}
+
+/// Receiver of an observer with adaptive capacity, it's created by
+/// `ChObservable::register_adaptive`
+pub struct AdaptiveReceiver<T> {
+    rx: Receiver<T>,
+    relink: UnboundedReceiver<Receiver<T>>,
+}
+
+impl<T> Debug for AdaptiveReceiver<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdaptiveReceiver").finish_non_exhaustive()
+    }
+}
+
+impl<T> AdaptiveReceiver<T> {
+    /// Receives the next value, like `Receiver::recv`. It returns `None`
+    /// after the observer was removed.
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            if let Some(v) = self.rx.recv().await {
+                return Some(v);
+            }
+            // the old channel is empty and closed after a resize
+            self.rx = self.relink.recv().await?;
+        }
+    }
+}
+
+impl<T: Clone> ChObservable<T> {
+    /// This function registers a new observer, whose channel capacity
+    /// follows its load. It starts with the configured capacity. If the
+    /// channel is full at repeated notifications, the capacity is doubled
+    /// up to `max_capacity`, if the channel is empty for a while, it's
+    /// halved down to the configured capacity again. The capacity is
+    /// adapted by `notify`. It returns the ID of the registered observer
+    /// and the receiver to get the new values.
+    ///
+    /// ## Arguments
+    /// * `max_capacity` - upper limit of the capacity
+    ///
+    pub async fn register_adaptive(
+        &self,
+        max_capacity: usize,
+    ) -> (ObserverId, AdaptiveReceiver<T>) {
+        let min = self.config.capacity;
+        let (tx, rx) = mpsc::channel(min);
+        let (relink_tx, relink_rx) = mpsc::unbounded_channel();
+        let adaptive = Adaptive {
+            min,
+            max: max_capacity.max(min),
+            full: 0,
+            idle: 0,
+            relink: relink_tx,
+        };
+        let id = self
+            .add_observer_with(Target::Channel(tx), |o| o.adaptive = Some(adaptive))
+            .await;
+        let rx = AdaptiveReceiver {
+            rx,
+            relink: relink_rx,
+        };
+        (id, rx)
+    }
+
+    /// Resizes the channels of the adaptive observers, before a value is
+    /// passed to them. An observer that still gets a value of a running
+    /// notification keeps its channel until the next one.
+    pub(super) fn adapt(&self, state: &mut State<T>) {
+        for o in state.observers.iter_mut() {
+            let Some(o) = Arc::get_mut(o) else {
+                continue;
+            };
+            let (Some(a), Target::Channel(tx)) = (&mut o.adaptive, &o.target) else {
+                continue;
+            };
+            let capacity = tx.max_capacity();
+            let free = tx.capacity();
+            let resize = if free == 0 {
+                a.idle = 0;
+                a.full += 1;
+                let grow = a.full >= GROW_AFTER_FULL && capacity < a.max;
+                grow.then(|| (capacity * 2).min(a.max))
+            } else if free == capacity {
+                a.full = 0;
+                a.idle += 1;
+                let shrink = a.idle >= SHRINK_AFTER_IDLE && capacity > a.min;
+                shrink.then(|| (capacity / 2).max(a.min))
+            } else {
+                a.full = 0;
+                a.idle = 0;
+                None
+            };
+            if let Some(new_capacity) = resize {
+                let (tx, rx) = mpsc::channel(new_capacity);
+                if a.relink.send(rx).is_ok() {
+                    a.full = 0;
+                    a.idle = 0;
+                    o.target = Target::Channel(tx);
+                    self.log(format_args!(
+                        "resize channel of observer: id={}, capacity={}",
+                        o.id, new_capacity
+                    ));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::{ChObservable, OverflowPolicy};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_adaptive() {
+        let cho: ChObservable<u32> = ChObservable::builder()
+            .capacity(2)
+            .overflow_policy(OverflowPolicy::DropNewest)
+            .build()
+            .unwrap();
+        let (_, mut rx) = cho.register_adaptive(8).await;
+        for i in 1..=9 {
+            cho.notify(&i).await.unwrap();
+        }
+        // 3 and 8 found a full channel, the following values a larger one
+        for i in [1, 2, 4, 5, 6, 7, 9] {
+            assert_eq!(rx.recv().await, Some(i));
+        }
+        assert!(format!("{:?}", cho).contains("capacity: 8"));
+
+        for i in 0..16 {
+            cho.notify(&i).await.unwrap();
+            assert_eq!(rx.recv().await, Some(i));
+        }
+        assert!(format!("{:?}", cho).contains("capacity: 4"));
+        drop(cho);
+        assert_eq!(rx.recv().await, None);
+    }
+}
@@ -0,0 +1,112 @@
+//! Observers that are async functions, running in a task of the observable
+
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+
+use tokio::sync::mpsc;
+use tokio::task::{AbortHandle, JoinHandle};
+
+use super::{ChObservable, Target};
+use crate::observer_id::ObserverId;
+
+/// Aborts the consumer task of an observer, when the observer is removed
+pub(super) struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Handle of an observer, that was registered by
+/// `ChObservable::register_async_fn`. The consumer task is owned by the
+/// observable, dropping the handle doesn't stop it.
+pub struct AsyncFnHandle {
+    id: ObserverId,
+    task: JoinHandle<()>,
+}
+
+impl Debug for AsyncFnHandle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncFnHandle")
+            .field("id", &self.id)
+            .field("finished", &self.task.is_finished())
+            .finish()
+    }
+}
+
+impl AsyncFnHandle {
+    /// Returns the ID of the registered observer
+    pub fn id(&self) -> ObserverId {
+        self.id
+    }
+
+    /// Returns true if the consumer task has ended
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+impl<T: Clone + Send + 'static> ChObservable<T> {
+    /// This function registers an async function as observer. The values
+    /// are received by a task, that is spawned by the spawner of the
+    /// observable and calls the function for one value after the other.
+    /// The task is aborted when the observer is unregistered, also in the
+    /// middle of a call.
+    ///
+    /// ## Arguments
+    /// * `f` - function that is called with every notified value
+    ///
+    pub async fn register_async_fn<F, Fut>(&self, mut f: F) -> AsyncFnHandle
+    where
+        F: FnMut(T) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (tx, mut rx) = mpsc::channel(self.config.capacity);
+        let task = self.config.spawner.spawn(async move {
+            while let Some(value) = rx.recv().await {
+                f(value).await;
+            }
+        });
+        let abort = AbortOnDrop(task.abort_handle());
+        let id = self
+            .add_observer_with(Target::Channel(tx), |o| o.task = Some(abort))
+            .await;
+        AsyncFnHandle { id, task }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use crate::chobservable::ChObservable;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_async_fn() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let handle = cho
+            .register_async_fn(move |v| {
+                let tx = tx.clone();
+                async move {
+                    tx.send(v).unwrap();
+                    if v == 2 {
+                        // never ends, only the abort stops it
+                        std::future::pending::<()>().await;
+                    }
+                }
+            })
+            .await;
+        cho.notify(&1).await.unwrap();
+        cho.notify(&2).await.unwrap();
+        assert_eq!(rx.recv().await, Some(1));
+        assert_eq!(rx.recv().await, Some(2));
+        assert!(!handle.is_finished());
+
+        cho.unregister(handle.id()).await.unwrap();
+        // the aborted task drops the function with its sender
+        assert_eq!(rx.recv().await, None);
+        assert!(handle.is_finished());
+    }
+}
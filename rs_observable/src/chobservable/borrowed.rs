@@ -0,0 +1,171 @@
+//! Observers that borrow the notified value, instead of getting a copy
+
+use std::future::Future;
+use std::pin::Pin;
+
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+use super::{discard, ChObservable, DeliveryReport, StoredObserver, Target};
+use crate::error::Result;
+use crate::observer_id::ObserverId;
+
+/// Send of a projection, that waits for free capacity
+type SendFuture<'a> = Pin<Box<dyn Future<Output = std::result::Result<(), ()>> + Send + 'a>>;
+
+/// Channel of an observer, that gets a projection of the values instead
+/// of a copy
+pub(super) trait Projection<T>: Send + Sync {
+    /// Passes the projection of a value into the channel without waiting
+    fn try_send(&self, data: &T) -> std::result::Result<(), TrySendError<()>>;
+
+    /// Passes the projection of a value into the channel, it waits for
+    /// free capacity. It fails if the receiver was dropped.
+    fn send(&self, data: &T) -> SendFuture<'_>;
+
+    /// Returns true if the receiver was dropped
+    fn is_closed(&self) -> bool;
+
+    /// Returns the capacity of the channel
+    fn max_capacity(&self) -> usize;
+}
+
+/// Channel of a `register_map` observer together with its projection
+struct Projector<U, F> {
+    tx: Sender<U>,
+    f: F,
+}
+
+impl<T, U, F> Projection<T> for Projector<U, F>
+where
+    U: Send + 'static,
+    F: Fn(&T) -> U + Send + Sync,
+{
+    fn try_send(&self, data: &T) -> std::result::Result<(), TrySendError<()>> {
+        self.tx.try_send((self.f)(data)).map_err(discard)
+    }
+
+    fn send(&self, data: &T) -> SendFuture<'_> {
+        let v = (self.f)(data);
+        Box::pin(async move { self.tx.send(v).await.map_err(|_| ()) })
+    }
+
+    fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+
+    fn max_capacity(&self) -> usize {
+        self.tx.max_capacity()
+    }
+}
+
+impl<T: Clone> ChObservable<T> {
+    /// This function registers a callback as observer, that borrows the
+    /// value within the notify call. Other than `register_callback` the
+    /// value isn't cloned for it. It returns the ID of the registered
+    /// observer.
+    ///
+    /// ## Arguments
+    /// * `callback` - function that is called with every notified value
+    ///
+    pub async fn register_ref<F>(&self, callback: F) -> ObserverId
+    where
+        F: Fn(&T) + Send + Sync + 'static,
+    {
+        self.add_observer(Target::Borrowed(Box::new(callback))).await
+    }
+
+    /// This function registers a new observer, that gets a projection of
+    /// the values, e.g. one field of a large struct. The projection is
+    /// computed from the borrowed value within the notify call, so the
+    /// value itself isn't cloned for it. A full channel is handled by the
+    /// overflow policy, like with `register`. It returns the ID of the
+    /// registered observer and a channel receiver to get the projections.
+    ///
+    /// ## Arguments
+    /// * `f` - returns the projection of a value
+    ///
+    pub async fn register_map<U, F>(&self, f: F) -> (ObserverId, Receiver<U>)
+    where
+        U: Send + 'static,
+        F: Fn(&T) -> U + Send + Sync + 'static,
+    {
+        let (tx, rx) = mpsc::channel(self.config.capacity);
+        let id = self
+            .add_observer(Target::Mapped(Box::new(Projector { tx, f })))
+            .await;
+        (id, rx)
+    }
+
+    /// Notifies the observers like `notify`, but first calls the
+    /// observers within the notify call and then passes copies of the
+    /// value to the channel observers. So the local observers are done,
+    /// before a channel observer can see the value.
+    ///
+    /// ## Arguments
+    /// * `data` - data that should be passed to the observers
+    pub async fn notify_sync_observers(&self, data: &T) -> Result<DeliveryReport> {
+        self.notify_in_passes(data, &[&StoredObserver::is_in_place, &|o| !o.is_in_place()])
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::chobservable::{ChObservable, OverflowPolicy};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_notify_sync_observers() {
+        let cho: ChObservable<String> = ChObservable::new();
+        let (channel, mut rx) = cho.register().await;
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let s = seen.clone();
+        let local = cho.register_ref(move |v: &String| s.lock().unwrap().push(v.len())).await;
+
+        let report = cho.notify_sync_observers(&"abc".to_string()).await.unwrap();
+        // the local observer comes first, independent of the registration
+        assert_eq!(report.delivered, vec![local, channel]);
+        assert_eq!(*seen.lock().unwrap(), vec![3]);
+        assert_eq!(rx.recv().await, Some("abc".to_string()));
+
+        cho.notify(&"de".to_string()).await.unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec![3, 2]);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_map() {
+        let cho: ChObservable<(String, u32)> = ChObservable::new();
+        let (id, mut ports) = cho.register_map(|(_, port)| *port).await;
+        cho.notify(&("localhost".to_string(), 80)).await.unwrap();
+        cho.notify(&("localhost".to_string(), 8080)).await.unwrap();
+        assert_eq!(ports.recv().await, Some(80));
+        assert_eq!(ports.recv().await, Some(8080));
+
+        // the observer is pruned with its receiver
+        drop(ports);
+        let report = cho.notify(&("localhost".to_string(), 1)).await.unwrap();
+        assert!(!report.delivered.contains(&id));
+        assert_eq!(cho.observer_count().await, 0);
+
+        // the notification waits for the full channel by default
+        let cho: ChObservable<u32> = ChObservable::builder().capacity(1).build().unwrap();
+        let (_, mut doubled) = cho.register_map(|v| v * 2).await;
+        let consumer = tokio::spawn(async move { (doubled.recv().await, doubled.recv().await) });
+        cho.notify(&1).await.unwrap();
+        cho.notify(&2).await.unwrap();
+        assert_eq!(consumer.await.unwrap(), (Some(2), Some(4)));
+
+        let cho: ChObservable<u32> = ChObservable::builder()
+            .capacity(1)
+            .overflow_policy(OverflowPolicy::DropNewest)
+            .build()
+            .unwrap();
+        let (id, mut doubled) = cho.register_map(|v| v * 2).await;
+        cho.notify(&1).await.unwrap();
+        assert_eq!(cho.notify(&2).await.unwrap().skipped, vec![id]);
+        assert_eq!(doubled.recv().await, Some(2));
+        assert!(format!("{:?}", cho).contains("dropped: 1"));
+    }
+}
@@ -0,0 +1,263 @@
+//! Bridge between the single threaded ObservedValue and the async
+//! ChObservedValue, so a codebase can move from one to the other module
+//! by module
+
+use std::cell::RefCell;
+use std::fmt::{self, Debug, Formatter};
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::runtime::Handle;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::Mutex;
+
+use super::{ChObservable, ChObservedValue, Spawner, StoredObserver, Target, ValueState};
+use crate::error::Result;
+use crate::observed_value::ObservedValue;
+use crate::observer_id::ObserverId;
+
+/// Sync side of an `ObservedValue`, that is mirrored to a `ChObservedValue`.
+/// It's created by `ObservedValue::to_async` and `ChObservedValue::to_sync`
+/// and is used like the wrapped `ObservedValue`.
+///
+/// Changes with `set_value` and `reset_value` are passed to the async value
+/// by a task. Changes of the async value are queued and applied by `sync`,
+/// because the single threaded value can't be changed by another task.
+/// Applied changes are not passed back. Changes over `DerefMut` of the
+/// wrapped value are not mirrored. The async value is kept alive by the
+/// mirror.
+pub struct SyncMirror<T: Clone> {
+    value: ObservedValue<T>,
+    /// Changes of the async value, that are not applied yet
+    rx: UnboundedReceiver<Option<T>>,
+    /// True while a change of the async value is applied, so it isn't
+    /// passed back
+    applying: Arc<AtomicBool>,
+}
+
+impl<T: Clone> Debug for SyncMirror<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncMirror")
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> SyncMirror<T> {
+    /// Connects both values. The changes of the sync value are applied to
+    /// the async value by a task of the spawner.
+    fn new(
+        mut value: ObservedValue<T>,
+        rx: UnboundedReceiver<Option<T>>,
+        mut target: ChObservedValue<T>,
+        echo: Arc<AtomicBool>,
+        spawner: &Spawner,
+    ) -> Self {
+        let applying = Arc::new(AtomicBool::new(false));
+        let (tx, mut changes) = mpsc::unbounded_channel();
+        let a = applying.clone();
+        value.register(Rc::new(RefCell::new(move |v: Option<T>| {
+            if !a.load(Ordering::SeqCst) {
+                let _ = tx.send(v);
+            }
+        })));
+        // ends with the drop of the mirror, it drops the sender
+        spawner.spawn(async move {
+            while let Some(v) = changes.recv().await {
+                let _ = target.apply_mirrored(v, &echo).await;
+            }
+        });
+        SyncMirror { value, rx, applying }
+    }
+
+    /// Applies the queued changes of the async value and notifies the
+    /// observers of the sync value. It returns the number of applied
+    /// changes.
+    pub fn sync(&mut self) -> usize {
+        let mut n = 0;
+        while let Ok(v) = self.rx.try_recv() {
+            self.applying.store(true, Ordering::SeqCst);
+            match v {
+                Some(v) => self.value.set_value(&v),
+                None => self.value.reset_value(),
+            }
+            self.applying.store(false, Ordering::SeqCst);
+            n += 1;
+        }
+        n
+    }
+}
+
+impl<T: Clone> Deref for SyncMirror<T> {
+    type Target = ObservedValue<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T: Clone> DerefMut for SyncMirror<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+/// Returns the callback and the closed check of the observer, that passes
+/// the changes of the async value to the sync side. Changes that came from
+/// the sync side are skipped.
+fn forwarder<T>(
+    tx: UnboundedSender<Option<T>>,
+    echo: Arc<AtomicBool>,
+) -> (impl Fn(Option<T>) + Send + Sync, impl Fn() -> bool + Send + Sync)
+where
+    T: Send + 'static,
+{
+    let probe = tx.clone();
+    let send = move |v| {
+        if !echo.load(Ordering::SeqCst) {
+            let _ = tx.send(v);
+        }
+    };
+    (send, move || probe.is_closed())
+}
+
+impl<T: Clone + Send + Sync + 'static> ObservedValue<T> {
+    /// Returns a mirror of this value together with a new async value,
+    /// that starts with the current value. After that, every change of one
+    /// side is passed to the other one, see `SyncMirror`.
+    ///
+    /// ## Arguments
+    /// * `handle` - runtime, that runs the task which passes the changes
+    ///   to the async value
+    ///
+    pub fn to_async(self, handle: Handle) -> (SyncMirror<T>, ChObservedValue<T>) {
+        let value = ChObservedValue {
+            value: Arc::new(Mutex::new((*self).clone())),
+            ..ChObservedValue::new()
+        };
+        let (tx, rx) = mpsc::unbounded_channel();
+        let echo = Arc::new(AtomicBool::new(false));
+        let (send, closed) = forwarder(tx, echo.clone());
+        value
+            .observable
+            .try_lock()
+            .expect("observable of a new value is unlocked")
+            .add_forwarder_now(send, closed);
+        let mirror = SyncMirror::new(self, rx, value.handle(), echo, &Spawner::Handle(handle));
+        (mirror, value)
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> ChObservedValue<T> {
+    /// Returns a new sync value, that starts with the current value. After
+    /// that, every change of one side is passed to the other one, see
+    /// `SyncMirror`. The changes of the sync value are passed by a task of
+    /// the spawner of the observable.
+    pub async fn to_sync(&mut self) -> SyncMirror<T> {
+        let (current, observable) = self.lock_current().await;
+        let mut value = ObservedValue::new();
+        *value = current;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let echo = Arc::new(AtomicBool::new(false));
+        let (send, closed) = forwarder(tx, echo.clone());
+        observable.add_forwarder(send, closed).await;
+        let spawner = observable.spawner().clone();
+        drop(observable);
+        SyncMirror::new(value, rx, self.handle(), echo, &spawner)
+    }
+
+    /// Sets a change of the sync side like `set_value` or `reset_value`.
+    /// `echo` is true while the observers are notified, they are locked
+    /// meanwhile, so only this change is skipped by the forwarder.
+    async fn apply_mirrored(&mut self, v: Option<T>, echo: &AtomicBool) -> Result<()> {
+        let observable = self.set_value_impl(v.clone()).await;
+        echo.store(true, Ordering::SeqCst);
+        let res = observable.notify(&v).await;
+        echo.store(false, Ordering::SeqCst);
+        drop(observable);
+        res?;
+        self.restore(v.map_or(ValueState::Unset, ValueState::Set)).await
+    }
+}
+
+impl<T: Clone> ChObservable<T> {
+    /// Registers a forwarder like `add_forwarder`, without waiting. It's
+    /// only used for new observables, whose observers aren't locked.
+    fn add_forwarder_now<F, C>(&self, callback: F, closed: C) -> ObserverId
+    where
+        F: Fn(T) + Send + Sync + 'static,
+        C: Fn() -> bool + Send + Sync + 'static,
+    {
+        let mut g = self
+            .state
+            .try_write()
+            .expect("observers of a new observable are unlocked");
+        let id = self.next_observer_id();
+        let mut o = StoredObserver::new(id, Target::Callback(Box::new(callback)));
+        o.closed = Some(Box::new(closed));
+        self.attach(&mut g, o);
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::mpsc;
+
+    use tokio::runtime::Handle;
+
+    use crate::chobservable::ChObservedValue;
+    use crate::observed_value::ObservedValue;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_to_async() {
+        let mut sync_value: ObservedValue<u32> = ObservedValue::new();
+        sync_value.set_value(&1);
+        let (mut mirror, mut async_value) = sync_value.to_async(Handle::current());
+        assert_eq!(async_value.value().await, Ok(Some(1)));
+        let (_, mut rx) = async_value.register().await;
+        let (tx, seen) = mpsc::channel();
+        mirror.register(Rc::new(RefCell::new(tx)));
+
+        mirror.set_value(&2);
+        assert_eq!(rx.recv().await, Some(Some(2)));
+        assert_eq!(async_value.value().await, Ok(Some(2)));
+        // the change came from the sync side and isn't passed back
+        assert_eq!(mirror.sync(), 0);
+
+        async_value.set_value(&3).await.unwrap();
+        async_value.reset_value().await.unwrap();
+        assert_eq!(mirror.sync(), 2);
+        assert_eq!(**mirror, None);
+        assert_eq!(seen.try_iter().collect::<Vec<_>>(), vec![Some(2), Some(3), None]);
+        assert_eq!(rx.recv().await, Some(Some(3)));
+        assert_eq!(rx.recv().await, Some(None));
+        tokio::task::yield_now().await;
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_to_sync() {
+        let mut async_value: ChObservedValue<u32> = ChObservedValue::new();
+        async_value.set_value(&1).await.unwrap();
+        let mut mirror = async_value.to_sync().await;
+        assert_eq!(**mirror, Some(1));
+        let (_, mut rx) = async_value.register().await;
+
+        mirror.reset_value();
+        assert_eq!(rx.recv().await, Some(None));
+        async_value.set_value(&2).await.unwrap();
+        assert_eq!(mirror.sync(), 1);
+        assert_eq!(**mirror, Some(2));
+
+        // the forwarder to the sync side is pruned with the mirror
+        drop(mirror);
+        async_value.set_value(&3).await.unwrap();
+        let observable = async_value.observable.lock().await;
+        assert_eq!(observable.state.read().await.observers.len(), 1);
+    }
+}
@@ -0,0 +1,87 @@
+//! Observers that share one tokio broadcast channel
+
+use std::sync::atomic::Ordering;
+
+use tokio::sync::broadcast::{self, Receiver};
+
+use super::{ChObservable, State};
+
+impl<T: Clone> ChObservable<T> {
+    /// This function registers a new observer, that gets the values over a
+    /// broadcast channel. All broadcast observers share one channel with
+    /// the configured capacity, so a notification passes the value only
+    /// once for all of them and never waits. A slow observer loses the
+    /// oldest values and gets `RecvError::Lagged`. The broadcast observers
+    /// get no replay values, no lifecycle events and no IDs, they are
+    /// removed by dropping the receiver. After the observable was closed,
+    /// the returned receiver ends right away.
+    pub async fn register_broadcast(&self) -> Receiver<T> {
+        let mut state = self.state.write().await;
+        let capacity = self.config.capacity;
+        if state.closed {
+            // the receiver of a closed observable ends right away
+            return broadcast::channel(capacity).1;
+        }
+        let tx = state
+            .broadcast
+            .get_or_insert_with(|| broadcast::channel(capacity).0);
+        self.log(format_args!("register broadcast observer"));
+        tx.subscribe()
+    }
+
+    /// Passes a value to the broadcast observers
+    pub(super) fn broadcast(&self, state: &State<T>, data: &T) {
+        if let Some(tx) = &state.broadcast {
+            if let Ok(n) = tx.send(data.clone()) {
+                self.stats.delivered.fetch_add(n as u64, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::broadcast::error::RecvError;
+
+    use crate::chobservable::ChObservable;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_broadcast() {
+        let cho: ChObservable<u32> = ChObservable::builder().capacity(2).build().unwrap();
+        let mut fast = cho.register_broadcast().await;
+        let mut slow = cho.register_broadcast().await;
+        let (_, mut plain) = cho.register().await;
+        cho.notify(&1).await.unwrap();
+        assert_eq!(fast.recv().await, Ok(1));
+        assert_eq!(plain.recv().await, Some(1));
+
+        // more values than the capacity don't block
+        for i in 2..=4 {
+            cho.notify(&i).await.unwrap();
+            assert_eq!(plain.recv().await, Some(i));
+        }
+        assert_eq!(slow.recv().await, Err(RecvError::Lagged(2)));
+        assert_eq!(slow.recv().await, Ok(3));
+        assert_eq!(slow.recv().await, Ok(4));
+
+        drop(cho);
+        assert_eq!(fast.recv().await, Err(RecvError::Lagged(1)));
+        assert_eq!(fast.recv().await, Ok(3));
+        assert_eq!(fast.recv().await, Ok(4));
+        assert_eq!(fast.recv().await, Err(RecvError::Closed));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_broadcast_close() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let mut rx = cho.register_broadcast().await;
+        cho.notify(&1).await.unwrap();
+
+        // the receivers end with close, not only with the drop
+        cho.close().await;
+        assert_eq!(rx.recv().await, Ok(1));
+        assert_eq!(rx.recv().await, Err(RecvError::Closed));
+        let mut late = cho.register_broadcast().await;
+        assert_eq!(late.recv().await, Err(RecvError::Closed));
+    }
+}
@@ -0,0 +1,315 @@
+//! Builder to configure a ChObservable before its creation
+
+use log::Level;
+use std::fmt::{self, Debug, Formatter};
+use std::time::Duration;
+
+use super::{ChObservable, Config, LatestFn, OverflowPolicy, Spawner};
+use crate::error::{Error, Result};
+
+/// Collects the settings of a `ChObservable`. It is created by
+/// `ChObservable::builder()`.
+pub struct ChObservableBuilder<T> {
+    config: Config,
+    latest_fn: Option<LatestFn<T>>,
+    summarizer: Option<fn(&T) -> String>,
+    completion: Option<T>,
+}
+
+impl<T> Debug for ChObservableBuilder<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChObservableBuilder")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl<T: Clone> ChObservableBuilder<T> {
+    pub(super) fn new() -> Self {
+        ChObservableBuilder {
+            config: Config::default(),
+            latest_fn: None,
+            summarizer: None,
+            completion: None,
+        }
+    }
+
+    /// Sets a last value, that is passed to the observers when the
+    /// observable is closed by `ChObservable::close` or dropped. It marks
+    /// the end of the stream for observers, that can't see the end of
+    /// their channel, e.g. callbacks. At the drop it's passed without
+    /// waiting, so it's lost for observers with a full channel.
+    ///
+    /// ## Arguments
+    /// * `value` - value that is notified last
+    ///
+    pub fn completion(mut self, value: T) -> Self {
+        self.completion = Some(value);
+        self
+    }
+
+    /// Sets the name that is used in log output and Debug dumps
+    ///
+    /// ## Arguments
+    /// * `name` - name of the observable
+    ///
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.config.name = Some(name.into());
+        self
+    }
+
+    /// Sets the capacity of the observer channels, default is 10
+    ///
+    /// ## Arguments
+    /// * `capacity` - max number of values that are buffered per observer
+    ///
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.config.capacity = capacity;
+        self
+    }
+
+    /// Sets the behavior for observers with a full channel, default is
+    /// `OverflowPolicy::Block`
+    ///
+    /// ## Arguments
+    /// * `policy` - policy to use
+    ///
+    pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.config.overflow_policy = policy;
+        self
+    }
+
+    /// Keeps the latest notified values and passes them to every new
+    /// observer directly after the registration, default is 0
+    ///
+    /// ## Arguments
+    /// * `depth` - number of values to keep
+    ///
+    pub fn replay(mut self, depth: usize) -> Self {
+        self.config.replay_depth = depth;
+        self
+    }
+
+    /// Keeps the latest notified values like `replay`, but values that are
+    /// older than `max_age` are not passed to new observers anymore
+    ///
+    /// ## Arguments
+    /// * `depth` - max number of values to keep
+    /// * `max_age` - max age of the values to keep
+    ///
+    pub fn replay_window(mut self, depth: usize, max_age: Duration) -> Self {
+        self.config.replay_depth = depth;
+        self.config.replay_max_age = Some(max_age);
+        self
+    }
+
+    /// Sets a function that returns the current value for new observers.
+    /// It's called during the registration, if the replay buffer is empty,
+    /// e.g. to read the value from an external source. Observers get no
+    /// initial value if the function returns `None`.
+    ///
+    /// ## Arguments
+    /// * `latest_fn` - returns the current value
+    ///
+    pub fn latest_fn<F>(mut self, latest_fn: F) -> Self
+    where
+        F: Fn() -> Option<T> + Send + Sync + 'static,
+    {
+        self.latest_fn = Some(Box::new(latest_fn));
+        self
+    }
+
+    /// Sets a function that describes the notified values in the log
+    /// output, this doesn't need `T: Debug`. Longer descriptions are cut.
+    ///
+    /// ## Arguments
+    /// * `summarizer` - returns a short description of a value
+    ///
+    pub fn summarizer(mut self, summarizer: fn(&T) -> String) -> Self {
+        self.summarizer = Some(summarizer);
+        self
+    }
+
+    /// Sets the level of the log output of the observable, default is
+    /// `Level::Debug`
+    ///
+    /// ## Arguments
+    /// * `level` - log level to use
+    ///
+    pub fn log_level(mut self, level: Level) -> Self {
+        self.config.log_level = level;
+        self
+    }
+
+    /// Sets where the internal tasks of the observable are spawned, e.g.
+    /// the forwarding tasks of `join` and `route!`, default is
+    /// `Spawner::Current`
+    ///
+    /// ## Arguments
+    /// * `spawner` - place to spawn the tasks
+    ///
+    pub fn spawn_on(mut self, spawner: Spawner) -> Self {
+        self.config.spawner = spawner;
+        self
+    }
+
+    /// Creates the observable. It fails with `Error::Validation` if the
+    /// settings don't fit together.
+    pub fn build(self) -> Result<ChObservable<T>> {
+        if self.config.capacity == 0 {
+            return Err(Error::Validation(
+                "channel capacity must be greater than 0".to_string(),
+            ));
+        }
+        if let Some(name) = &self.config.name {
+            if name.is_empty() {
+                return Err(Error::Validation("name must not be empty".to_string()));
+            }
+        }
+        let mut o = ChObservable::with_config(self.config);
+        o.latest_fn = self.latest_fn;
+        o.summarizer = self.summarizer;
+        o.completion = self.completion;
+        Ok(o)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::chobservable::{ChObservable, OverflowPolicy};
+    use crate::error::Error;
+    use crate::testing::TestScheduler;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_builder() {
+        let cho: ChObservable<u32> = ChObservable::builder()
+            .name("sensor")
+            .capacity(2)
+            .replay(3)
+            .overflow_policy(OverflowPolicy::DropNewest)
+            .log_level(log::Level::Trace)
+            .build()
+            .unwrap();
+        let s = format!("{:?}", cho);
+        assert!(s.starts_with(
+            "ChObservable { name: Some(\"sensor\"), capacity: 2, \
+             overflow_policy: DropNewest, replay_depth: 3"
+        ));
+
+        for i in 1..=4 {
+            cho.notify(&i).await.unwrap();
+        }
+        // the channel can only take the latest two of the three replayed values
+        let (_, mut rx) = cho.register().await;
+        assert_eq!(rx.recv().await, Some(3));
+        assert_eq!(rx.recv().await, Some(4));
+
+        for i in 5..=7 {
+            cho.notify(&i).await.unwrap();
+        }
+        assert_eq!(rx.recv().await, Some(5));
+        assert_eq!(rx.recv().await, Some(6));
+        assert!(rx.try_recv().is_err());
+        assert!(format!("{:?}", cho).contains("dropped: 1"));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_builder_overflow_error() {
+        let cho: ChObservable<u32> = ChObservable::builder()
+            .capacity(1)
+            .overflow_policy(OverflowPolicy::Error)
+            .build()
+            .unwrap();
+        let (id, _rx) = cho.register().await;
+        cho.notify(&1).await.unwrap();
+        assert_eq!(cho.notify(&2).await.unwrap().failed, vec![id]);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_builder_replay_window() {
+        let scheduler = TestScheduler::new();
+        let cho: ChObservable<u32> = ChObservable::builder()
+            .replay_window(3, Duration::from_secs(10))
+            .build()
+            .unwrap();
+        for i in 1..=4 {
+            cho.notify(&i).await.unwrap();
+        }
+        scheduler.advance(Duration::from_secs(6)).await;
+        cho.notify(&5).await.unwrap();
+        // the count limit applies as well
+        let (_, mut rx) = cho.register().await;
+        assert_eq!(rx.recv().await, Some(3));
+        assert_eq!(rx.recv().await, Some(4));
+        assert_eq!(rx.recv().await, Some(5));
+
+        // the older values expire without further notifications
+        scheduler.advance(Duration::from_secs(5)).await;
+        let (_, mut rx) = cho.register().await;
+        assert_eq!(rx.recv().await, Some(5));
+        assert!(rx.try_recv().is_err());
+        scheduler.advance(Duration::from_secs(6)).await;
+        let (_, mut rx) = cho.register().await;
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_builder_latest_fn() {
+        let current = Arc::new(AtomicU32::new(0));
+        let c = current.clone();
+        let cho: ChObservable<u32> = ChObservable::builder()
+            .latest_fn(move || match c.load(Ordering::SeqCst) {
+                0 => None,
+                v => Some(v),
+            })
+            .build()
+            .unwrap();
+        let (_, mut rx) = cho.register().await;
+        assert!(rx.try_recv().is_err());
+
+        current.store(7, Ordering::SeqCst);
+        let (_, mut rx) = cho.register().await;
+        assert_eq!(rx.try_recv(), Ok(7));
+        cho.notify(&8).await.unwrap();
+        assert_eq!(rx.recv().await, Some(8));
+
+        // the replay buffer is preferred
+        let cho: ChObservable<u32> = ChObservable::builder()
+            .replay(1)
+            .latest_fn(|| Some(1))
+            .build()
+            .unwrap();
+        cho.notify(&2).await.unwrap();
+        let (_, mut rx) = cho.register().await;
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_builder_summarizer() {
+        let cho: ChObservable<Vec<u8>> = ChObservable::builder()
+            .summarizer(|v: &Vec<u8>| format!("{} bytes, first={:?}", v.len(), v.first()))
+            .build()
+            .unwrap();
+        assert_eq!(cho.summary(&vec![7; 1024]).unwrap(), "1024 bytes, first=Some(7)");
+        let cho: ChObservable<String> = ChObservable::builder()
+            .summarizer(|v: &String| v.clone())
+            .build()
+            .unwrap();
+        let summary = cho.summary(&"ü".repeat(1000)).unwrap();
+        assert_eq!(summary, format!("{}...", "ü".repeat(120)));
+        assert_eq!(ChObservable::<u32>::new().summary(&1), None);
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        let r = ChObservable::<u32>::builder().capacity(0).build();
+        assert!(matches!(r, Err(Error::Validation(_))));
+        let r = ChObservable::<u32>::builder().name("").build();
+        assert!(matches!(r, Err(Error::Validation(_))));
+    }
+}
@@ -0,0 +1,100 @@
+//! Registration and removal of many observers with one lock of the state
+
+use tokio::sync::mpsc::{self, Receiver};
+
+use super::{ChObservable, Lifecycle, StoredObserver, Target};
+use crate::error::Result;
+use crate::observer_id::ObserverId;
+
+impl<T: Clone> ChObservable<T> {
+    /// This function registers `n` new observers, e.g. for a worker pool.
+    /// All of them are added with one lock of the observers. It returns
+    /// the IDs of the registered observers together with their channel
+    /// receivers.
+    ///
+    /// ## Arguments
+    /// * `n` - number of observers to register
+    ///
+    pub async fn register_many(&self, n: usize) -> Vec<(ObserverId, Receiver<T>)> {
+        let mut ret = Vec::with_capacity(n);
+        let mut attached = true;
+        {
+            let mut g = self.state.write().await;
+            for _ in 0..n {
+                let id = self.next_observer_id();
+                let (tx, rx) = mpsc::channel(self.config.capacity);
+                attached &= self.attach(&mut g, StoredObserver::new(id, Target::Channel(tx)));
+                ret.push((id, rx));
+            }
+        }
+        if !attached {
+            // the receivers of a closed observable end right away
+            return ret;
+        }
+        self.log(format_args!("register observers: count={}", n));
+        for (id, _) in &ret {
+            self.emit_lifecycle(Lifecycle::Subscribed(*id)).await;
+        }
+        ret
+    }
+
+    /// This function unregisters many observers with one lock of the
+    /// observers. It fails without removing any observer if an ID was
+    /// created by another observable, unknown IDs are ignored.
+    ///
+    /// ## Arguments
+    /// * `observer_ids` - IDs returned after the registration of the observers
+    ///
+    pub async fn unregister_many(&self, observer_ids: &[ObserverId]) -> Result<()> {
+        for id in observer_ids {
+            id.check_tag(self.tag)?;
+        }
+        let mut removed = Vec::new();
+        {
+            let mut g = self.state.write().await;
+            g.observers.retain(|o| {
+                let keep = !observer_ids.contains(&o.id);
+                if !keep {
+                    removed.push(o.id);
+                }
+                keep
+            });
+        }
+        self.log(format_args!("unregister observers: count={}", removed.len()));
+        for id in removed {
+            self.emit_lifecycle(Lifecycle::Unsubscribed(id)).await;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::ChObservable;
+    use crate::error::Error;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_many() {
+        let cho: ChObservable<u32> = ChObservable::builder().replay(1).build().unwrap();
+        cho.notify(&1).await.unwrap();
+        let mut workers = cho.register_many(500).await;
+        assert_eq!(workers.len(), 500);
+        cho.notify(&2).await.unwrap();
+        for (_, rx) in workers.iter_mut() {
+            assert_eq!(rx.recv().await, Some(1));
+            assert_eq!(rx.recv().await, Some(2));
+        }
+
+        let other: ChObservable<u32> = ChObservable::new();
+        let (foreign, _) = other.register().await;
+        assert_eq!(
+            cho.unregister_many(&[workers[0].0, foreign]).await,
+            Err(Error::ForeignObserverId(foreign))
+        );
+        let ids: Vec<_> = workers.iter().skip(1).map(|(id, _)| *id).collect();
+        cho.unregister_many(&ids).await.unwrap();
+        cho.notify(&3).await.unwrap();
+        assert_eq!(workers[0].1.recv().await, Some(3));
+        assert_eq!(workers[1].1.recv().await, None);
+    }
+}
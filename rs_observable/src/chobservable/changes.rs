@@ -0,0 +1,54 @@
+//! Observers of a ChObservedValue, that get the former value with every
+//! change
+
+use std::sync::{Arc, Mutex};
+
+use super::queue::QueueSender;
+use super::{ChObservedValue, QueueReceiver};
+use crate::change::Change;
+use crate::observer_id::ObserverId;
+
+impl<T: Clone + Send + Sync + 'static> ChObservedValue<T> {
+    /// This function registers a new observer, that gets the former value
+    /// together with the new one. The current value is the first former
+    /// value. The changes are buffered without a limit, so the former
+    /// values stay in order even for a slow observer. It returns the ID of
+    /// the registered observer and the receiver of the changes.
+    pub async fn register_change(&mut self) -> (ObserverId, QueueReceiver<Change<T>>) {
+        let (current, observable) = self.lock_current().await;
+        let last = Mutex::new(current);
+        let (tx, rx) = QueueSender::new(None);
+        let tx = Arc::new(tx);
+        let probe = tx.clone();
+        let id = observable
+            .add_forwarder(
+                move |v| {
+                    let change = Change::advance(&mut last.lock().unwrap(), v);
+                    let _ = tx.send(change);
+                },
+                move || probe.is_closed(),
+            )
+            .await;
+        (id, rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::change::Change;
+    use crate::chobservable::ChObservedValue;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_change() {
+        let mut value: ChObservedValue<u32> = ChObservedValue::new();
+        value.set_value(&1).await.unwrap();
+        let (id, mut rx) = value.register_change().await;
+        value.set_value(&2).await.unwrap();
+        value.reset_value().await.unwrap();
+
+        assert_eq!(rx.recv().await, Some(Change { old: Some(1), new: Some(2) }));
+        assert_eq!(rx.recv().await, Some(Change { old: Some(2), new: None }));
+        value.unregister(id).await.unwrap();
+        assert_eq!(rx.recv().await, None);
+    }
+}
@@ -0,0 +1,157 @@
+//! Observers that are only active while another observed value is true
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+
+use tokio::sync::mpsc::{self, Receiver, WeakSender};
+use tokio::sync::RwLock;
+
+use super::{ChObservable, ChObservedValue, Spawner, State, StoredObserver, Target};
+use crate::observer_id::ObserverId;
+
+/// Observer of `subscribe_while`, that is moved out of the observers while
+/// the condition is false and back when it's true again
+struct Switch<T> {
+    state: Weak<RwLock<State<T>>>,
+    spawner: Spawner,
+    id: ObserverId,
+    /// Last value of the condition
+    active: AtomicBool,
+    /// The observer, while the condition is false
+    parked: std::sync::Mutex<Option<Arc<StoredObserver<T>>>>,
+    /// Sender of the observer, to detect the end of the subscription
+    tx: WeakSender<T>,
+}
+
+impl<T: Send + 'static> Switch<T> {
+    /// Follows a change of the condition. If the observers are locked by
+    /// another task, the observer is moved in a spawned task.
+    fn set(self: &Arc<Self>, active: bool) {
+        self.active.store(active, Ordering::SeqCst);
+        let Some(state) = self.state.upgrade() else {
+            return;
+        };
+        if let Ok(mut g) = state.try_write() {
+            self.apply(&mut g);
+            return;
+        }
+        let switch = self.clone();
+        self.spawner.try_spawn(async move {
+            switch.apply(&mut *state.write().await);
+        });
+    }
+
+    /// Moves the observer into the observers or out of them, depending on
+    /// the last value of the condition
+    fn apply(&self, state: &mut State<T>) {
+        let mut parked = self.parked.lock().unwrap();
+        if state.closed {
+            // the observer ends with the closed observable
+            parked.take();
+        } else if self.active.load(Ordering::SeqCst) {
+            if let Some(o) = parked.take() {
+                state.observers.insert(o.id, o);
+            }
+        } else if let Some(o) = state.observers.remove(self.id) {
+            *parked = Some(o);
+        }
+    }
+
+    /// Returns true after the subscription ended, because the receiver or
+    /// the observable was dropped, or the observer was unregistered
+    fn is_closed(&self) -> bool {
+        self.state.strong_count() == 0 || self.tx.upgrade().is_none_or(|tx| tx.is_closed())
+    }
+}
+
+impl<T: Clone + Send + 'static> ChObservable<T> {
+    /// This function registers a new observer, that gets only the values
+    /// which are notified while the condition is true. An unset condition
+    /// counts as false. The observer is removed from the observers while
+    /// the condition is false and added again when it becomes true, inline
+    /// with the change of the condition. So a value notified after
+    /// `set_value(&true)` returned is passed to the observer, unless the
+    /// observers were locked by another task at the change. The
+    /// subscription ends with the drop of the receiver, the condition gets
+    /// rid of its observer with the next change then. It returns the ID of
+    /// the registered observer and a channel receiver to get the new values.
+    ///
+    /// ## Arguments
+    /// * `cond` - decides if the observer gets values
+    ///
+    pub async fn subscribe_while(
+        &self,
+        cond: &mut ChObservedValue<bool>,
+    ) -> (ObserverId, Receiver<T>) {
+        let (tx, rx) = mpsc::channel(self.config.capacity);
+        let weak_tx = tx.downgrade();
+        let (active, observable) = cond.lock_current().await;
+        let active = active.unwrap_or(false);
+        let switch = Arc::new(Switch {
+            state: Arc::downgrade(&self.state),
+            spawner: self.spawner().clone(),
+            id: self.add_observer(Target::Channel(tx)).await,
+            active: AtomicBool::new(active),
+            parked: std::sync::Mutex::new(None),
+            tx: weak_tx,
+        });
+        switch.apply(&mut *self.state.write().await);
+        let (id, s) = (switch.id, switch.clone());
+        observable
+            .add_forwarder(
+                move |v: Option<bool>| s.set(v.unwrap_or(false)),
+                move || switch.is_closed(),
+            )
+            .await;
+        (id, rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::{ChObservable, ChObservedValue};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_subscribe_while() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let mut online: ChObservedValue<bool> = ChObservedValue::new();
+        let (_, mut rx) = cho.subscribe_while(&mut online).await;
+        cho.notify(&1).await.unwrap();
+        online.set_value(&true).await.unwrap();
+        cho.notify(&2).await.unwrap();
+        online.set_value(&false).await.unwrap();
+        cho.notify(&3).await.unwrap();
+        online.set_value(&true).await.unwrap();
+        cho.notify(&4).await.unwrap();
+        online.reset_value().await.unwrap();
+        cho.notify(&5).await.unwrap();
+        assert_eq!(rx.recv().await, Some(2));
+        assert_eq!(rx.recv().await, Some(4));
+        assert!(rx.try_recv().is_err());
+
+        let (_, mut rx) = cho.subscribe_while(&mut online).await;
+        online.set_value(&true).await.unwrap();
+        let (_, mut rx2) = cho.subscribe_while(&mut online).await;
+        cho.notify(&6).await.unwrap();
+        assert_eq!(rx.recv().await, Some(6));
+        assert_eq!(rx2.recv().await, Some(6));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_subscribe_while_registration() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let mut online: ChObservedValue<bool> = ChObservedValue::new();
+        let (_, rx) = cho.subscribe_while(&mut online).await;
+        assert!(cho.state.read().await.observers.is_empty());
+        online.set_value(&true).await.unwrap();
+        assert_eq!(cho.state.read().await.observers.len(), 1);
+        online.set_value(&false).await.unwrap();
+        assert!(cho.state.read().await.observers.is_empty());
+
+        // the condition removes its observer after the subscription ended
+        drop(rx);
+        let report = online.set_value(&true).await.unwrap();
+        assert!(report.delivered.is_empty());
+        assert!(online.observable.lock().await.state.read().await.observers.is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+//! Notification with a bounded time for the fan-out
+
+use std::time::Duration;
+
+use tokio::time::{timeout_at, Instant};
+
+use super::{ChObservable, DeliveryReport, SendStrategy};
+use crate::error::{Error, Result};
+
+impl<T: Clone> ChObservable<T> {
+    /// Notifies as many observers as possible before the deadline. Observers
+    /// are served in the order of registration, also with
+    /// `OverflowPolicy::WeightedFair`, the ones that could not be reached
+    /// in time are skipped. A full channel is waited for until
+    /// the deadline, if the overflow policy says so, and
+    /// `Error::SendTimeout` is passed to the error receivers if it stays
+    /// full. With the other policies the observer is skipped without
+    /// waiting, with `OverflowPolicy::Error` it fails. Observers with a
+    /// dropped receiver are listed as failed and unregistered afterwards. Observers of paused
+    /// groups and observers that filter the value out are not part of the
+    /// report. Linked observables get the value like with `notify` until
+    /// the deadline is reached, their observers are not part of the report.
+    /// It fails with `Error::Timeout` if the observers are locked by
+    /// another task until the deadline, no observer got the value then. It
+    /// fails with `Error::Closed` after `drain` was called.
+    ///
+    /// ## Arguments
+    /// * `data` - data that should be passed to the observers
+    /// * `deadline` - point in time, after which no observer is served anymore
+    ///
+    pub async fn notify_deadline(&self, data: &T, deadline: Instant) -> Result<DeliveryReport> {
+        self.log_value(format_args!("received notify request with deadline"), data);
+        let Ok(state) = timeout_at(deadline, self.lock_for_notify()).await else {
+            self.log(format_args!("deadline reached before the observers were locked"));
+            return Err(Error::Timeout);
+        };
+        if state.closed {
+            return Err(Error::Closed);
+        }
+        let strategy = SendStrategy::Until(deadline);
+        Ok(self.notify_locked(state, data, &[&|_| true], strategy).await)
+    }
+
+    /// Notifies the observers like `notify`, but gives each observer only
+    /// the given time to take the value. Observers whose send didn't
+    /// complete in time are skipped, and `Error::SendTimeout` is passed to
+    /// the error receivers for them. So one observer with a full channel
+    /// delays the notification by `timeout` at most. The observers are
+    /// served in the order of registration, also with
+    /// `OverflowPolicy::WeightedFair`. It fails with `Error::Closed` after
+    /// `drain` was called.
+    ///
+    /// ## Arguments
+    /// * `data` - data that should be passed to the observers
+    /// * `send_timeout` - max time to wait for one observer
+    ///
+    pub async fn notify_timeout(&self, data: &T, send_timeout: Duration) -> Result<DeliveryReport> {
+        self.log_value(format_args!("received notify request with timeout"), data);
+        let state = self.lock_for_notify().await;
+        if state.closed {
+            return Err(Error::Closed);
+        }
+        let strategy = SendStrategy::Timeout(send_timeout);
+        Ok(self.notify_locked(state, data, &[&|_| true], strategy).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::chobservable::{ChObservable, Propagation};
+    use crate::error::Error;
+    use crate::testing::TestScheduler;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_notify_deadline() {
+        let scheduler = TestScheduler::new();
+        let cho: ChObservable<u32> = ChObservable::builder().capacity(1).build().unwrap();
+        let (id1, mut rx1) = cho.register().await;
+        let (id2, mut rx2) = cho.register().await;
+        let (id3, rx3) = cho.register().await;
+        drop(rx3);
+
+        let deadline = scheduler.now() + Duration::from_secs(1);
+        let report = cho.notify_deadline(&1, deadline).await.unwrap();
+        assert_eq!(report.delivered, vec![id1, id2]);
+        assert!(report.skipped.is_empty());
+        assert_eq!(report.failed, vec![id3]);
+        assert!(!report.is_complete());
+
+        // the channel of the first observer is full now, it blocks until
+        // the deadline and leaves no time for the second one
+        assert_eq!(rx2.recv().await, Some(1));
+        let deadline = scheduler.now() + Duration::from_secs(1);
+        let report = cho.notify_deadline(&2, deadline).await.unwrap();
+        assert!(report.delivered.is_empty());
+        assert!(report.failed.is_empty());
+        // the observer with the dropped receiver was removed
+        assert_eq!(report.skipped, vec![id1, id2]);
+        assert!(scheduler.elapsed() >= Duration::from_secs(1));
+
+        assert_eq!(rx1.recv().await, Some(1));
+        assert!(rx2.try_recv().is_err());
+        assert!(format!("{:?}", cho).contains("notifications: 2, delivered: 2, failed: 1"));
+
+        cho.drain(Duration::from_secs(1)).await;
+        let res = cho.notify_deadline(&3, scheduler.now() + Duration::from_secs(1)).await;
+        assert_eq!(res, Err(Error::Closed));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_notify_deadline_locked() {
+        let scheduler = TestScheduler::new();
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (_, mut rx) = cho.register().await;
+
+        // another task holds the observers past the deadline, nobody got
+        // the value, so it's no complete report
+        let g = cho.state.write().await;
+        let deadline = scheduler.now() + Duration::from_secs(1);
+        let res = cho.notify_deadline(&1, deadline).await;
+        assert_eq!(res, Err(Error::Timeout));
+        assert!(scheduler.elapsed() >= Duration::from_secs(1));
+        drop(g);
+        assert!(rx.try_recv().is_err());
+
+        let deadline = scheduler.now() + Duration::from_secs(1);
+        let report = cho.notify_deadline(&2, deadline).await.unwrap();
+        assert!(report.is_complete());
+        assert_eq!(rx.try_recv(), Ok(2));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_notify_deadline_linked() {
+        let scheduler = TestScheduler::new();
+        let parent: ChObservable<u32> = ChObservable::new();
+        let child: ChObservable<u32> = ChObservable::new();
+        parent.add_child(&child, Propagation::Bubble).await.unwrap();
+        let (_, mut rx) = parent.register().await;
+
+        let deadline = scheduler.now() + Duration::from_secs(1);
+        let report = child.notify_deadline(&1, deadline).await.unwrap();
+        assert!(report.delivered.is_empty());
+        assert_eq!(rx.try_recv(), Ok(1));
+        child.notify_quorum(&2, 0, Duration::from_secs(1)).await.unwrap();
+        assert_eq!(rx.try_recv(), Ok(2));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_notify_timeout() {
+        let scheduler = TestScheduler::new();
+        let cho: ChObservable<u32> = ChObservable::builder().capacity(1).build().unwrap();
+        let mut errors = cho.errors().await;
+        let (id1, mut rx1) = cho.register().await;
+        let (id2, mut rx2) = cho.register().await;
+        cho.notify(&1).await.unwrap();
+        assert_eq!(rx2.recv().await, Some(1));
+
+        // the channel of the first observer is full, it's skipped after
+        // the timeout and the second one gets the value anyway
+        let report = cho.notify_timeout(&2, Duration::from_millis(100)).await.unwrap();
+        assert_eq!(report.delivered, vec![id2]);
+        assert_eq!(report.skipped, vec![id1]);
+        assert!(scheduler.elapsed() >= Duration::from_millis(100));
+        assert!(scheduler.elapsed() < Duration::from_secs(1));
+        assert_eq!(errors.recv().await, Some(Error::SendTimeout { observer_id: id1 }));
+        assert_eq!(rx2.recv().await, Some(2));
+        assert_eq!(rx1.recv().await, Some(1));
+        assert!(rx1.try_recv().is_err());
+
+        cho.drain(Duration::from_secs(1)).await;
+        let res = cho.notify_timeout(&3, Duration::from_millis(100)).await;
+        assert_eq!(res, Err(Error::Closed));
+    }
+}
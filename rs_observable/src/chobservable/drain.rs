@@ -0,0 +1,313 @@
+//! Shutdown of a ChObservable. It's closed right away, or gracefully by
+//! waiting until the observers have received the values in their channels
+
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::time::{timeout_at, Instant};
+
+use super::queue::QueueWaiter;
+use super::{ChObservable, Lifecycle, Select, SendStrategy, StoredObserver, Target};
+use crate::error::{Error, Result};
+use crate::observer_id::ObserverId;
+
+/// Buffered values of an observer, that are waited for by `drain`
+enum Buffer<T> {
+    Channel(Sender<T>),
+    Queue(QueueWaiter<T>),
+}
+
+impl<T> Buffer<T> {
+    fn of(o: &StoredObserver<T>) -> Option<Self> {
+        match &o.target {
+            Target::Channel(tx) => Some(Buffer::Channel(tx.clone())),
+            Target::Queue(tx) => Some(Buffer::Queue(tx.waiter())),
+            // an unbounded channel has no capacity to check
+            Target::Unbounded(_) | Target::Mapped(_) | Target::Callback(_) | Target::Borrowed(_) => {
+                None
+            }
+        }
+    }
+
+    /// Waits until the receiver got all values or was dropped. A channel
+    /// is empty, when its whole capacity can be reserved.
+    async fn wait_empty(&self) {
+        match self {
+            Buffer::Channel(tx) => {
+                let mut permits = Vec::with_capacity(tx.max_capacity());
+                while permits.len() < tx.max_capacity() {
+                    match tx.reserve().await {
+                        Ok(p) => permits.push(p),
+                        Err(_) => return,
+                    }
+                }
+            }
+            Buffer::Queue(q) => q.wait_empty().await,
+        }
+    }
+
+    /// Returns the number of values that were not received, values of a
+    /// dropped receiver are not counted
+    fn queued(&self) -> usize {
+        match self {
+            Buffer::Channel(tx) if !tx.is_closed() => tx.max_capacity() - tx.capacity(),
+            Buffer::Channel(_) => 0,
+            Buffer::Queue(q) => q.queued(),
+        }
+    }
+}
+
+impl<T: Clone> ChObservable<T> {
+    /// Closes the observable. All observers are removed, so their
+    /// receivers get `None` after the values that are still in their
+    /// channels, the broadcast receivers get `RecvError::Closed`. This tells them that the stream is finished, instead of
+    /// waiting for more values. Later notify calls fail with
+    /// `Error::Closed`, later registrations get a receiver that ends after
+    /// the replayed values. The completion value of
+    /// `ChObservableBuilder::completion` is notified before, no other
+    /// notification can follow it. The lifecycle observers get
+    /// `Lifecycle::Closed` once, it isn't passed again when the observable
+    /// is dropped. It returns the number of removed observers.
+    pub async fn close(&self) -> usize {
+        let mut state = self.state.write().await;
+        let closing = !std::mem::replace(&mut state.closed, true);
+        match self.completion.as_ref().filter(|_| closing) {
+            Some(v) => {
+                self.adapt(&mut state);
+                let passes: [&Select<'_, T>; 1] = [&|_| true];
+                self.notify_locked(state.downgrade(), v, &passes, SendStrategy::Wait)
+                    .await;
+            }
+            None => drop(state),
+        }
+        let n = self.clear().await;
+        if !self.closed_event.swap(true, Ordering::Relaxed) {
+            self.emit_lifecycle(Lifecycle::Closed).await;
+        }
+        self.log(format_args!("closed"));
+        n
+    }
+
+    /// Returns true after the observable was closed by `close`, `drain`
+    /// or `auto_close_when_idle`
+    pub async fn is_closed(&self) -> bool {
+        self.state.read().await.closed
+    }
+
+    /// This function registers a new observer like `register`. It fails
+    /// with `Error::Closed` if the observable is closed.
+    pub async fn try_register(&self) -> Result<(ObserverId, Receiver<T>)> {
+        if self.is_closed().await {
+            return Err(Error::Closed);
+        }
+        Ok(self.register().await)
+    }
+
+    /// Stops to accept notifications and waits until all values in the
+    /// observer channels and queues are received, or the timeout expires.
+    /// Later notify calls fail with `Error::Closed`. It returns the number
+    /// of values that were not received in time, values for dropped
+    /// receivers are not counted.
+    ///
+    /// ## Arguments
+    /// * `timeout` - max time to wait for the observers
+    ///
+    pub async fn drain(&self, timeout: Duration) -> usize {
+        let deadline = Instant::now() + timeout;
+        let buffers: Vec<Buffer<T>> = {
+            let mut state = self.state.write().await;
+            state.closed = true;
+            state.observers.iter().filter_map(|o| Buffer::of(o)).collect()
+        };
+        self.log(format_args!("drain observers ..."));
+        let received = async {
+            for b in buffers.iter() {
+                b.wait_empty().await;
+            }
+        };
+        // the reserved capacity is released with the timeout
+        let _ = timeout_at(deadline, received).await;
+        let queued = buffers.iter().map(Buffer::queued).sum();
+        self.log(format_args!("drained, undelivered={}", queued));
+        queued
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::chobservable::{ChObservable, Lifecycle};
+    use crate::error::Error;
+    use crate::testing::TestScheduler;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_drain() {
+        let scheduler = TestScheduler::new();
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (_, mut fast) = cho.register().await;
+        let (_, mut slow) = cho.register().await;
+        let (_, dropped) = cho.register().await;
+        drop(dropped);
+        cho.register_callback(|_| ()).await;
+        let (_, mut queue) = cho.register_drop_oldest().await;
+        for i in 0..3 {
+            let _ = cho.notify(&i).await;
+        }
+
+        let consumer = tokio::spawn(async move {
+            let mut received = Vec::new();
+            while let Some(v) = fast.recv().await {
+                received.push(v);
+            }
+            received
+        });
+        // the slow observer receives only one of its three values in time
+        let slow_consumer = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            let v = slow.recv().await;
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            v
+        });
+        // the queue is drained as well, it keeps two values
+        let queue_consumer = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            let v = queue.recv().await;
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            v
+        });
+        assert_eq!(cho.drain(Duration::from_secs(5)).await, 4);
+        assert!(scheduler.elapsed() >= Duration::from_secs(5));
+        assert_eq!(cho.notify(&4).await, Err(Error::Closed));
+        assert_eq!(cho.notify_deadline(&4, scheduler.now()).await, Err(Error::Closed));
+
+        drop(cho);
+        assert_eq!(consumer.await.unwrap(), vec![0, 1, 2]);
+        assert_eq!(slow_consumer.await.unwrap(), Some(0));
+        assert_eq!(queue_consumer.await.unwrap(), Some(0));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_drain_empty() {
+        let scheduler = TestScheduler::new();
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (_, mut rx) = cho.register().await;
+        cho.notify(&1).await.unwrap();
+        assert_eq!(rx.recv().await, Some(1));
+        assert_eq!(cho.drain(Duration::from_secs(5)).await, 0);
+        assert_eq!(scheduler.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_drain_waits_for_receiver() {
+        let scheduler = TestScheduler::new();
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (_, mut rx) = cho.register().await;
+        let (_, mut queue) = cho.register_drop_oldest().await;
+        cho.notify(&1).await.unwrap();
+        let consumer = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            assert_eq!(rx.recv().await, Some(1));
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            assert_eq!(queue.recv().await, Some(1));
+            (rx, queue)
+        });
+        assert_eq!(cho.drain(Duration::from_secs(5)).await, 0);
+        assert!(scheduler.elapsed() < Duration::from_secs(3));
+        consumer.await.unwrap();
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_close() {
+        let cho: ChObservable<u32> = ChObservable::builder().replay(1).build().unwrap();
+        let (_, mut rx) = cho.register().await;
+        let (_, mut queue) = cho.register_unbounded().await;
+        cho.notify(&1).await.unwrap();
+        assert!(!cho.is_closed().await);
+        assert_eq!(cho.close().await, 2);
+        assert!(cho.is_closed().await);
+
+        // the streams end after the queued values
+        assert_eq!(rx.recv().await, Some(1));
+        assert_eq!(rx.recv().await, None);
+        assert_eq!(queue.recv().await, Some(1));
+        assert_eq!(queue.recv().await, None);
+        assert_eq!(cho.notify(&2).await, Err(Error::Closed));
+        assert_eq!(cho.try_register().await.unwrap_err(), Error::Closed);
+
+        // late observers get the replayed values and the end
+        let (_, mut late) = cho.register().await;
+        assert_eq!(late.recv().await, Some(1));
+        assert_eq!(late.recv().await, None);
+        assert_eq!(cho.observer_count().await, 0);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_completion() {
+        let cho: ChObservable<Option<u32>> =
+            ChObservable::builder().completion(None).build().unwrap();
+        let (_, mut rx) = cho.register().await;
+        let (tx, seen) = std::sync::mpsc::channel();
+        cho.register_callback(move |v| tx.send(v).unwrap()).await;
+        cho.notify(&Some(1)).await.unwrap();
+
+        // the completion value is the last one before the end
+        drop(cho);
+        assert_eq!(rx.recv().await, Some(Some(1)));
+        assert_eq!(rx.recv().await, Some(None));
+        assert_eq!(rx.recv().await, None);
+        assert_eq!(seen.try_iter().collect::<Vec<_>>(), vec![Some(1), None]);
+
+        let cho: ChObservable<Option<u32>> =
+            ChObservable::builder().completion(None).build().unwrap();
+        let (_, mut rx) = cho.register().await;
+        cho.close().await;
+        drop(cho);
+        // it's passed only once
+        assert_eq!(rx.recv().await, Some(None));
+        assert_eq!(rx.recv().await, None);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_completion_is_last() {
+        let cho: Arc<ChObservable<Option<u32>>> = Arc::new(
+            ChObservable::builder()
+                .capacity(1)
+                .completion(None)
+                .build()
+                .unwrap(),
+        );
+        let (_, mut rx) = cho.register().await;
+        cho.notify(&Some(1)).await.unwrap();
+        let closing = tokio::spawn({
+            let cho = cho.clone();
+            async move { cho.close().await }
+        });
+        tokio::task::yield_now().await;
+        // the completion waits for the full channel, the observable is
+        // already closed for other notifications
+        assert!(!closing.is_finished());
+        assert_eq!(cho.notify(&Some(2)).await, Err(Error::Closed));
+        assert_eq!(rx.recv().await, Some(Some(1)));
+        assert_eq!(rx.recv().await, Some(None));
+        assert_eq!(rx.recv().await, None);
+        assert_eq!(closing.await.unwrap(), 1);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_close_lifecycle() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (_, mut events) = cho.lifecycle().register().await;
+        let (id, _rx) = cho.register().await;
+        cho.close().await;
+        cho.close().await;
+        drop(cho);
+        assert_eq!(events.recv().await, Some(Lifecycle::Subscribed(id)));
+        assert_eq!(events.recv().await, Some(Lifecycle::Unsubscribed(id)));
+        // passed once, not again by the second close or the drop
+        assert_eq!(events.recv().await, Some(Lifecycle::Closed));
+        assert_eq!(events.recv().await, None);
+    }
+}
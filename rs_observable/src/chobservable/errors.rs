@@ -0,0 +1,109 @@
+//! Side channel that reports the delivery errors of a ChObservable, so
+//! they can be monitored apart from the results of the notify calls
+
+use tokio::sync::mpsc::Receiver;
+
+use super::{ChObservable, Config, OverflowPolicy};
+use crate::error::Error;
+
+/// Capacity of the error channels
+const ERRORS_CAPACITY: usize = 64;
+
+impl<T: Clone> ChObservable<T> {
+    /// Returns a receiver that gets the errors of this observable, e.g.
+    /// failed deliveries, overflows, timed out sends, pruned observers and
+    /// deliveries that were vetoed by the interceptor. The channel is
+    /// sticky, a new receiver gets the latest error that was reported
+    /// before its creation first.
+    ///
+    /// Errors are reported without waiting, they are lost for receivers
+    /// with a full channel. While the error receivers are locked, e.g. by
+    /// the creation of another receiver, an error is reported by a task of
+    /// the spawner of the observable.
+    pub async fn errors(&self) -> Receiver<Error> {
+        let config = &self.config;
+        let errors = self.errors.get_or_init(|| {
+            ChObservable::with_config(Config {
+                name: config.name.as_ref().map(|n| format!("{}.errors", n)),
+                capacity: ERRORS_CAPACITY,
+                overflow_policy: OverflowPolicy::DropNewest,
+                replay_depth: 1,
+                replay_max_age: None,
+                log_level: config.log_level,
+                spawner: config.spawner.clone(),
+            })
+        });
+        errors.register().await.1
+    }
+
+    /// Passes an error to the error receivers, if there are any
+    pub(super) fn report_error(&self, e: &Error) {
+        let Some(errors) = self.errors.get() else {
+            return;
+        };
+        if errors.try_notify(e) != Err(Error::WouldBlock) {
+            return;
+        }
+        let (errors, e) = (self.errors.clone(), e.clone());
+        self.config.spawner.try_spawn(async move {
+            if let Some(errors) = errors.get() {
+                let _ = errors.notify(&e).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::{ChObservable, OverflowPolicy};
+    use crate::error::Error;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_errors() {
+        let cho: ChObservable<u32> = ChObservable::builder()
+            .capacity(1)
+            .overflow_policy(OverflowPolicy::Error)
+            .build()
+            .unwrap();
+        let mut errors = cho.errors().await;
+        let (id1, rx1) = cho.register().await;
+        drop(rx1);
+        assert_eq!(cho.notify(&1).await.unwrap().failed, vec![id1]);
+        assert_eq!(errors.recv().await, Some(Error::Delivery { observer_id: id1 }));
+        assert_eq!(errors.recv().await, Some(Error::Pruned { observer_id: id1 }));
+
+        cho.unregister(id1).await.unwrap();
+        let (id2, _rx2) = cho.register().await;
+        cho.notify(&2).await.unwrap();
+        let _ = cho.notify(&3).await;
+        assert_eq!(errors.recv().await, Some(Error::Overflow { observer_id: id2 }));
+
+        // a late receiver gets the latest error
+        let mut late = cho.errors().await;
+        assert_eq!(late.recv().await, Some(Error::Overflow { observer_id: id2 }));
+        assert!(late.try_recv().is_err());
+        assert!(errors.try_recv().is_err());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_errors_locked() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let mut errors = cho.errors().await;
+        let (id, rx) = cho.register().await;
+        drop(rx);
+        // the errors are passed later, not lost
+        let g = cho.errors.get().unwrap().state.write().await;
+        assert_eq!(cho.notify(&1).await.unwrap().failed, vec![id]);
+        assert!(errors.try_recv().is_err());
+        drop(g);
+        let mut received = vec![errors.recv().await.unwrap(), errors.recv().await.unwrap()];
+        received.sort_by_key(|e| e.to_string());
+        assert_eq!(
+            received,
+            vec![
+                Error::Delivery { observer_id: id },
+                Error::Pruned { observer_id: id }
+            ]
+        );
+    }
+}
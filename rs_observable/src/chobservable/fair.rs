@@ -0,0 +1,142 @@
+//! Weighted fair delivery to observers with full channels, it's used with
+//! `OverflowPolicy::WeightedFair`
+//!
+//! Observers with free capacity always get the value. Every observer with
+//! a full channel collects its weight as credit. If the credit reaches the
+//! highest weight of the notified observers, notify waits for free
+//! capacity and the credit is reduced again, otherwise the value is dropped
+//! for this observer. The waiting starts with the highest weights.
+
+use std::cmp::Reverse;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver};
+
+use super::{ChObservable, DeliveryReport, SendStrategy, StoredObserver, Target};
+use crate::error::Error;
+use crate::observer_id::ObserverId;
+
+impl<T: Clone> ChObservable<T> {
+    /// This function registers a new observer with a weight, that is used
+    /// by `OverflowPolicy::WeightedFair`. It returns the ID of the registered
+    /// observer and a channel receiver to get the new values. Observers of
+    /// the other registration functions have the weight 1.
+    ///
+    /// ## Arguments
+    /// * `weight` - share of the observer, if its channel is full, values
+    ///   below 1 are handled as 1
+    ///
+    pub async fn register_weighted(&self, weight: u32) -> (ObserverId, Receiver<T>) {
+        let (tx, rx) = mpsc::channel(self.config.capacity);
+        let id = self
+            .add_observer_with(Target::Channel(tx), |o| o.weight = weight.max(1))
+            .await;
+        (id, rx)
+    }
+
+    pub(super) async fn deliver_fair(
+        &self,
+        targets: &[Arc<StoredObserver<T>>],
+        data: &T,
+    ) -> DeliveryReport {
+        let mut order: Vec<&StoredObserver<T>> = targets.iter().map(|o| &**o).collect();
+        order.sort_by_key(|o| Reverse(o.weight));
+        let quantum = order.iter().map(|o| o.weight as u64).max().unwrap_or(1);
+
+        let mut report = DeliveryReport::default();
+        let mut full = Vec::new();
+        for o in order {
+            // callbacks, queues and observers with an own policy don't use
+            // the weight
+            let (Target::Channel(tx), None) = (&o.target, o.overflow_policy) else {
+                report.push_result(o, &self.deliver(o, data, SendStrategy::Wait).await);
+                continue;
+            };
+            let delivered = match tx.try_send(data.clone()) {
+                Ok(()) => Ok(true),
+                Err(TrySendError::Full(v)) => {
+                    full.push((o, v));
+                    continue;
+                }
+                Err(TrySendError::Closed(_)) => Err(()),
+            };
+            self.fair_result(&mut report, o, delivered);
+        }
+
+        for (o, v) in full {
+            let credit = o.credit.fetch_add(o.weight as u64, Ordering::Relaxed) + o.weight as u64;
+            if credit < quantum {
+                self.log(format_args!("channel is full, drop value: id={}", o.label()));
+                self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+                self.fair_result(&mut report, o, Ok(false));
+                continue;
+            }
+            o.credit.fetch_sub(quantum, Ordering::Relaxed);
+            if let Target::Channel(tx) = &o.target {
+                let delivered = tx.send(v).await.map(|()| true).map_err(|_| ());
+                self.fair_result(&mut report, o, delivered);
+            }
+        }
+        report
+    }
+
+    /// Adds the result of a delivery to the report, a failed delivery is
+    /// counted and passed to the error receivers
+    fn fair_result(
+        &self,
+        report: &mut DeliveryReport,
+        o: &StoredObserver<T>,
+        delivered: std::result::Result<bool, ()>,
+    ) {
+        match delivered {
+            Ok(true) => {
+                self.delivered(o);
+                report.push_delivered(o);
+            }
+            Ok(false) => report.push_skipped(o),
+            Err(()) => {
+                self.stats.failed.fetch_add(1, Ordering::Relaxed);
+                self.report_error(&Error::Delivery { observer_id: o.id });
+                report.push_failed(o);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::{ChObservable, OverflowPolicy};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_weighted_fair() {
+        let cho: ChObservable<u32> = ChObservable::builder()
+            .capacity(1)
+            .overflow_policy(OverflowPolicy::WeightedFair)
+            .build()
+            .unwrap();
+        let (_, low) = cho.register_weighted(1).await;
+        let (_, mut high) = cho.register_weighted(2).await;
+
+        // the consumer of the low weighted observer only runs while notify
+        // waits for its channel, so the channel is full at every notify
+        // after the first one. It gets only every second value.
+        let low = tokio::spawn(async move {
+            let mut low = low;
+            let mut received = Vec::new();
+            while let Some(v) = low.recv().await {
+                received.push(v);
+            }
+            received
+        });
+        let mut high_received = Vec::new();
+        for i in 1..=9 {
+            cho.notify(&i).await.unwrap();
+            high_received.push(high.try_recv().unwrap());
+        }
+        drop(cho);
+        assert_eq!(high_received, (1..=9).collect::<Vec<_>>());
+        assert_eq!(low.await.unwrap(), vec![1, 3, 5, 7, 9]);
+    }
+}
@@ -0,0 +1,133 @@
+//! Async observable boolean, e.g. a readiness flag
+
+use std::fmt::{self, Debug, Formatter};
+
+use tokio::sync::mpsc::Receiver;
+
+use super::ChObservedValueInit;
+use crate::error::Result;
+use crate::observer_id::ObserverId;
+
+/// Observable boolean, that can be shared between tasks, e.g. in an
+/// `Arc`. The observers are only notified, when the flag changes.
+pub struct ChObservedFlag(ChObservedValueInit<bool>);
+
+impl Debug for ChObservedFlag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ChObservedFlag").field(&self.0).finish()
+    }
+}
+
+impl Default for ChObservedFlag {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl ChObservedFlag {
+    /// Creates an new object
+    ///
+    /// ## Arguments
+    /// * `value` - initial state
+    ///
+    pub fn new(value: bool) -> Self {
+        ChObservedFlag(ChObservedValueInit::new(value))
+    }
+
+    /// Returns the current state
+    pub async fn get(&self) -> bool {
+        self.0.get().await
+    }
+
+    /// Sets the flag. It returns true if it was changed. The flag is set
+    /// in any case, the notification fails like `ChObservable::notify`.
+    ///
+    /// ## Arguments
+    /// * `v` - new state
+    ///
+    pub async fn set(&self, v: bool) -> Result<bool> {
+        self.0.set_value_if_changed(&v).await
+    }
+
+    /// Sets the flag to true, like `set`
+    pub async fn set_true(&self) -> Result<bool> {
+        self.set(true).await
+    }
+
+    /// Sets the flag to false, like `set`
+    pub async fn set_false(&self) -> Result<bool> {
+        self.set(false).await
+    }
+
+    /// Inverts the flag and returns the new state. The flag is inverted
+    /// in any case, the notification fails like `ChObservable::notify`.
+    pub async fn toggle(&self) -> Result<bool> {
+        let mut new_v = false;
+        self.0
+            .update(|v| {
+                *v = !*v;
+                new_v = *v;
+            })
+            .await?;
+        Ok(new_v)
+    }
+
+    /// Waits until the flag has the given state. It returns right away if
+    /// it's already in that state. It fails like
+    /// `ChObservedValueInit::wait_for`.
+    ///
+    /// ## Arguments
+    /// * `v` - state to wait for
+    ///
+    pub async fn wait_until(&self, v: bool) -> Result<()> {
+        self.0.wait_for(move |f| *f == v).await.map(drop)
+    }
+
+    /// This function registers a new observer. It returns the ID of the registered
+    /// observer and a channel receiver to get the new states
+    pub async fn register(&self) -> (ObserverId, Receiver<bool>) {
+        self.0.register().await
+    }
+
+    /// This function registers a new observer, whose receiver gets the
+    /// current state as first message
+    pub async fn register_with_current(&self) -> (ObserverId, Receiver<bool>) {
+        self.0.register_with_current().await
+    }
+
+    /// This function unregisters an observer. It fails if the ID was
+    /// created by another observable.
+    ///
+    /// ## Arguments
+    /// * `observer_id` - ID returned after the registration of an observer
+    ///
+    pub async fn unregister(&self, observer_id: ObserverId) -> Result<()> {
+        self.0.unregister(observer_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::chobservable::ChObservedFlag;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_observed_flag() {
+        let ready = Arc::new(ChObservedFlag::default());
+        let (_, mut rx) = ready.register().await;
+        let r = ready.clone();
+        let waiting = tokio::spawn(async move { r.wait_until(true).await });
+        tokio::task::yield_now().await;
+
+        assert_eq!(ready.set_true().await, Ok(true));
+        assert_eq!(ready.set_true().await, Ok(false));
+        assert_eq!(waiting.await.unwrap(), Ok(()));
+        // already in the state
+        assert_eq!(ready.wait_until(true).await, Ok(()));
+        assert_eq!(ready.toggle().await, Ok(false));
+        assert!(!ready.get().await);
+        assert_eq!(rx.recv().await, Some(true));
+        assert_eq!(rx.recv().await, Some(false));
+    }
+}
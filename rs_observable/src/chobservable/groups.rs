@@ -0,0 +1,172 @@
+//! Named groups of observers, that can be notified, paused and
+//! unregistered together
+
+use tokio::sync::mpsc::{self, Receiver};
+
+use super::{
+    ChObservable, DeliveryReport, Lifecycle, SendStrategy, State, StoredObserver, Target,
+};
+use crate::error::{Error, Result};
+use crate::observer_id::ObserverId;
+
+impl<T: Clone> ChObservable<T> {
+    /// This function registers a new observer as member of a group. It
+    /// returns the ID of the registered observer and a channel receiver to
+    /// get the new values. The observer gets the values of `notify` as
+    /// well as the ones of `notify_group` for its group.
+    ///
+    /// ## Arguments
+    /// * `group` - name of the group, e.g. "ui"
+    ///
+    pub async fn register_in_group(
+        &self,
+        group: impl Into<String>,
+    ) -> (ObserverId, Receiver<T>) {
+        let group = group.into();
+        let (tx, rx) = mpsc::channel(self.config.capacity);
+        let id = self
+            .add_observer_with(Target::Channel(tx), |o| o.group = Some(group))
+            .await;
+        (id, rx)
+    }
+
+    /// Notifies only the observers of a group. Apart from that it works
+    /// like `notify`, the value is kept for replay and passed to the
+    /// broadcast observers and the linked observables. Nothing is notified
+    /// while the group is paused. It fails like `notify`.
+    ///
+    /// ## Arguments
+    /// * `group` - name of the group
+    /// * `data` - data that should be passed to the observers
+    ///
+    pub async fn notify_group(&self, group: &str, data: &T) -> Result<DeliveryReport> {
+        self.log_value(format_args!("received notify request for group: {}", group), data);
+        let state = self.lock_for_notify().await;
+        if state.closed {
+            return Err(Error::Closed);
+        }
+        if state.paused_groups.contains(group) {
+            self.log(format_args!("group is paused: {}", group));
+            return Ok(DeliveryReport::default());
+        }
+        let in_group = |o: &StoredObserver<T>| o.group.as_deref() == Some(group);
+        Ok(self.notify_locked(state, data, &[&in_group], SendStrategy::Wait).await)
+    }
+
+    /// Stops the notifications for the observers of a group, until
+    /// `resume_group` is called. Values that are notified in between don't
+    /// reach the observers of the group.
+    ///
+    /// ## Arguments
+    /// * `group` - name of the group
+    ///
+    pub async fn pause_group(&self, group: &str) {
+        self.log(format_args!("pause group: {}", group));
+        self.state.write().await.paused_groups.insert(group.to_string());
+    }
+
+    /// Continues the notifications for the observers of a paused group
+    ///
+    /// ## Arguments
+    /// * `group` - name of the group
+    ///
+    pub async fn resume_group(&self, group: &str) {
+        self.log(format_args!("resume group: {}", group));
+        self.state.write().await.paused_groups.remove(group);
+    }
+
+    /// Returns true if the group is paused
+    ///
+    /// ## Arguments
+    /// * `group` - name of the group
+    ///
+    pub async fn is_group_paused(&self, group: &str) -> bool {
+        self.state.read().await.paused_groups.contains(group)
+    }
+
+    /// Unregisters all observers of a group and returns their IDs
+    ///
+    /// ## Arguments
+    /// * `group` - name of the group
+    ///
+    pub async fn unregister_group(&self, group: &str) -> Vec<ObserverId> {
+        let removed: Vec<ObserverId> = {
+            let mut g = self.state.write().await;
+            let state: &mut State<T> = &mut g;
+            let removed = state
+                .observers
+                .iter()
+                .filter(|o| o.group.as_deref() == Some(group))
+                .map(|o| o.id)
+                .collect();
+            state.observers.retain(|o| o.group.as_deref() != Some(group));
+            removed
+        };
+        self.log(format_args!("unregister group: {}, observers={}", group, removed.len()));
+        for id in removed.iter() {
+            self.emit_lifecycle(Lifecycle::Unsubscribed(*id)).await;
+        }
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::{ChObservable, Propagation};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_groups() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (_, mut ui1) = cho.register_in_group("ui").await;
+        let (_, mut ui2) = cho.register_in_group("ui").await;
+        let (net_id, mut net) = cho.register_in_group("network").await;
+        let (_, mut all) = cho.register().await;
+
+        cho.notify_group("ui", &1).await.unwrap();
+        cho.notify(&2).await.unwrap();
+        assert_eq!(ui1.recv().await, Some(1));
+        assert_eq!(ui1.recv().await, Some(2));
+        assert_eq!(ui2.recv().await, Some(1));
+        assert_eq!(ui2.recv().await, Some(2));
+        assert_eq!(net.recv().await, Some(2));
+        assert_eq!(all.recv().await, Some(2));
+        assert!(format!("{:?}", cho).contains("group: \"network\""));
+
+        cho.pause_group("ui").await;
+        assert!(cho.is_group_paused("ui").await);
+        cho.notify(&3).await.unwrap();
+        cho.notify_group("ui", &4).await.unwrap();
+        cho.resume_group("ui").await;
+        cho.notify(&5).await.unwrap();
+        assert_eq!(ui1.recv().await, Some(5));
+        assert_eq!(net.recv().await, Some(3));
+        assert_eq!(net.recv().await, Some(5));
+
+        let (_, mut events) = cho.lifecycle().register().await;
+        let removed = cho.unregister_group("network").await;
+        assert_eq!(removed, vec![net_id]);
+        assert_eq!(events.recv().await, Some(crate::Lifecycle::Unsubscribed(net_id)));
+        assert!(net.recv().await.is_none());
+        assert!(cho.unregister_group("network").await.is_empty());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_notify_group_like_notify() {
+        let parent: ChObservable<u32> = ChObservable::new();
+        let cho: ChObservable<u32> = ChObservable::builder().replay(1).build().unwrap();
+        parent.add_child(&cho, Propagation::Bubble).await.unwrap();
+        let (_, mut up) = parent.register().await;
+        let (_, mut ui) = cho.register_in_group("ui").await;
+        let (dropped, rx) = cho.register_in_group("ui").await;
+        drop(rx);
+
+        let report = cho.notify_group("ui", &1).await.unwrap();
+        assert_eq!(report.failed, vec![dropped]);
+        assert_eq!(ui.recv().await, Some(1));
+        assert_eq!(up.recv().await, Some(1));
+        // the dropped receiver was pruned, the value is replayed
+        assert_eq!(cho.observer_count().await, 1);
+        let (_, mut late) = cho.register().await;
+        assert_eq!(late.recv().await, Some(1));
+    }
+}
@@ -0,0 +1,337 @@
+//! Parent/child relations between observables, to pass values up and down
+//! a tree of observables, e.g. along a widget hierarchy
+
+use std::collections::HashSet;
+use std::sync::atomic::AtomicU32;
+use std::sync::{Arc, Weak};
+
+use tokio::sync::RwLock;
+
+use super::{ChObservable, Config, SendStrategy, State, Stats};
+use crate::error::{Error, Result};
+
+/// Direction in which values are passed between a parent and a child
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Propagation {
+    /// Values of the child are passed to the observers of the parent
+    Bubble,
+    /// Values of the parent are passed to the observers of the child
+    Broadcast,
+    /// Values are passed in both directions
+    Both,
+}
+
+impl Propagation {
+    fn bubbles(self) -> bool {
+        self != Propagation::Broadcast
+    }
+
+    fn broadcasts(self) -> bool {
+        self != Propagation::Bubble
+    }
+}
+
+/// Reference to a linked observable, that doesn't keep it alive
+pub(super) struct Link<T> {
+    state: Weak<RwLock<State<T>>>,
+    config: Arc<Config>,
+    stats: Arc<Stats>,
+    tag: u64,
+    next_id: Arc<AtomicU32>,
+}
+
+impl<T> Clone for Link<T> {
+    fn clone(&self) -> Self {
+        Link {
+            state: self.state.clone(),
+            config: self.config.clone(),
+            stats: self.stats.clone(),
+            tag: self.tag,
+            next_id: self.next_id.clone(),
+        }
+    }
+}
+
+impl<T: Clone> Link<T> {
+    pub(super) fn new(o: &ChObservable<T>) -> Self {
+        Link {
+            state: Arc::downgrade(&o.state),
+            config: o.config.clone(),
+            stats: o.stats.clone(),
+            tag: o.tag,
+            next_id: o.next_id.clone(),
+        }
+    }
+
+    fn is(&self, o: &ChObservable<T>) -> bool {
+        self.state.as_ptr() == Arc::as_ptr(&o.state)
+    }
+
+    /// Returns a key that identifies the linked observable
+    fn key(&self) -> usize {
+        self.state.as_ptr() as usize
+    }
+
+    /// Returns an observable that shares the observers with the linked one,
+    /// if it still exists
+    pub(super) fn upgrade(&self) -> Option<ChObservable<T>> {
+        let state = self.state.upgrade()?;
+        Some(ChObservable::with_shared(
+            state,
+            self.config.clone(),
+            self.stats.clone(),
+            self.tag,
+            self.next_id.clone(),
+        ))
+    }
+
+    /// Returns the linked observable like `upgrade`, but only if it isn't
+    /// in the visited ones yet, and adds it to them
+    fn visit(&self, visited: &mut HashSet<usize>) -> Option<ChObservable<T>> {
+        if visited.insert(self.key()) {
+            self.upgrade()
+        } else {
+            None
+        }
+    }
+}
+
+impl<T: Clone> ChObservable<T> {
+    /// Makes an observable to a child of this one. A child has at most one
+    /// parent that it bubbles to, a former parent is replaced. It fails
+    /// with `Error::Registration` if the relation would create a cycle,
+    /// i.e. the child is the observable itself or one of its ancestors.
+    ///
+    /// ## Arguments
+    /// * `child` - observable to add
+    /// * `propagation` - direction in which the values are passed
+    ///
+    pub async fn add_child(
+        &self,
+        child: &ChObservable<T>,
+        propagation: Propagation,
+    ) -> Result<()> {
+        if (propagation.bubbles() && self.reaches(child, true).await)
+            || (propagation.broadcasts() && child.reaches(self, false).await)
+        {
+            self.log(format_args!("reject child, it would create a cycle"));
+            return Err(Error::Registration(
+                "child is the observable or one of its ancestors".to_string(),
+            ));
+        }
+        if propagation.broadcasts() {
+            let mut state = self.state.write().await;
+            state.children.retain(|l| !l.is(child));
+            state.children.push(Link::new(child));
+        }
+        if propagation.bubbles() {
+            child.state.write().await.parent = Some(Link::new(self));
+        }
+        Ok(())
+    }
+
+    /// Returns true if the given observable is this one, or is reached
+    /// from this one along the parents or along the children
+    ///
+    /// ## Arguments
+    /// * `o` - observable to look for
+    /// * `up` - true to follow the parents, false to follow the children
+    ///
+    async fn reaches(&self, o: &ChObservable<T>, up: bool) -> bool {
+        let mut visited = HashSet::new();
+        let mut pending = vec![Link::new(self)];
+        while let Some(l) = pending.pop() {
+            if l.is(o) {
+                return true;
+            }
+            let Some(state) = l.state.upgrade().filter(|_| visited.insert(l.key())) else {
+                continue;
+            };
+            let state = state.read().await;
+            if up {
+                pending.extend(state.parent.clone());
+            } else {
+                pending.extend(state.children.iter().cloned());
+            }
+        }
+        false
+    }
+
+    /// Removes the relation to a child in both directions. It returns false
+    /// if the observable wasn't a child of this one.
+    ///
+    /// ## Arguments
+    /// * `child` - observable to remove
+    ///
+    pub async fn remove_child(&self, child: &ChObservable<T>) -> bool {
+        let mut found = {
+            let mut state = self.state.write().await;
+            let n = state.children.len();
+            state.children.retain(|l| !l.is(child));
+            state.children.len() != n
+        };
+        let mut state = child.state.write().await;
+        if state.parent.as_ref().is_some_and(|l| l.is(self)) {
+            state.parent = None;
+            found = true;
+        }
+        found
+    }
+
+    /// Passes a notified value to the linked observables. Values that bubble
+    /// are not broadcast again and the other way around, so every observer
+    /// gets a value only once. Failed deliveries to the observers of linked
+    /// observables are counted in their stats, but not returned.
+    pub(super) async fn propagate(
+        &self,
+        data: &T,
+        parent: Option<Link<T>>,
+        children: Vec<Link<T>>,
+    ) {
+        let mut visited = HashSet::from([Arc::as_ptr(&self.state) as usize]);
+        let mut next = parent;
+        while let Some(o) = next.take().and_then(|l| l.visit(&mut visited)) {
+            next = o.deliver_linked(data).await.0;
+        }
+        let mut pending = children;
+        while let Some(l) = pending.pop() {
+            if let Some(o) = l.visit(&mut visited) {
+                pending.extend(o.deliver_linked(data).await.1);
+            }
+        }
+    }
+
+    /// Passes a value to the linked observables like `propagate`, but
+    /// without waiting. Linked observables that are locked at the moment
+    /// are skipped.
+    pub(super) fn propagate_now(
+        &self,
+        data: &T,
+        parent: Option<Link<T>>,
+        children: Vec<Link<T>>,
+    ) {
+        let mut visited = HashSet::from([Arc::as_ptr(&self.state) as usize]);
+        let mut next = parent;
+        while let Some(o) = next.take().and_then(|l| l.visit(&mut visited)) {
+            next = o.deliver_linked_now(data).0;
+        }
+        let mut pending = children;
+        while let Some(l) = pending.pop() {
+            if let Some(o) = l.visit(&mut visited) {
+                pending.extend(o.deliver_linked_now(data).1);
+            }
+        }
+    }
+
+    /// Notifies the own observers of a linked observable without waiting
+    /// and returns its links
+    fn deliver_linked_now(&self, data: &T) -> (Option<Link<T>>, Vec<Link<T>>) {
+        let Ok(mut g) = self.state.try_write() else {
+            self.log(format_args!("linked observable is locked, skip value"));
+            return (None, Vec::new());
+        };
+        if g.closed {
+            return (None, Vec::new());
+        }
+        let (_, parent, children) = self.notify_now_locked(&mut g, data);
+        (parent, children)
+    }
+
+    /// Notifies the own observers of a linked observable and returns its
+    /// links
+    async fn deliver_linked(&self, data: &T) -> (Option<Link<T>>, Vec<Link<T>>) {
+        let state = self.lock_for_notify().await;
+        if state.closed {
+            return (None, Vec::new());
+        }
+        let (_, parent, children) = self
+            .deliver_passes(state, data, &[&|_| true], SendStrategy::Wait)
+            .await;
+        (parent, children)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::{ChObservable, Propagation};
+    use crate::error::Error;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_hierarchy() {
+        let window: ChObservable<&str> = ChObservable::new();
+        let panel: ChObservable<&str> = ChObservable::new();
+        let button: ChObservable<&str> = ChObservable::new();
+        let (_, mut window_rx) = window.register().await;
+        let (_, mut panel_rx) = panel.register().await;
+        let (_, mut button_rx) = button.register().await;
+        window.add_child(&panel, Propagation::Both).await.unwrap();
+        panel.add_child(&button, Propagation::Bubble).await.unwrap();
+
+        button.notify(&"click").await.unwrap();
+        assert_eq!(button_rx.try_recv(), Ok("click"));
+        assert_eq!(panel_rx.try_recv(), Ok("click"));
+        assert_eq!(window_rx.try_recv(), Ok("click"));
+
+        // the button is not reached by broadcasts
+        window.notify(&"resize").await.unwrap();
+        assert_eq!(window_rx.try_recv(), Ok("resize"));
+        assert_eq!(panel_rx.try_recv(), Ok("resize"));
+        assert!(button_rx.try_recv().is_err());
+        assert!(window_rx.try_recv().is_err());
+
+        assert!(window.remove_child(&panel).await);
+        assert!(!window.remove_child(&panel).await);
+        panel.notify(&"scroll").await.unwrap();
+        assert_eq!(panel_rx.try_recv(), Ok("scroll"));
+        assert!(window_rx.try_recv().is_err());
+
+        // a dropped parent closes its receivers, the child keeps working
+        drop(panel);
+        assert_eq!(panel_rx.recv().await, None);
+        button.notify(&"click").await.unwrap();
+        assert_eq!(button_rx.try_recv(), Ok("click"));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_linked_prune() {
+        let parent: ChObservable<u32> = ChObservable::new();
+        let child: ChObservable<u32> = ChObservable::new();
+        parent.add_child(&child, Propagation::Broadcast).await.unwrap();
+        let (_, child_rx) = child.register().await;
+        let (_, mut kept_rx) = child.register().await;
+        drop(child_rx);
+
+        // a broadcast prunes the closed receivers of the child as well
+        parent.notify(&1).await.unwrap();
+        assert_eq!(kept_rx.try_recv(), Ok(1));
+        assert_eq!(child.observer_count().await, 1);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_reject_cycles() {
+        let a: ChObservable<u32> = ChObservable::new();
+        let b: ChObservable<u32> = ChObservable::new();
+        let c: ChObservable<u32> = ChObservable::new();
+        assert!(matches!(
+            a.add_child(&a, Propagation::Both).await,
+            Err(Error::Registration(_))
+        ));
+        a.add_child(&b, Propagation::Both).await.unwrap();
+        b.add_child(&c, Propagation::Broadcast).await.unwrap();
+        // a is the parent and the broadcast ancestor of b
+        assert!(matches!(
+            b.add_child(&a, Propagation::Bubble).await,
+            Err(Error::Registration(_))
+        ));
+        assert!(matches!(
+            c.add_child(&a, Propagation::Broadcast).await,
+            Err(Error::Registration(_))
+        ));
+
+        // the relations are unchanged
+        let (_, mut rx) = b.register().await;
+        a.notify(&1).await.unwrap();
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,106 @@
+//! Automatic close of observables, that have no observers for a while
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+use tokio::time::{sleep, Instant};
+
+use super::{ChObservable, State};
+
+/// Number of checks for observers within the idle duration
+const IDLE_CHECKS: u32 = 4;
+
+/// Completes when the observable was closed because it was idle, it's
+/// created by `ChObservable::auto_close_when_idle`. It completes as well
+/// if the observable was dropped before.
+#[derive(Debug)]
+pub struct IdleClose(oneshot::Receiver<()>);
+
+impl Future for IdleClose {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        Pin::new(&mut self.0).poll(cx).map(|_| ())
+    }
+}
+
+impl<T> State<T> {
+    /// Returns true if no observer is registered, broadcast observers
+    /// included
+    fn is_idle(&self) -> bool {
+        self.observers.is_empty()
+            && self.broadcast.as_ref().is_none_or(|tx| tx.receiver_count() == 0)
+    }
+}
+
+impl<T: Clone + Send + 'static> ChObservable<T> {
+    /// Closes the observable, after it had no observers for the given
+    /// duration. Later notify calls fail with `Error::Closed`, like after
+    /// `drain`. The observers are checked in a task of the configured
+    /// spawner, a few times within the duration. The returned future
+    /// completes when the observable was closed, so the owner can release
+    /// it.
+    ///
+    /// ## Arguments
+    /// * `idle` - time without observers, after which the observable closes
+    ///
+    pub fn auto_close_when_idle(&self, idle: Duration) -> IdleClose {
+        let (tx, rx) = oneshot::channel();
+        let state = Arc::downgrade(&self.state);
+        let interval = (idle / IDLE_CHECKS).max(Duration::from_millis(1));
+        self.log(format_args!("close when idle for: {:?}", idle));
+        self.spawner().spawn(async move {
+            let mut idle_since: Option<Instant> = None;
+            while let Some(state) = state.upgrade() {
+                let mut state = state.write().await;
+                if state.closed {
+                    break;
+                }
+                if !state.is_idle() {
+                    idle_since = None;
+                } else if idle_since.get_or_insert_with(Instant::now).elapsed() >= idle {
+                    state.closed = true;
+                    let _ = tx.send(());
+                    break;
+                }
+                drop(state);
+                sleep(interval).await;
+            }
+        });
+        IdleClose(rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::sync::oneshot::error::TryRecvError;
+
+    use crate::chobservable::ChObservable;
+    use crate::error::Error;
+    use crate::testing::TestScheduler;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_auto_close_when_idle() {
+        let scheduler = TestScheduler::new();
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (id, _rx) = cho.register().await;
+        let mut closed = cho.auto_close_when_idle(Duration::from_secs(4));
+
+        // the observable isn't idle while the observer is registered
+        scheduler.advance(Duration::from_secs(10)).await;
+        cho.unregister(id).await.unwrap();
+        scheduler.advance(Duration::from_secs(3)).await;
+        assert_eq!(closed.0.try_recv(), Err(TryRecvError::Empty));
+        cho.notify(&1).await.unwrap();
+
+        scheduler.advance(Duration::from_secs(2)).await;
+        closed.await;
+        assert_eq!(cho.notify(&2).await, Err(Error::Closed));
+    }
+}
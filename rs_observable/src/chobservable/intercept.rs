@@ -0,0 +1,74 @@
+//! Interceptor, that can veto the delivery of values to single observers
+
+use super::{ChObservable, State, StoredObserver};
+use crate::error::Error;
+use crate::observer_id::ObserverId;
+
+/// Decides if a value is passed to an observer, false vetoes the delivery
+pub(super) type Interceptor<T> = Box<dyn Fn(ObserverId, &T) -> bool + Send + Sync>;
+
+impl<T: Clone> ChObservable<T> {
+    /// Sets a function, that is asked before a value is passed to an
+    /// observer. If it returns false, the observer doesn't get the value
+    /// and `Error::Vetoed` is reported to the error receivers. Vetoed
+    /// observers are not part of the delivery report. A former interceptor
+    /// is replaced.
+    ///
+    /// ## Arguments
+    /// * `interceptor` - gets the ID of the observer and the value
+    ///
+    pub async fn set_interceptor<F>(&self, interceptor: F)
+    where
+        F: Fn(ObserverId, &T) -> bool + Send + Sync + 'static,
+    {
+        self.state.write().await.interceptor = Some(Box::new(interceptor));
+    }
+
+    /// Removes the interceptor, so all values are passed again
+    pub async fn clear_interceptor(&self) {
+        self.state.write().await.interceptor = None;
+    }
+
+    /// Returns true if the value should be passed to the observer now and
+    /// the interceptor doesn't veto it
+    pub(super) fn admits(&self, state: &State<T>, o: &StoredObserver<T>, data: &T) -> bool {
+        if !state.wants(o, data) {
+            return false;
+        }
+        match &state.interceptor {
+            Some(interceptor) if !interceptor(o.id, data) => {
+                self.log(format_args!("delivery vetoed: id={}", o.label()));
+                self.report_error(&Error::Vetoed { observer_id: o.id });
+                false
+            }
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::ChObservable;
+    use crate::error::Error;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_interceptor() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let mut errors = cho.errors().await;
+        let (id1, mut rx1) = cho.register().await;
+        let (id2, mut rx2) = cho.register().await;
+        cho.set_interceptor(move |id, v| id != id2 || *v < 10).await;
+
+        let report = cho.notify(&10).await.unwrap();
+        assert_eq!(report.delivered, vec![id1]);
+        assert_eq!(errors.recv().await, Some(Error::Vetoed { observer_id: id2 }));
+        cho.notify(&1).await.unwrap();
+        assert_eq!(rx2.recv().await, Some(1));
+
+        cho.clear_interceptor().await;
+        cho.notify(&11).await.unwrap();
+        assert_eq!(rx2.recv().await, Some(11));
+        assert_eq!(rx1.recv().await, Some(10));
+        assert!(errors.try_recv().is_err());
+    }
+}
@@ -0,0 +1,201 @@
+//! Join of two observables, that pairs their values by a key
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::time::Duration;
+
+use tokio::sync::mpsc::{self, Receiver};
+use tokio::time::Instant;
+
+use super::ChObservable;
+
+/// Values of one side of a join, that wait for their partner
+struct Pending<K, V> {
+    values: HashMap<K, VecDeque<(Instant, V)>>,
+    /// Max time a value waits for its partner
+    window: Duration,
+}
+
+impl<K: Eq + Hash, V> Pending<K, V> {
+    fn new(window: Duration) -> Self {
+        Pending {
+            values: HashMap::new(),
+            window,
+        }
+    }
+
+    /// Removes the values that waited longer than the window
+    fn expire(&mut self) {
+        let now = Instant::now();
+        let window = self.window;
+        self.values.retain(|_, q| {
+            q.retain(|(t, _)| now - *t <= window);
+            !q.is_empty()
+        });
+    }
+
+    fn push(&mut self, key: K, value: V) {
+        self.expire();
+        self.values
+            .entry(key)
+            .or_default()
+            .push_back((Instant::now(), value));
+    }
+
+    /// Returns the oldest waiting value with the key
+    fn take(&mut self, key: &K) -> Option<V> {
+        self.expire();
+        let q = self.values.get_mut(key)?;
+        let v = q.pop_front().map(|(_, v)| v);
+        if q.is_empty() {
+            self.values.remove(key);
+        }
+        v
+    }
+}
+
+/// Pairs the values of two observables, that have the same key and are
+/// notified within the time window. Every value is used for one pair,
+/// values without a partner in time are dropped. It registers an observer
+/// on both observables and returns the receiver of the pairs. The pairing
+/// runs in a task that is spawned by the spawner of `a` and ends, when both
+/// observables or the receiver are dropped. The observers are unregistered
+/// when the task ends.
+///
+/// ## Arguments
+/// * `a` - observable of the first values of the pairs
+/// * `b` - observable of the second values of the pairs
+/// * `key_a` - returns the key of a value of `a`
+/// * `key_b` - returns the key of a value of `b`
+/// * `window` - max time between two values of a pair
+///
+pub async fn join<A, B, K, FA, FB>(
+    a: &ChObservable<A>,
+    b: &ChObservable<B>,
+    key_a: FA,
+    key_b: FB,
+    window: Duration,
+) -> Receiver<(A, B)>
+where
+    A: Clone + Send + 'static,
+    B: Clone + Send + 'static,
+    K: Eq + Hash + Send + 'static,
+    FA: Fn(&A) -> K + Send + 'static,
+    FB: Fn(&B) -> K + Send + 'static,
+{
+    let mut rx_a = a.register_guarded().await;
+    let mut rx_b = b.register_guarded().await;
+    let (tx, rx) = mpsc::channel(a.config.capacity);
+    a.config.spawner.spawn(async move {
+        let mut pending_a: Pending<K, A> = Pending::new(window);
+        let mut pending_b: Pending<K, B> = Pending::new(window);
+        let (mut open_a, mut open_b) = (true, true);
+        while open_a || open_b {
+            let pair = tokio::select! {
+                va = rx_a.recv(), if open_a => match va {
+                    Some(va) => {
+                        let key = key_a(&va);
+                        match pending_b.take(&key) {
+                            Some(vb) => Some((va, vb)),
+                            None => {
+                                pending_a.push(key, va);
+                                None
+                            }
+                        }
+                    }
+                    None => {
+                        open_a = false;
+                        None
+                    }
+                },
+                vb = rx_b.recv(), if open_b => match vb {
+                    Some(vb) => {
+                        let key = key_b(&vb);
+                        match pending_a.take(&key) {
+                            Some(va) => Some((va, vb)),
+                            None => {
+                                pending_b.push(key, vb);
+                                None
+                            }
+                        }
+                    }
+                    None => {
+                        open_b = false;
+                        None
+                    }
+                },
+                // ends the task without waiting for the next pair
+                _ = tx.closed() => break,
+            };
+            if let Some(pair) = pair {
+                if tx.send(pair).await.is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::chobservable::{join, ChObservable};
+    use crate::testing::TestScheduler;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_join() {
+        let scheduler = TestScheduler::new();
+        let requests: ChObservable<(u32, &str)> = ChObservable::new();
+        let responses: ChObservable<(u32, u16)> = ChObservable::new();
+        let mut rx = join(
+            &requests,
+            &responses,
+            |r: &(u32, &str)| r.0,
+            |r: &(u32, u16)| r.0,
+            Duration::from_secs(5),
+        )
+        .await;
+
+        requests.notify(&(1, "GET /")).await.unwrap();
+        requests.notify(&(2, "GET /a")).await.unwrap();
+        scheduler.advance(Duration::from_secs(1)).await;
+        responses.notify(&(2, 404)).await.unwrap();
+        assert_eq!(rx.recv().await, Some(((2, "GET /a"), (2, 404))));
+
+        // the request 1 waited too long for its response
+        scheduler.advance(Duration::from_secs(5)).await;
+        responses.notify(&(1, 200)).await.unwrap();
+        scheduler.advance(Duration::from_secs(1)).await;
+        requests.notify(&(3, "GET /b")).await.unwrap();
+        responses.notify(&(3, 200)).await.unwrap();
+        assert_eq!(rx.recv().await, Some(((3, "GET /b"), (3, 200))));
+
+        drop(requests);
+        drop(responses);
+        assert_eq!(rx.recv().await, None);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_join_dropped_receiver() {
+        let requests: ChObservable<(u32, &str)> = ChObservable::new();
+        let responses: ChObservable<(u32, u16)> = ChObservable::new();
+        let rx = join(
+            &requests,
+            &responses,
+            |r: &(u32, &str)| r.0,
+            |r: &(u32, u16)| r.0,
+            Duration::from_secs(5),
+        )
+        .await;
+        requests.notify(&(1, "GET /")).await.unwrap();
+        assert_eq!(requests.observer_count().await, 1);
+
+        // the task ends without a pair and unregisters its observers
+        drop(rx);
+        while requests.observer_count().await > 0 || responses.observer_count().await > 0 {
+            tokio::task::yield_now().await;
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! Observable of key/value pairs, whose observers follow a changeable set
+//! of keys
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Debug, Formatter};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, RwLock};
+
+use tokio::sync::mpsc::{self, Receiver};
+
+use super::{ChObservable, DeliveryReport, Target};
+use crate::error::Result;
+use crate::observer_id::ObserverId;
+
+/// Matches the keys of a registration
+type KeyPredicate<K> = Box<dyn Fn(&K) -> bool + Send + Sync>;
+
+/// Keys that are followed by one observer
+struct KeyFilter<K> {
+    /// Explicitly given keys
+    keys: HashSet<K>,
+    /// Matches additional keys
+    predicate: Option<KeyPredicate<K>>,
+}
+
+impl<K: Eq + Hash> KeyFilter<K> {
+    fn matches(&self, key: &K) -> bool {
+        self.keys.contains(key) || self.predicate.as_ref().is_some_and(|p| p(key))
+    }
+}
+
+/// Async observable of key/value pairs. Every observer gets only the pairs
+/// of the keys it follows, the keys of a registration can be changed
+/// later.
+pub struct ChKeyedObservable<K: Clone, V: Clone> {
+    /// Delivers the pairs to the observers
+    observable: ChObservable<(K, V)>,
+    /// Keys of the registered observers. The observer holds the other
+    /// reference to a filter, so the filters of pruned observers are the
+    /// ones without it.
+    filters: Mutex<HashMap<ObserverId, Arc<RwLock<KeyFilter<K>>>>>,
+}
+
+impl<K: Clone, V: Clone> Debug for ChKeyedObservable<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChKeyedObservable")
+            .field("observable", &self.observable)
+            .finish()
+    }
+}
+
+impl<K, V> Default for ChKeyedObservable<K, V>
+where
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+    V: Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> ChKeyedObservable<K, V>
+where
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+    V: Clone,
+{
+    /// Creates a new object
+    pub fn new() -> Self {
+        ChKeyedObservable {
+            observable: ChObservable::new(),
+            filters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn add_observer(&self, filter: KeyFilter<K>) -> (ObserverId, Receiver<(K, V)>) {
+        let (tx, rx) = mpsc::channel(self.observable.config.capacity);
+        let filter = Arc::new(RwLock::new(filter));
+        let f = filter.clone();
+        let id = self
+            .observable
+            .add_observer_with(Target::Channel(tx), |o| {
+                o.filter = Some(Box::new(move |(k, _): &(K, V)| f.read().unwrap().matches(k)))
+            })
+            .await;
+        let mut filters = self.filters.lock().unwrap();
+        Self::remove_pruned(&mut filters);
+        filters.insert(id, filter);
+        (id, rx)
+    }
+
+    /// This function registers a new observer for a set of keys. It returns
+    /// the ID of the registered observer and a channel receiver to get the
+    /// pairs of the keys.
+    ///
+    /// ## Arguments
+    /// * `keys` - keys to follow, more can be added with `add_keys`
+    ///
+    pub async fn register_keys<I>(&self, keys: I) -> (ObserverId, Receiver<(K, V)>)
+    where
+        I: IntoIterator<Item = K>,
+    {
+        self.add_observer(KeyFilter {
+            keys: keys.into_iter().collect(),
+            predicate: None,
+        })
+        .await
+    }
+
+    /// This function registers a new observer for all keys that match a
+    /// predicate. It returns the ID of the registered observer and a channel
+    /// receiver to get the pairs of the keys.
+    ///
+    /// ## Arguments
+    /// * `predicate` - returns true for the keys to follow, keys that are
+    ///   added with `add_keys` are followed as well
+    ///
+    pub async fn register_matching<F>(&self, predicate: F) -> (ObserverId, Receiver<(K, V)>)
+    where
+        F: Fn(&K) -> bool + Send + Sync + 'static,
+    {
+        self.add_observer(KeyFilter {
+            keys: HashSet::new(),
+            predicate: Some(Box::new(predicate)),
+        })
+        .await
+    }
+
+    /// Removes the filters of the observers, that were pruned by the
+    /// observable
+    fn remove_pruned(filters: &mut HashMap<ObserverId, Arc<RwLock<KeyFilter<K>>>>) {
+        filters.retain(|_, f| Arc::strong_count(f) > 1);
+    }
+
+    fn filter(&self, observer_id: ObserverId) -> Result<Option<Arc<RwLock<KeyFilter<K>>>>> {
+        observer_id.check_tag(self.observable.tag)?;
+        let filters = self.filters.lock().unwrap();
+        Ok(filters
+            .get(&observer_id)
+            .filter(|f| Arc::strong_count(f) > 1)
+            .cloned())
+    }
+
+    /// Adds keys to the ones the observer follows. It returns false if the
+    /// observer isn't registered. It fails if the ID was created by another
+    /// observable.
+    ///
+    /// ## Arguments
+    /// * `observer_id` - ID returned after the registration of an observer
+    /// * `keys` - keys to add
+    ///
+    pub fn add_keys<I>(&self, observer_id: ObserverId, keys: I) -> Result<bool>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        Ok(match self.filter(observer_id)? {
+            Some(f) => {
+                f.write().unwrap().keys.extend(keys);
+                true
+            }
+            None => false,
+        })
+    }
+
+    /// Removes keys from the ones the observer follows, keys that match
+    /// the predicate of the registration are still followed. It returns
+    /// false if the observer isn't registered. It fails if the ID was created
+    /// by another observable.
+    ///
+    /// ## Arguments
+    /// * `observer_id` - ID returned after the registration of an observer
+    /// * `keys` - keys to remove
+    ///
+    pub fn remove_keys<'a, I>(&self, observer_id: ObserverId, keys: I) -> Result<bool>
+    where
+        I: IntoIterator<Item = &'a K>,
+        K: 'a,
+    {
+        Ok(match self.filter(observer_id)? {
+            Some(f) => {
+                let mut f = f.write().unwrap();
+                for k in keys {
+                    f.keys.remove(k);
+                }
+                true
+            }
+            None => false,
+        })
+    }
+
+    /// This function unregisters an observer. It fails if the ID was
+    /// created by another observable.
+    ///
+    /// ## Arguments
+    /// * `observer_id` - ID returned after the registration of an observer
+    ///
+    pub async fn unregister(&self, observer_id: ObserverId) -> Result<()> {
+        self.observable.unregister(observer_id).await?;
+        self.filters.lock().unwrap().remove(&observer_id);
+        Ok(())
+    }
+
+    /// Returns the number of registered observers, see
+    /// `ChObservable::observer_count`
+    pub async fn observer_count(&self) -> usize {
+        self.observable.observer_count().await
+    }
+
+    /// Returns true if no observer is registered
+    pub async fn is_empty(&self) -> bool {
+        self.observable.is_empty().await
+    }
+
+    /// Notifies the observers that follow the key. It fails like
+    /// `ChObservable::notify`. The keys of observers with a dropped
+    /// receiver are removed together with them.
+    ///
+    /// ## Arguments
+    /// * `key` - key of the value
+    /// * `value` - value that should be passed to the observers
+    ///
+    pub async fn notify(&self, key: &K, value: &V) -> Result<DeliveryReport> {
+        let res = self.observable.notify(&(key.clone(), value.clone())).await;
+        Self::remove_pruned(&mut self.filters.lock().unwrap());
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::chobservable::ChKeyedObservable;
+    use crate::error::Error;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_keyed() {
+        let prices: ChKeyedObservable<String, u32> = ChKeyedObservable::new();
+        let key = |k: &str| k.to_string();
+        let (watchlist, mut rx1) = prices.register_keys([key("ACME"), key("INIT")]).await;
+        let (_, mut rx2) = prices.register_matching(|k: &String| k.starts_with('I')).await;
+
+        prices.notify(&key("ACME"), &1).await.unwrap();
+        prices.notify(&key("INIT"), &2).await.unwrap();
+        prices.notify(&key("BOLT"), &3).await.unwrap();
+        assert_eq!(rx1.recv().await, Some((key("ACME"), 1)));
+        assert_eq!(rx1.recv().await, Some((key("INIT"), 2)));
+        assert_eq!(rx2.recv().await, Some((key("INIT"), 2)));
+        assert!(rx1.try_recv().is_err());
+        assert!(rx2.try_recv().is_err());
+
+        // the watchlist changes without a new registration
+        assert_eq!(prices.add_keys(watchlist, [key("BOLT")]), Ok(true));
+        assert_eq!(prices.remove_keys(watchlist, &[key("ACME")]), Ok(true));
+        prices.notify(&key("ACME"), &4).await.unwrap();
+        prices.notify(&key("BOLT"), &5).await.unwrap();
+        assert_eq!(rx1.recv().await, Some((key("BOLT"), 5)));
+        assert!(rx1.try_recv().is_err());
+
+        prices.unregister(watchlist).await.unwrap();
+        assert_eq!(prices.add_keys(watchlist, [key("ACME")]), Ok(false));
+        assert!(rx1.recv().await.is_none());
+
+        let other: ChKeyedObservable<String, u32> = ChKeyedObservable::new();
+        assert_eq!(
+            other.add_keys(watchlist, [key("ACME")]),
+            Err(Error::ForeignObserverId(watchlist))
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_keyed_prune() {
+        let prices: ChKeyedObservable<String, u32> = ChKeyedObservable::new();
+        let (id, rx) = prices.register_keys(["ACME".to_string()]).await;
+        let (_, _rx2) = prices.register_keys(["ACME".to_string()]).await;
+        drop(rx);
+        prices.notify(&"ACME".to_string(), &1).await.unwrap();
+        assert_eq!(prices.filters.lock().unwrap().len(), 1);
+        assert_eq!(prices.add_keys(id, ["INIT".to_string()]), Ok(false));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_keyed_shared() {
+        let prices: Arc<ChKeyedObservable<String, u32>> = Arc::new(ChKeyedObservable::new());
+        let dashboard = tokio::spawn({
+            let prices = prices.clone();
+            async move {
+                let (id, mut rx) = prices.register_keys(["ACME".to_string()]).await;
+                let v = rx.recv().await;
+                prices.unregister(id).await.unwrap();
+                v
+            }
+        });
+        while prices.observer_count().await == 0 {
+            tokio::task::yield_now().await;
+        }
+        prices.notify(&"ACME".to_string(), &1).await.unwrap();
+        assert_eq!(dashboard.await.unwrap(), Some(("ACME".to_string(), 1)));
+        assert!(prices.is_empty().await);
+    }
+}
@@ -0,0 +1,252 @@
+//! Exclusive write access to a ChObservedValue over several steps
+
+use std::fmt::{self, Debug, Formatter};
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use log::warn;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+use super::poison::Status;
+use super::{ChObservable, ChObservedValue, Spawner, ValueState};
+use crate::error::{Error, Result};
+
+/// Exclusive write access to an observed value, it's created by
+/// `ChObservedValue::lease`. While the lease exists, `set_value` waits and
+/// `try_set_value` fails with `Error::Busy`. If the value was changed,
+/// the observers are notified when the lease is released.
+pub struct ValueLease<T: Clone + Send + Sync + 'static> {
+    value: Option<OwnedMutexGuard<Option<T>>>,
+    observable: Arc<Mutex<ChObservable<Option<T>>>>,
+    /// Poison marker of the value, it's removed by a change
+    status: Arc<Status<T>>,
+    /// Runs the notification of a dropped lease, if the observers are
+    /// locked at the moment
+    spawner: Spawner,
+    /// True after a mutable access to the value
+    changed: bool,
+}
+
+impl<T: Clone + Send + Sync + 'static> Debug for ValueLease<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValueLease")
+            .field("changed", &self.changed)
+            .finish()
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> Deref for ValueLease<T> {
+    type Target = Option<T>;
+
+    fn deref(&self) -> &Option<T> {
+        self.value.as_ref().unwrap()
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> DerefMut for ValueLease<T> {
+    fn deref_mut(&mut self) -> &mut Option<T> {
+        self.changed = true;
+        self.value.as_mut().unwrap()
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> ValueLease<T> {
+    /// Ends the lease and waits until the observers are notified about
+    /// the change. The observers are locked before the value is unlocked,
+    /// like in `set_value`, so a registration that reads the value doesn't
+    /// get the change twice. A change removes the poison marker and is
+    /// passed to the state observers, like with `set_value`. It fails like
+    /// `ChObservable::notify`.
+    pub async fn release(mut self) -> Result<()> {
+        let value = self.value.take().unwrap();
+        if !self.changed {
+            return Ok(());
+        }
+        let v = value.clone();
+        let observable = self.observable.lock().await;
+        drop(value);
+        observable.notify(&v).await?;
+        drop(observable);
+        self.status.restore(v.map_or(ValueState::Unset, ValueState::Set)).await
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> Drop for ValueLease<T> {
+    /// A lease that is dropped without `release` notifies without waiting,
+    /// the value is lost for observers with a full channel. If the
+    /// observers are locked at the moment, e.g. by a running notification,
+    /// they are notified by a task of the spawner of the observable. Other
+    /// changes can reach the observers before this one then.
+    fn drop(&mut self) {
+        let Some(value) = self.value.take() else {
+            return;
+        };
+        if !self.changed {
+            return;
+        }
+        let v = value.clone();
+        drop(value);
+        let notified = match self.observable.try_lock() {
+            Ok(o) => !matches!(o.try_notify(&v), Err(Error::WouldBlock)),
+            Err(_) => false,
+        };
+        let state = v.clone().map_or(ValueState::Unset, ValueState::Set);
+        if notified && self.status.try_restore(&state) {
+            return;
+        }
+        warn!("observers of the dropped lease are locked, notify them later");
+        let observable = (!notified).then(|| self.observable.clone());
+        let status = self.status.clone();
+        self.spawner.try_spawn(async move {
+            if let Some(o) = observable {
+                if let Err(e) = o.lock().await.notify(&v).await {
+                    warn!("notification of the dropped lease failed: {}", e);
+                }
+            }
+            if let Err(e) = status.restore(state).await {
+                warn!("notification of the dropped lease failed: {}", e);
+            }
+        });
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> ChObservedValue<T> {
+    /// Waits until no other lease exists and returns a lease with exclusive
+    /// write access to the value
+    pub async fn lease(&self) -> ValueLease<T> {
+        let spawner = self.spawner().await;
+        ValueLease {
+            value: Some(self.value.clone().lock_owned().await),
+            observable: self.observable.clone(),
+            status: self.status.clone(),
+            spawner,
+            changed: false,
+        }
+    }
+
+    /// Sets a new value like `set_value`, but fails with `Error::Busy`
+    /// instead of waiting while the value is leased. Like `set_value` it
+    /// removes the poison marker.
+    ///
+    /// ## Arguments
+    /// * `v` - value to set
+    ///
+    pub async fn try_set_value(&mut self, v: &T) -> Result<()> {
+        let spawner = self.spawner().await;
+        let mut lease = ValueLease {
+            value: Some(self.value.clone().try_lock_owned().map_err(|_| Error::Busy)?),
+            observable: self.observable.clone(),
+            status: self.status.clone(),
+            spawner,
+            changed: false,
+        };
+        *lease = Some(v.clone());
+        lease.release().await
+    }
+
+    /// Returns the spawner of the observers
+    async fn spawner(&self) -> Spawner {
+        self.observable.lock().await.config.spawner.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use crate::chobservable::{ChObservedValue, ValueState};
+    use crate::error::Error;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_lease() {
+        let mut cho: ChObservedValue<Vec<u32>> = ChObservedValue::new();
+        let (_, mut rx) = cho.register().await;
+        cho.set_value(&vec![1]).await.unwrap();
+        assert_eq!(rx.recv().await, Some(Some(vec![1])));
+
+        let mut lease = cho.lease().await;
+        assert_eq!(cho.try_set_value(&vec![0]).await, Err(Error::Busy));
+        // multi step edit, the observers get only the result
+        lease.as_mut().unwrap().push(2);
+        lease.as_mut().unwrap().push(3);
+        assert!(rx.try_recv().is_err());
+        lease.release().await.unwrap();
+        assert_eq!(rx.recv().await, Some(Some(vec![1, 2, 3])));
+
+        // read only leases don't notify
+        drop(cho.lease().await);
+        cho.try_set_value(&vec![4]).await.unwrap();
+        assert_eq!(rx.recv().await, Some(Some(vec![4])));
+        let mut lease = cho.lease().await;
+        *lease = None;
+        drop(lease);
+        assert_eq!(rx.recv().await, Some(None));
+        assert_eq!(*cho.value_ref().unwrap().lock().await, None);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_release_while_registering() {
+        let mut cho: ChObservedValue<Vec<u32>> = ChObservedValue::new();
+        let mut lease = cho.lease().await;
+        *lease = Some(vec![5]);
+        // the registration locks the observers and waits for the value,
+        // while the release waits for the observers
+        let release = tokio::spawn(lease.release());
+        let (_, watch) = timeout(Duration::from_secs(1), cho.register_watch()).await.unwrap();
+        release.await.unwrap().unwrap();
+        assert_eq!(*watch.borrow(), Some(vec![5]));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_lease_restores_poisoned() {
+        let mut cho: ChObservedValue<u32> = ChObservedValue::new();
+        let (_, mut states) = cho.register_state().await;
+        cho.poison("bad input").await.unwrap();
+        assert!(cho.value().await.is_err());
+
+        let mut lease = cho.lease().await;
+        *lease = Some(1);
+        lease.release().await.unwrap();
+        assert!(!cho.is_poisoned());
+        assert_eq!(cho.value().await, Ok(Some(1)));
+
+        cho.poison("bad input").await.unwrap();
+        cho.try_set_value(&2).await.unwrap();
+        assert_eq!(cho.value().await, Ok(Some(2)));
+
+        // a dropped lease restores the value as well
+        cho.poison("bad input").await.unwrap();
+        let mut lease = cho.lease().await;
+        *lease = None;
+        drop(lease);
+        assert_eq!(cho.value().await, Ok(None));
+
+        let poisoned = ValueState::Poisoned("bad input".to_string());
+        assert_eq!(states.recv().await, Some(poisoned.clone()));
+        assert_eq!(states.recv().await, Some(ValueState::Set(1)));
+        assert_eq!(states.recv().await, Some(poisoned.clone()));
+        assert_eq!(states.recv().await, Some(ValueState::Set(2)));
+        assert_eq!(states.recv().await, Some(poisoned));
+        assert_eq!(states.recv().await, Some(ValueState::Unset));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_drop_lease_while_locked() {
+        let mut cho: ChObservedValue<u32> = ChObservedValue::new();
+        let (_, mut rx) = cho.register().await;
+        cho.poison("bad input").await.unwrap();
+
+        // the observers are locked by another task, the dropped lease
+        // notifies them after they are unlocked
+        let mut lease = cho.lease().await;
+        *lease = Some(1);
+        let observers = cho.observable.lock().await;
+        drop(lease);
+        assert!(rx.try_recv().is_err());
+        drop(observers);
+        assert_eq!(rx.recv().await, Some(Some(1)));
+        assert_eq!(cho.value().await, Ok(Some(1)));
+    }
+}
@@ -0,0 +1,81 @@
+//! Events about the registrations of a ChObservable, that are published
+//! over a secondary observable
+
+use super::{ChObservable, Config};
+use crate::observer_id::ObserverId;
+
+/// Change in the set of registered observers of a `ChObservable`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lifecycle {
+    /// A new observer was registered
+    Subscribed(ObserverId),
+    /// An observer was unregistered
+    Unsubscribed(ObserverId),
+    /// An observer was removed by the observable itself, e.g. because
+    /// its receiver was dropped
+    Pruned(ObserverId),
+    /// The observable was closed by `close` or dropped, this is the last
+    /// event
+    Closed,
+}
+
+impl<T: Clone> ChObservable<T> {
+    /// Returns the observable of the lifecycle events, it is created with
+    /// the first call. Observers that are registered on it get informed
+    /// about all later registrations and unregistrations, and about the
+    /// end of this observable.
+    ///
+    /// The `Closed` event of a dropped observable is passed without
+    /// waiting, it's lost for lifecycle observers with a full channel.
+    pub fn lifecycle(&self) -> &ChObservable<Lifecycle> {
+        let config = &self.config;
+        self.lifecycle.get_or_init(|| {
+            ChObservable::with_config(Config {
+                name: config.name.as_ref().map(|n| format!("{}.lifecycle", n)),
+                log_level: config.log_level,
+                ..Config::default()
+            })
+        })
+    }
+
+    /// Passes an event to the lifecycle observers, if there are any. A
+    /// failed notification is passed to the error receivers.
+    pub(super) async fn emit_lifecycle(&self, event: Lifecycle) {
+        if let Some(l) = self.lifecycle.get() {
+            if let Err(e) = l.notify(&event).await {
+                self.report_error(&e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::{ChObservable, Lifecycle};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_lifecycle() {
+        let cho: ChObservable<u32> = ChObservable::builder().name("src").build().unwrap();
+        let (_, mut events) = cho.lifecycle().register().await;
+        assert!(format!("{:?}", cho.lifecycle()).contains("name: Some(\"src.lifecycle\")"));
+
+        let (id1, _rx1) = cho.register().await;
+        let id2 = cho.register_callback(|_| ()).await;
+        cho.unregister(id1).await.unwrap();
+        // unknown IDs don't produce events
+        cho.unregister(id1).await.unwrap();
+        // observers with a dropped receiver are removed by notify
+        let (id3, rx3) = cho.register().await;
+        drop(rx3);
+        cho.notify(&1).await.unwrap();
+        drop(cho);
+
+        assert_eq!(events.recv().await, Some(Lifecycle::Subscribed(id1)));
+        assert_eq!(events.recv().await, Some(Lifecycle::Subscribed(id2)));
+        assert_eq!(events.recv().await, Some(Lifecycle::Unsubscribed(id1)));
+        assert_eq!(events.recv().await, Some(Lifecycle::Subscribed(id3)));
+        assert_eq!(events.recv().await, Some(Lifecycle::Pruned(id3)));
+        assert_eq!(events.recv().await, Some(Lifecycle::Closed));
+        assert_eq!(events.recv().await, None);
+    }
+}
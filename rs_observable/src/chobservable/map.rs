@@ -0,0 +1,307 @@
+//! Observable map, whose changes can be mirrored to a remote replica
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::Hash;
+use std::sync::{Arc, Weak};
+
+use tokio::sync::mpsc::{self, Receiver};
+use tokio::sync::Mutex;
+
+use super::ChObservable;
+use crate::error::{Error, Result};
+use crate::observer_id::ObserverId;
+
+/// Change of a `ChObservableMap`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapChange<K, V> {
+    /// The key was set to the value
+    Insert(K, V),
+    /// The key was removed
+    Remove(K),
+}
+
+/// Change together with the version of the map after the change. The
+/// versions of consecutive changes differ by one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapUpdate<K, V> {
+    /// Version of the map after the change, the first change has version 1
+    pub version: u64,
+    /// Changed entry
+    pub change: MapChange<K, V>,
+}
+
+/// Content of a map at one version
+#[derive(Debug, Clone)]
+pub struct MapSnapshot<K, V> {
+    /// Number of changes that are contained
+    pub version: u64,
+    /// Entries of the map
+    pub entries: HashMap<K, V>,
+}
+
+struct MapState<K, V> {
+    entries: HashMap<K, V>,
+    /// Number of changes since the creation
+    version: u64,
+}
+
+impl<K: Clone, V: Clone> MapState<K, V> {
+    fn snapshot(&self) -> MapSnapshot<K, V> {
+        MapSnapshot {
+            version: self.version,
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+/// Async map that notifies its observers about every change
+pub struct ChObservableMap<K: Clone, V: Clone> {
+    state: Arc<Mutex<MapState<K, V>>>,
+    observable: ChObservable<MapUpdate<K, V>>,
+}
+
+impl<K: Clone, V: Clone> Debug for ChObservableMap<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChObservableMap")
+            .field("observable", &self.observable)
+            .finish()
+    }
+}
+
+impl<K, V> Default for ChObservableMap<K, V>
+where
+    K: Clone + Eq + Hash + Send + 'static,
+    V: Clone + Send + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> ChObservableMap<K, V>
+where
+    K: Clone + Eq + Hash + Send + 'static,
+    V: Clone + Send + 'static,
+{
+    /// Creates a new object
+    pub fn new() -> Self {
+        ChObservableMap {
+            state: Arc::new(Mutex::new(MapState {
+                entries: HashMap::new(),
+                version: 0,
+            })),
+            observable: ChObservable::new(),
+        }
+    }
+
+    /// The observers are notified while the map is locked, so the order of
+    /// the updates is the order of the changes
+    async fn change(&self, state: &mut MapState<K, V>, change: MapChange<K, V>) -> Result<()> {
+        state.version += 1;
+        let update = MapUpdate {
+            version: state.version,
+            change,
+        };
+        self.observable.notify(&update).await.map(|_| ())
+    }
+
+    /// Sets the value of a key and notifies the observers. It returns the
+    /// former value of the key. Observers that can't be reached are
+    /// reported like in `ChObservable::notify`, the map is changed anyway.
+    ///
+    /// ## Arguments
+    /// * `key` - key to set
+    /// * `value` - new value of the key
+    ///
+    pub async fn insert(&self, key: K, value: V) -> Result<Option<V>> {
+        let mut state = self.state.lock().await;
+        let old = state.entries.insert(key.clone(), value.clone());
+        self.change(&mut state, MapChange::Insert(key, value)).await?;
+        Ok(old)
+    }
+
+    /// Removes a key and notifies the observers. It returns the removed
+    /// value, nothing is notified if the key didn't exist.
+    ///
+    /// ## Arguments
+    /// * `key` - key to remove
+    ///
+    pub async fn remove(&self, key: &K) -> Result<Option<V>> {
+        let mut state = self.state.lock().await;
+        let old = state.entries.remove(key);
+        if old.is_some() {
+            self.change(&mut state, MapChange::Remove(key.clone())).await?;
+        }
+        Ok(old)
+    }
+
+    /// Returns a copy of the value of a key
+    ///
+    /// ## Arguments
+    /// * `key` - key to look up
+    ///
+    pub async fn get(&self, key: &K) -> Option<V> {
+        self.state.lock().await.entries.get(key).cloned()
+    }
+
+    /// Returns the current content
+    pub async fn snapshot(&self) -> MapSnapshot<K, V> {
+        self.state.lock().await.snapshot()
+    }
+
+    /// This function registers a new observer. It returns the ID of the
+    /// registered observer and a channel receiver to get the changes.
+    pub async fn register(&mut self) -> (ObserverId, Receiver<MapUpdate<K, V>>) {
+        self.observable.register().await
+    }
+
+    /// This function unregisters an observer. It fails if the ID was
+    /// created by another observable.
+    ///
+    /// ## Arguments
+    /// * `observer_id` - ID returned after the registration of an observer
+    ///
+    pub async fn unregister(&mut self, observer_id: ObserverId) -> Result<()> {
+        self.observable.unregister(observer_id).await
+    }
+
+    /// Returns the number of registered observers, see
+    /// `ChObservable::observer_count`
+    pub async fn observer_count(&self) -> usize {
+        self.observable.observer_count().await
+    }
+
+    /// Returns the endpoint to mirror the map, e.g. over a transport to
+    /// another process. It contains a snapshot and the later changes. The
+    /// changes are passed without waiting, if the replica is too slow,
+    /// changes are lost and the replica has to resync.
+    pub async fn sync_handle(&mut self) -> SyncHandle<K, V> {
+        let (tx, rx) = mpsc::channel(self.observable.config.capacity);
+        let state = self.state.lock().await;
+        let probe = tx.clone();
+        let send = move |update| {
+            let _ = tx.try_send(update);
+        };
+        self.observable
+            .add_forwarder(send, move || probe.is_closed())
+            .await;
+        SyncHandle {
+            snapshot: state.snapshot(),
+            rx,
+            state: Arc::downgrade(&self.state),
+        }
+    }
+}
+
+/// Endpoint to keep a replica of a `ChObservableMap`, it's created by
+/// `ChObservableMap::sync_handle`. A replica starts with the snapshot and
+/// applies the updates in order. If the version of an update is not the
+/// next version of the replica, updates were lost and the replica calls
+/// `resync`.
+pub struct SyncHandle<K, V> {
+    snapshot: MapSnapshot<K, V>,
+    rx: Receiver<MapUpdate<K, V>>,
+    state: Weak<Mutex<MapState<K, V>>>,
+}
+
+impl<K, V> Debug for SyncHandle<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncHandle")
+            .field("version", &self.snapshot.version)
+            .finish()
+    }
+}
+
+impl<K: Clone, V: Clone> SyncHandle<K, V> {
+    /// Returns the snapshot, that the updates follow
+    pub fn snapshot(&self) -> &MapSnapshot<K, V> {
+        &self.snapshot
+    }
+
+    /// Receives the next update. It returns `None` after the map was
+    /// dropped.
+    pub async fn recv(&mut self) -> Option<MapUpdate<K, V>> {
+        self.rx.recv().await
+    }
+
+    /// Returns a new snapshot and drops the received updates it contains,
+    /// the following updates continue with the next version. It fails
+    /// with `Error::Closed` if the map was dropped.
+    pub async fn resync(&mut self) -> Result<MapSnapshot<K, V>> {
+        let state = self.state.upgrade().ok_or(Error::Closed)?;
+        let state = state.lock().await;
+        // no new updates while the map is locked
+        while self.rx.try_recv().is_ok() {}
+        self.snapshot = state.snapshot();
+        Ok(self.snapshot.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::chobservable::{ChObservableMap, MapChange, MapUpdate};
+    use crate::error::Error;
+
+    /// Replica that follows the updates of a sync handle
+    fn apply(replica: &mut HashMap<&'static str, u32>, update: MapUpdate<&'static str, u32>) {
+        match update.change {
+            MapChange::Insert(k, v) => replica.insert(k, v),
+            MapChange::Remove(k) => replica.remove(k),
+        };
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_sync_handle() {
+        let mut map: ChObservableMap<&str, u32> = ChObservableMap::new();
+        map.insert("a", 1).await.unwrap();
+        let mut handle = map.sync_handle().await;
+        let mut replica = handle.snapshot().entries.clone();
+        let mut version = handle.snapshot().version;
+        assert_eq!(version, 1);
+
+        map.insert("b", 2).await.unwrap();
+        assert_eq!(map.remove(&"a").await, Ok(Some(1)));
+        assert_eq!(map.remove(&"a").await, Ok(None));
+        for _ in 0..2 {
+            let update = handle.recv().await.unwrap();
+            assert_eq!(update.version, version + 1);
+            version = update.version;
+            apply(&mut replica, update);
+        }
+        assert_eq!(replica, map.snapshot().await.entries);
+
+        // more changes than the channel can take, the replica sees a gap
+        for i in 0..12 {
+            map.insert("c", i).await.unwrap();
+        }
+        map.insert("d", 4).await.unwrap();
+        while let Ok(update) = handle.rx.try_recv() {
+            assert_eq!(update.version, version + 1);
+            version = update.version;
+        }
+        assert_ne!(version, map.snapshot().await.version);
+        let snapshot = handle.resync().await.unwrap();
+        assert_eq!(snapshot.version, 16);
+        assert_eq!(snapshot.entries.get("c"), Some(&11));
+        map.insert("e", 5).await.unwrap();
+        assert_eq!(handle.recv().await.map(|u| u.version), Some(17));
+
+        drop(map);
+        assert_eq!(handle.recv().await, None);
+        assert_eq!(handle.resync().await.unwrap_err(), Error::Closed);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_prune_dropped_sync_handle() {
+        let mut map: ChObservableMap<&str, u32> = ChObservableMap::new();
+        let handle = map.sync_handle().await;
+        let _kept = map.sync_handle().await;
+        drop(handle);
+        map.insert("a", 1).await.unwrap();
+        assert_eq!(map.observable.state.read().await.observers.len(), 1);
+        assert_eq!(map.observable.prune_closed().await, 0);
+    }
+}
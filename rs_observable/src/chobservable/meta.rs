@@ -0,0 +1,128 @@
+//! User data that is attached to the registrations of a ChObservable
+
+use std::any::Any;
+
+use tokio::sync::mpsc::{self, Receiver};
+
+use super::{ChObservable, Target};
+use crate::error::Result;
+use crate::observer_id::ObserverId;
+
+impl<T: Clone> ChObservable<T> {
+    /// This function registers a new observer together with user data,
+    /// e.g. the state of a routing layer for this observer. It returns the
+    /// ID of the registered observer and a channel receiver to get the new
+    /// values.
+    ///
+    /// ## Arguments
+    /// * `meta` - user data that is kept with the registration
+    ///
+    pub async fn register_with_meta<M>(&self, meta: M) -> (ObserverId, Receiver<T>)
+    where
+        M: Any + Send + Sync,
+    {
+        let (tx, rx) = mpsc::channel(self.config.capacity);
+        let id = self
+            .add_observer_with(Target::Channel(tx), |o| *o.meta.get_mut().unwrap() = Some(Box::new(meta)))
+            .await;
+        (id, rx)
+    }
+
+    /// Returns a copy of the user data of an observer. It returns `None`
+    /// if the observer isn't registered, has no user data or if the user
+    /// data has another type. It fails if the ID was created by another
+    /// observable.
+    ///
+    /// ## Arguments
+    /// * `observer_id` - ID returned after the registration of an observer
+    ///
+    pub async fn meta<M>(&self, observer_id: ObserverId) -> Result<Option<M>>
+    where
+        M: Any + Clone,
+    {
+        observer_id.check_tag(self.tag)?;
+        let state = self.state.read().await;
+        Ok(state
+            .observers
+            .get(observer_id)
+            .and_then(|o| o.meta().as_ref()?.downcast_ref::<M>().cloned()))
+    }
+
+    /// Replaces the user data of an observer. It returns false if the
+    /// observer isn't registered. It fails if the ID was created by another
+    /// observable.
+    ///
+    /// ## Arguments
+    /// * `observer_id` - ID returned after the registration of an observer
+    /// * `meta` - new user data
+    ///
+    pub async fn set_meta<M>(&self, observer_id: ObserverId, meta: M) -> Result<bool>
+    where
+        M: Any + Send + Sync,
+    {
+        observer_id.check_tag(self.tag)?;
+        let state = self.state.read().await;
+        match state.observers.get(observer_id) {
+            Some(o) => {
+                *o.meta() = Some(Box::new(meta));
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Returns the IDs and copies of the user data of all observers, that
+    /// have user data of the given type, in the order of registration
+    pub async fn metas<M>(&self) -> Vec<(ObserverId, M)>
+    where
+        M: Any + Clone,
+    {
+        let state = self.state.read().await;
+        state
+            .observers
+            .iter()
+            .filter_map(|o| {
+                let m = o.meta().as_ref()?.downcast_ref::<M>()?.clone();
+                Some((o.id, m))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::ChObservable;
+    use crate::error::Error;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Route {
+        topic: String,
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_meta() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let route = |t: &str| Route { topic: t.to_string() };
+        let (id1, _rx1) = cho.register_with_meta(route("a")).await;
+        let (id2, _rx2) = cho.register().await;
+        let (id3, mut rx3) = cho.register_with_meta(route("b")).await;
+
+        assert_eq!(cho.meta::<Route>(id1).await, Ok(Some(route("a"))));
+        assert_eq!(cho.meta::<Route>(id2).await, Ok(None));
+        // the type must match the stored one
+        assert_eq!(cho.meta::<u32>(id1).await, Ok(None));
+        assert_eq!(cho.metas::<Route>().await, vec![(id1, route("a")), (id3, route("b"))]);
+
+        assert_eq!(cho.set_meta(id2, route("c")).await, Ok(true));
+        assert_eq!(cho.meta::<Route>(id2).await, Ok(Some(route("c"))));
+        cho.unregister(id1).await.unwrap();
+        assert_eq!(cho.set_meta(id1, route("d")).await, Ok(false));
+        assert_eq!(cho.metas::<Route>().await, vec![(id2, route("c")), (id3, route("b"))]);
+
+        cho.notify(&1).await.unwrap();
+        assert_eq!(rx3.recv().await, Some(1));
+
+        let other: ChObservable<u32> = ChObservable::new();
+        assert_eq!(other.meta::<Route>(id3).await, Err(Error::ForeignObserverId(id3)));
+    }
+}
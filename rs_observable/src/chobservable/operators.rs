@@ -0,0 +1,117 @@
+//! Observables that are derived from another one, the values are passed
+//! by a task of the crate
+
+use super::hierarchy::Link;
+use super::{ChObservable, Config};
+
+impl<T: Clone + Send + 'static> ChObservable<T> {
+    /// Returns a new observable, that gets every value of this one
+    /// converted by `f`, e.g. to chain the steps of a processing pipeline.
+    /// The values are passed by a task of the spawner of this observable.
+    /// The task ends with this observable and closes the returned one.
+    /// After the returned observable was dropped, it ends with the next
+    /// value.
+    ///
+    /// ## Arguments
+    /// * `f` - returns the converted value
+    ///
+    pub async fn map<U, F>(&self, f: F) -> ChObservable<U>
+    where
+        U: Clone + Send + Sync + 'static,
+        F: Fn(T) -> U + Send + 'static,
+    {
+        self.derive(move |v| Some(f(v))).await
+    }
+
+    /// Returns a new observable, that gets only the values of this one, for
+    /// which `pred` returns true. The values are passed by a task like the
+    /// ones of `map`.
+    ///
+    /// ## Arguments
+    /// * `pred` - returns true for the values that should be passed
+    ///
+    pub async fn filter<F>(&self, pred: F) -> ChObservable<T>
+    where
+        T: Sync,
+        F: Fn(&T) -> bool + Send + 'static,
+    {
+        self.derive(move |v| pred(&v).then_some(v)).await
+    }
+
+    /// Registers an observer, whose values pass the step and are notified
+    /// to a new observable with the same capacity and spawner
+    async fn derive<U, F>(&self, mut step: F) -> ChObservable<U>
+    where
+        U: Clone + Send + Sync + 'static,
+        F: FnMut(T) -> Option<U> + Send + 'static,
+    {
+        let derived = ChObservable::with_config(Config {
+            capacity: self.config.capacity,
+            spawner: self.config.spawner.clone(),
+            ..Config::default()
+        });
+        let link = Link::new(&derived);
+        let (_, mut rx) = self.register().await;
+        self.config.spawner.spawn(async move {
+            while let Some(v) = rx.recv().await {
+                let Some(u) = step(v) else {
+                    continue;
+                };
+                let Some(target) = link.upgrade() else {
+                    return;
+                };
+                if target.notify(&u).await.is_err() {
+                    return;
+                }
+            }
+            // the source was closed, so the derived observable ends too
+            if let Some(target) = link.upgrade() {
+                target.close().await;
+            }
+        });
+        derived
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::ChObservable;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_map() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let labels = cho.map(|v| format!("#{}", v)).await;
+        let (_, mut rx) = labels.register().await;
+        cho.notify(&1).await.unwrap();
+        cho.notify(&2).await.unwrap();
+        assert_eq!(rx.recv().await.as_deref(), Some("#1"));
+        assert_eq!(rx.recv().await.as_deref(), Some("#2"));
+
+        // the forwarding ends with the derived observable
+        drop(labels);
+        assert_eq!(rx.recv().await, None);
+        cho.notify(&3).await.unwrap();
+        tokio::task::yield_now().await;
+        cho.notify(&4).await.unwrap();
+        assert_eq!(cho.observer_count().await, 0);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_filter() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let even = cho.filter(|v| v % 2 == 0).await;
+        let halves = even.map(|v| v / 2).await;
+        let (_, mut rx) = halves.register().await;
+        for v in 1..=6 {
+            cho.notify(&v).await.unwrap();
+        }
+        assert_eq!(rx.recv().await, Some(1));
+        assert_eq!(rx.recv().await, Some(2));
+        assert_eq!(rx.recv().await, Some(3));
+
+        // the derived observables end with the source
+        drop(cho);
+        drop(even);
+        assert_eq!(rx.recv().await, None);
+    }
+}
@@ -0,0 +1,89 @@
+//! Overflow policies of single registrations
+
+use tokio::sync::mpsc::{self, Receiver};
+
+use super::queue::QueueSender;
+use super::{ChObservable, OverflowPolicy, QueueReceiver, Target};
+use crate::error::{Error, Result};
+use crate::observer_id::ObserverId;
+
+impl<T: Clone + Send + 'static> ChObservable<T> {
+    /// This function registers a new observer, that uses its own overflow
+    /// policy instead of the one of the observable. So a slow observer can
+    /// e.g. lose values, without slowing down the notification. It fails
+    /// with `Error::Validation` for `OverflowPolicy::WeightedFair`, that is
+    /// only used for the whole observable.
+    ///
+    /// ## Arguments
+    /// * `policy` - behavior if the channel of the observer is full
+    ///
+    pub async fn register_with_policy(
+        &self,
+        policy: OverflowPolicy,
+    ) -> Result<(ObserverId, Receiver<T>)> {
+        if policy == OverflowPolicy::WeightedFair {
+            return Err(Error::Validation(
+                "WeightedFair can't be used per registration".to_string(),
+            ));
+        }
+        let (tx, rx) = mpsc::channel(self.config.capacity);
+        let id = self
+            .add_observer_with(Target::Channel(tx), |o| o.overflow_policy = Some(policy))
+            .await;
+        Ok((id, rx))
+    }
+
+    /// This function registers a new observer, that keeps only the latest
+    /// values. The values are kept in a queue of the observable with the
+    /// configured capacity, if it's full the oldest value is dropped for
+    /// the new one. So the observer sees the latest values, without
+    /// slowing down the notification, the overflow policy of the
+    /// observable doesn't apply to it. The queue is waited for by `drain`.
+    /// It returns the ID of the registered observer and the receiver of
+    /// the queue.
+    pub async fn register_drop_oldest(&self) -> (ObserverId, QueueReceiver<T>) {
+        let (tx, rx) = QueueSender::new(Some(self.config.capacity));
+        let id = self.add_observer(Target::Queue(tx)).await;
+        (id, rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::{ChObservable, OverflowPolicy};
+    use crate::error::Error;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_with_policy() {
+        let cho: ChObservable<u32> = ChObservable::builder().capacity(2).build().unwrap();
+        let (_, mut newest) = cho
+            .register_with_policy(OverflowPolicy::DropNewest)
+            .await
+            .unwrap();
+        let (_, mut oldest) = cho.register_drop_oldest().await;
+        // the observable blocks by default, but not for these observers
+        for i in 0..5 {
+            cho.notify(&i).await.unwrap();
+        }
+        drop(cho);
+        assert_eq!(newest.recv().await, Some(0));
+        assert_eq!(newest.recv().await, Some(1));
+        assert_eq!(newest.recv().await, None);
+        assert_eq!(oldest.len(), 2);
+        assert_eq!(oldest.recv().await, Some(3));
+        assert_eq!(oldest.recv().await, Some(4));
+        assert_eq!(oldest.recv().await, None);
+
+        // the dropped receiver is pruned without a further value
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (_, oldest) = cho.register_drop_oldest().await;
+        drop(oldest);
+        assert_eq!(cho.prune_closed().await, 1);
+
+        let cho: ChObservable<u32> = ChObservable::new();
+        assert!(matches!(
+            cho.register_with_policy(OverflowPolicy::WeightedFair).await,
+            Err(Error::Validation(_))
+        ));
+    }
+}
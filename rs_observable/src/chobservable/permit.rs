@@ -0,0 +1,174 @@
+//! Notification in two steps, first the capacity of all observer channels
+//! is reserved and then the value is passed without waiting
+
+use std::fmt::{self, Debug, Formatter};
+
+use tokio::sync::mpsc::OwnedPermit;
+use tokio::sync::RwLockWriteGuard;
+
+use super::{ChObservable, DeliveryReport, State, Target};
+use crate::error::{Error, Result};
+
+/// Reserved capacity in the channels of all observers, it's created by
+/// `ChObservable::reserve`. Registrations and other notifications wait
+/// until the permit is used or dropped. Dropping the permit releases the
+/// capacity without a notification.
+pub struct NotifyPermit<'a, T: Clone> {
+    observable: &'a ChObservable<T>,
+    state: RwLockWriteGuard<'a, State<T>>,
+    /// Reserved slot per observer, `None` for callbacks and dropped
+    /// receivers
+    permits: Vec<Option<OwnedPermit<T>>>,
+}
+
+impl<T: Clone> Debug for NotifyPermit<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NotifyPermit")
+            .field("observable", &self.observable.config.name)
+            .field("reserved", &self.permits.iter().flatten().count())
+            .finish()
+    }
+}
+
+impl<T: Clone> NotifyPermit<'_, T> {
+    /// Passes the value to the observers, this never waits. All observers
+    /// are tried, the returned report lists the ones whose receiver was
+    /// dropped as failed, like `ChObservable::notify`, and they are
+    /// unregistered afterwards. Projections and the other observers without
+    /// reserved capacity are served like with `try_notify`. Linked
+    /// observables get the value without waiting as well, the ones that are
+    /// locked at the moment are skipped.
+    ///
+    /// ## Arguments
+    /// * `data` - data that should be passed to the observers
+    ///
+    pub fn notify(self, data: &T) -> Result<DeliveryReport> {
+        let NotifyPermit {
+            observable,
+            mut state,
+            permits,
+        } = self;
+        let s: &mut State<T> = &mut state;
+        let (parent, children) = observable.start_notify(s, data);
+        let mut report = DeliveryReport::default();
+        for (o, permit) in s.observers.iter().zip(permits) {
+            if !observable.admits(s, o, data) {
+                continue;
+            }
+            let res = match (&o.target, permit) {
+                (Target::Channel(_), Some(p)) => {
+                    p.send(data.clone());
+                    observable.sent(o, Ok(()))
+                }
+                (Target::Channel(_), None) => {
+                    observable.sent(o, Err(Error::Delivery { observer_id: o.id }))
+                }
+                _ => observable.try_deliver(o, data),
+            };
+            report.push_result(o, &res);
+        }
+        observable.prune_now(s, &report.failed);
+        drop(state);
+        if parent.is_some() || !children.is_empty() {
+            observable.propagate_now(data, parent, children);
+        }
+        observable.log(format_args!(
+            "notified with permit: delivered={}, skipped={}, failed={}",
+            report.delivered.len(),
+            report.skipped.len(),
+            report.failed.len()
+        ));
+        Ok(report)
+    }
+}
+
+impl<T: Clone> ChObservable<T> {
+    /// Waits until every observer channel has free capacity for one value
+    /// and reserves it, independent of the overflow policy. The returned
+    /// permit passes the value without waiting, so a started notification
+    /// never stops in the middle. It fails with `Error::Closed` after
+    /// `drain` was called.
+    pub async fn reserve(&self) -> Result<NotifyPermit<'_, T>> {
+        let state = self.state.write().await;
+        if state.closed {
+            return Err(Error::Closed);
+        }
+        let mut permits = Vec::with_capacity(state.observers.len());
+        for o in state.observers.iter() {
+            permits.push(match &o.target {
+                Target::Channel(tx) => tx.clone().reserve_owned().await.ok(),
+                Target::Callback(_)
+                | Target::Unbounded(_)
+                | Target::Mapped(_)
+                | Target::Queue(_)
+                | Target::Borrowed(_) => None,
+            });
+        }
+        Ok(NotifyPermit {
+            observable: self,
+            state,
+            permits,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::chobservable::{ChObservable, Propagation};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_reserve() {
+        let cho: ChObservable<u32> = ChObservable::builder().capacity(1).build().unwrap();
+        let (id1, mut rx1) = cho.register().await;
+        let (id2, mut rx2) = cho.register().await;
+        let (dropped, rx3) = cho.register().await;
+        cho.notify(&1).await.unwrap();
+        drop(rx3);
+        assert_eq!(rx1.recv().await, Some(1));
+
+        // the permit waits for the full channel of the second observer
+        let consumer = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            assert_eq!(rx2.recv().await, Some(1));
+            rx2
+        });
+        let permit = cho.reserve().await.unwrap();
+        let mut rx2 = consumer.await.unwrap();
+        let report = permit.notify(&2).unwrap();
+        assert_eq!(report.delivered, vec![id1, id2]);
+        assert_eq!(report.failed, vec![dropped]);
+        assert!(!report.is_complete());
+        assert_eq!(rx1.try_recv(), Ok(2));
+        assert_eq!(rx2.try_recv(), Ok(2));
+        // the observer with the dropped receiver was removed
+        assert_eq!(cho.observer_ids().await, vec![id1, id2]);
+        assert_eq!(cho.prune_closed().await, 0);
+
+        // an unused permit releases the capacity
+        drop(cho.reserve().await.unwrap());
+        cho.notify(&3).await.unwrap();
+        assert_eq!(rx1.try_recv(), Ok(3));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_reserve_linked() {
+        let parent: ChObservable<u32> = ChObservable::new();
+        let child: ChObservable<u32> = ChObservable::new();
+        parent.add_child(&child, Propagation::Both).await.unwrap();
+        let (_, mut parent_rx) = parent.register().await;
+        let (_, mut child_rx) = child.register().await;
+
+        // the value bubbles up from the child
+        let report = child.reserve().await.unwrap().notify(&1).unwrap();
+        assert!(report.is_complete());
+        assert_eq!(child_rx.try_recv(), Ok(1));
+        assert_eq!(parent_rx.try_recv(), Ok(1));
+
+        // and goes down from the parent
+        parent.reserve().await.unwrap().notify(&2).unwrap();
+        assert_eq!(parent_rx.try_recv(), Ok(2));
+        assert_eq!(child_rx.try_recv(), Ok(2));
+    }
+}
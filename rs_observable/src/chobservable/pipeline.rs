@@ -0,0 +1,179 @@
+//! Transformations of the values of one observer, that are set up with
+//! the registration
+
+use std::fmt::{self, Debug, Formatter};
+use std::time::Duration;
+
+use tokio::sync::mpsc::{self, Receiver};
+use tokio::time::{sleep_until, Instant};
+
+use super::{ChObservable, Target};
+use crate::observer_id::ObserverId;
+
+/// Combined filter and map steps of a pipeline
+type Stage<T, U> = Box<dyn FnMut(T) -> Option<U> + Send>;
+
+/// Steps between the notified values and the channel of one observer. It's
+/// composed in the closure of `ChObservable::register_with`.
+pub struct Pipeline<T, U> {
+    stage: Stage<T, U>,
+    /// Quiet period, before the latest value is passed
+    debounce: Option<Duration>,
+}
+
+impl<T, U> Debug for Pipeline<T, U> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("debounce", &self.debounce)
+            .finish()
+    }
+}
+
+impl<T: Send + 'static> Pipeline<T, T> {
+    fn new() -> Self {
+        Pipeline {
+            stage: Box::new(Some),
+            debounce: None,
+        }
+    }
+}
+
+impl<T: Send + 'static, U: Send + 'static> Pipeline<T, U> {
+    /// Passes only the values, for that the predicate returns true
+    ///
+    /// ## Arguments
+    /// * `predicate` - decides if a value is passed
+    ///
+    pub fn filter<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&U) -> bool + Send + 'static,
+    {
+        let mut stage = self.stage;
+        self.stage = Box::new(move |v| stage(v).filter(|u| predicate(u)));
+        self
+    }
+
+    /// Converts the values
+    ///
+    /// ## Arguments
+    /// * `f` - returns the converted value
+    ///
+    pub fn map<V, F>(self, f: F) -> Pipeline<T, V>
+    where
+        F: Fn(U) -> V + Send + 'static,
+    {
+        let mut stage = self.stage;
+        Pipeline {
+            stage: Box::new(move |v| stage(v).map(&f)),
+            debounce: self.debounce,
+        }
+    }
+
+    /// Passes a value only after no other value followed for the given
+    /// time, values in between are dropped. The debounce is applied to the
+    /// output of the pipeline, independent of its position.
+    ///
+    /// ## Arguments
+    /// * `quiet` - time without new values, before the latest is passed
+    ///
+    pub fn debounce(mut self, quiet: Duration) -> Self {
+        self.debounce = Some(quiet);
+        self
+    }
+}
+
+impl<T: Clone + Send + 'static> ChObservable<T> {
+    /// This function registers a new observer, whose values pass the
+    /// pipeline composed by `build`, e.g.
+    /// `register_with(|p| p.filter(|v| *v > 0).map(|v| v * 2))`. The
+    /// pipeline runs in a task of the spawner of the observable, the
+    /// output channel has the configured capacity. It returns the ID of
+    /// the registered observer and a channel receiver to get the output
+    /// of the pipeline.
+    ///
+    /// ## Arguments
+    /// * `build` - composes the steps of the pipeline
+    ///
+    pub async fn register_with<U, F>(&self, build: F) -> (ObserverId, Receiver<U>)
+    where
+        U: Send + 'static,
+        F: FnOnce(Pipeline<T, T>) -> Pipeline<T, U>,
+    {
+        let Pipeline {
+            mut stage,
+            debounce,
+        } = build(Pipeline::new());
+        let (tx_in, mut rx_in) = mpsc::channel(self.config.capacity);
+        let (tx, rx) = mpsc::channel(self.config.capacity);
+        self.config.spawner.spawn(async move {
+            let mut pending: Option<(Instant, U)> = None;
+            loop {
+                let due = pending.as_ref().map(|(t, _)| *t);
+                let out = tokio::select! {
+                    v = rx_in.recv() => match v {
+                        Some(v) => match (stage(v), debounce) {
+                            (Some(u), Some(d)) => {
+                                pending = Some((Instant::now() + d, u));
+                                None
+                            }
+                            (u, _) => u,
+                        },
+                        // the latest value is passed when the observable is gone
+                        None => match pending.take() {
+                            Some((_, u)) => {
+                                let _ = tx.send(u).await;
+                                break;
+                            }
+                            None => break,
+                        },
+                    },
+                    _ = sleep_until(due.unwrap_or_else(Instant::now)), if due.is_some() => {
+                        pending.take().map(|(_, u)| u)
+                    }
+                };
+                if let Some(u) = out {
+                    if tx.send(u).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        let id = self.add_observer(Target::Channel(tx_in)).await;
+        (id, rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::chobservable::ChObservable;
+    use crate::testing::TestScheduler;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_with() {
+        let scheduler = TestScheduler::new();
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (_, mut even) = cho
+            .register_with(|p| p.filter(|v| v % 2 == 0).map(|v| format!("#{}", v)))
+            .await;
+        let (_, mut settled) = cho
+            .register_with(|p| p.debounce(Duration::from_secs(1)))
+            .await;
+
+        for i in 1..=4 {
+            cho.notify(&i).await.unwrap();
+        }
+        assert_eq!(even.recv().await, Some("#2".to_string()));
+        assert_eq!(even.recv().await, Some("#4".to_string()));
+        // only the value after the quiet period passes the debounce
+        assert_eq!(settled.recv().await, Some(4));
+        assert!(scheduler.elapsed() >= Duration::from_secs(1));
+
+        cho.notify(&5).await.unwrap();
+        drop(cho);
+        assert_eq!(settled.recv().await, Some(5));
+        assert_eq!(settled.recv().await, None);
+        assert_eq!(even.recv().await, None);
+    }
+}
@@ -0,0 +1,155 @@
+//! Marker for observed values, that are known to be invalid
+
+use tokio::sync::mpsc::Receiver;
+use tokio::sync::Mutex;
+
+use super::{ChObservable, ChObservedValue};
+use crate::error::{Error, Result};
+use crate::observer_id::ObserverId;
+
+/// State of an observed value, as it's passed to the observers of
+/// `ChObservedValue::register_state`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueState<T> {
+    /// No value is set
+    Unset,
+    /// The value is set
+    Set(T),
+    /// The value is known to be invalid, with the reason
+    Poisoned(String),
+}
+
+/// Poison marker of a value and the observers of its state
+pub(super) struct Status<T: Clone> {
+    reason: std::sync::Mutex<Option<String>>,
+    observable: Mutex<ChObservable<ValueState<T>>>,
+}
+
+impl<T: Clone> Status<T> {
+    pub(super) fn new() -> Self {
+        Status {
+            reason: std::sync::Mutex::new(None),
+            observable: Mutex::new(ChObservable::new()),
+        }
+    }
+
+    fn reason(&self) -> Option<String> {
+        self.reason.lock().unwrap().clone()
+    }
+
+    /// Removes the poison marker and passes the new state to the state
+    /// observers
+    pub(super) async fn restore(&self, state: ValueState<T>) -> Result<()> {
+        self.reason.lock().unwrap().take();
+        self.observable.lock().await.notify(&state).await?;
+        Ok(())
+    }
+
+    /// Removes the poison marker like `restore`, but passes the new state
+    /// without waiting. It returns false if the state observers are locked
+    /// at the moment and didn't get it.
+    pub(super) fn try_restore(&self, state: &ValueState<T>) -> bool {
+        self.reason.lock().unwrap().take();
+        match self.observable.try_lock() {
+            Ok(o) => !matches!(o.try_notify(state), Err(Error::WouldBlock)),
+            Err(_) => false,
+        }
+    }
+}
+
+impl<T: Clone> ChObservedValue<T> {
+    /// Removes the poison marker and passes the new state to the state
+    /// observers, it's called with every change of the value
+    pub(super) async fn restore(&self, state: ValueState<T>) -> Result<()> {
+        self.status.restore(state).await
+    }
+
+    /// Marks the value as invalid, e.g. after a failed update from its
+    /// source. The state observers get `ValueState::Poisoned`, the plain
+    /// observers are not notified. Reads with `value`, `value_ref` and
+    /// `value_mutref` fail until the value is set or reset again. The value is marked in any case, the
+    /// notification of the state observers fails like
+    /// `ChObservable::notify`.
+    ///
+    /// ## Arguments
+    /// * `reason` - description why the value is invalid
+    ///
+    pub async fn poison(&self, reason: impl Into<String>) -> Result<()> {
+        let reason = reason.into();
+        *self.status.reason.lock().unwrap() = Some(reason.clone());
+        let observable = self.status.observable.lock().await;
+        observable.notify(&ValueState::Poisoned(reason)).await?;
+        Ok(())
+    }
+
+    /// Returns true if the value is marked as invalid
+    pub fn is_poisoned(&self) -> bool {
+        self.status.reason().is_some()
+    }
+
+    /// Returns a copy of the current value. It fails with
+    /// `Error::Poisoned` if the value is marked as invalid.
+    pub async fn value(&self) -> Result<Option<T>> {
+        self.check_poison()?;
+        Ok(self.value.lock().await.clone())
+    }
+
+    /// Fails with `Error::Poisoned` if the value is marked as invalid
+    pub(super) fn check_poison(&self) -> Result<()> {
+        match self.status.reason() {
+            Some(reason) => Err(Error::Poisoned(reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// This function registers a new observer, that gets the state of the
+    /// value. Other than the plain observers it's also notified when the
+    /// value is poisoned. It returns the ID of the registered observer and
+    /// a channel receiver to get the states.
+    pub async fn register_state(&mut self) -> (ObserverId, Receiver<ValueState<T>>) {
+        self.status.observable.lock().await.register().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::{ChObservedValue, ValueState};
+    use crate::error::Error;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_poison() {
+        let mut config: ChObservedValue<u32> = ChObservedValue::new();
+        let (_, mut states) = config.register_state().await;
+        let (_, mut values) = config.register().await;
+        assert_eq!(config.value().await, Ok(None));
+
+        config.set_value(&1).await.unwrap();
+        config.poison("source unreachable").await.unwrap();
+        assert!(config.is_poisoned());
+        assert_eq!(
+            config.value().await,
+            Err(Error::Poisoned("source unreachable".to_string()))
+        );
+        assert!(matches!(config.value_ref(), Err(Error::Poisoned(_))));
+        assert!(matches!(config.value_mutref(), Err(Error::Poisoned(_))));
+
+        config.reset_value().await.unwrap();
+        assert!(config.value_ref().is_ok());
+        assert!(!config.is_poisoned());
+        assert_eq!(config.value().await, Ok(None));
+        config.set_value(&2).await.unwrap();
+        assert_eq!(config.value().await, Ok(Some(2)));
+
+        assert_eq!(states.recv().await, Some(ValueState::Set(1)));
+        assert_eq!(
+            states.recv().await,
+            Some(ValueState::Poisoned("source unreachable".to_string()))
+        );
+        assert_eq!(states.recv().await, Some(ValueState::Unset));
+        assert_eq!(states.recv().await, Some(ValueState::Set(2)));
+        // the plain observers don't see the poisoning
+        assert_eq!(values.recv().await, Some(Some(1)));
+        assert_eq!(values.recv().await, Some(None));
+        assert_eq!(values.recv().await, Some(Some(2)));
+    }
+}
@@ -0,0 +1,198 @@
+//! Buffers of observers, that are owned by the observable instead of a
+//! channel. They are unbounded, or drop the oldest value for a new one.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::sync::Notify;
+
+/// Values of an observer, that wait to be received
+struct Queue<T> {
+    values: Mutex<VecDeque<T>>,
+    /// Max number of kept values, `None` for an unbounded queue
+    capacity: Option<usize>,
+    /// Wakes the receiver after a value was added or the sender was dropped
+    pushed: Notify,
+    /// Wakes the tasks that wait for an empty queue, after a value was
+    /// received or the receiver was dropped
+    popped: Notify,
+    sender_dropped: AtomicBool,
+    receiver_dropped: AtomicBool,
+}
+
+impl<T> Queue<T> {
+    fn pop(&self) -> Option<T> {
+        let v = self.values.lock().unwrap().pop_front();
+        if v.is_some() {
+            self.popped.notify_waiters();
+        }
+        v
+    }
+
+    fn len(&self) -> usize {
+        self.values.lock().unwrap().len()
+    }
+}
+
+/// Sending side of a queue, it's kept by the observer
+pub(super) struct QueueSender<T>(Arc<Queue<T>>);
+
+impl<T> QueueSender<T> {
+    /// Creates a queue and returns both sides of it
+    ///
+    /// ## Arguments
+    /// * `capacity` - max number of kept values, `None` for no limit
+    ///
+    pub(super) fn new(capacity: Option<usize>) -> (Self, QueueReceiver<T>) {
+        let queue = Arc::new(Queue {
+            values: Mutex::new(VecDeque::new()),
+            capacity,
+            pushed: Notify::new(),
+            popped: Notify::new(),
+            sender_dropped: AtomicBool::new(false),
+            receiver_dropped: AtomicBool::new(false),
+        });
+        (QueueSender(queue.clone()), QueueReceiver(queue))
+    }
+
+    /// Adds a value to the queue. It returns true if the oldest value was
+    /// dropped for it, and the value back if the receiver was dropped.
+    pub(super) fn send(&self, v: T) -> Result<bool, T> {
+        if self.is_closed() {
+            return Err(v);
+        }
+        let mut values = self.0.values.lock().unwrap();
+        let full = self.0.capacity.is_some_and(|c| values.len() >= c);
+        if full {
+            values.pop_front();
+        }
+        values.push_back(v);
+        drop(values);
+        self.0.pushed.notify_one();
+        Ok(full)
+    }
+
+    /// Returns true if the receiver was dropped
+    pub(super) fn is_closed(&self) -> bool {
+        self.0.receiver_dropped.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of values, that wait to be received
+    pub(super) fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns a handle to wait for the receiver
+    pub(super) fn waiter(&self) -> QueueWaiter<T> {
+        QueueWaiter(self.0.clone())
+    }
+}
+
+impl<T> Debug for QueueSender<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Queue")
+            .field("capacity", &self.0.capacity)
+            .field("queued", &self.len())
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
+impl<T> Drop for QueueSender<T> {
+    fn drop(&mut self) {
+        self.0.sender_dropped.store(true, Ordering::SeqCst);
+        self.0.pushed.notify_one();
+    }
+}
+
+/// Waits for the receiver of a queue, without keeping the queue open
+pub(super) struct QueueWaiter<T>(Arc<Queue<T>>);
+
+impl<T> QueueWaiter<T> {
+    /// Waits until all values were received or the receiver was dropped
+    pub(super) async fn wait_empty(&self) {
+        loop {
+            let popped = self.0.popped.notified();
+            tokio::pin!(popped);
+            popped.as_mut().enable();
+            if self.queued() == 0 {
+                return;
+            }
+            popped.await;
+        }
+    }
+
+    /// Returns the number of values that were not received, values of a
+    /// dropped receiver are not counted
+    pub(super) fn queued(&self) -> usize {
+        if self.0.receiver_dropped.load(Ordering::SeqCst) {
+            return 0;
+        }
+        self.0.len()
+    }
+}
+
+/// Receiver of an observer, whose values are buffered by the observable.
+/// It's created by `ChObservable::register_drop_oldest` and
+/// `ChObservedValue::register_change`.
+pub struct QueueReceiver<T>(Arc<Queue<T>>);
+
+impl<T> QueueReceiver<T> {
+    /// Waits for the next value. It returns `None` after the observer was
+    /// removed and all values were received.
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            if let Some(v) = self.0.pop() {
+                return Some(v);
+            }
+            if self.0.sender_dropped.load(Ordering::SeqCst) {
+                return self.0.pop();
+            }
+            self.0.pushed.notified().await;
+        }
+    }
+
+    /// Returns the next value without waiting. It fails with
+    /// `TryRecvError::Empty` if no value is queued, and with
+    /// `TryRecvError::Disconnected` after the observer was removed and all
+    /// values were received.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        if let Some(v) = self.0.pop() {
+            return Ok(v);
+        }
+        if self.0.sender_dropped.load(Ordering::SeqCst) {
+            return self.0.pop().ok_or(TryRecvError::Disconnected);
+        }
+        Err(TryRecvError::Empty)
+    }
+
+    /// Returns the number of values, that wait to be received
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if no value waits to be received
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Debug for QueueReceiver<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueueReceiver")
+            .field("capacity", &self.0.capacity)
+            .field("queued", &self.len())
+            .finish()
+    }
+}
+
+impl<T> Drop for QueueReceiver<T> {
+    fn drop(&mut self) {
+        self.0.receiver_dropped.store(true, Ordering::SeqCst);
+        self.0.values.lock().unwrap().clear();
+        self.0.popped.notify_waiters();
+    }
+}
@@ -0,0 +1,127 @@
+//! Notification that succeeds only if enough observers accepted the value
+
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::Duration;
+
+use tokio::time::{timeout_at, Instant};
+
+use super::{tracker::Progress, ChObservable};
+use crate::error::{Error, Result};
+
+impl<T: Clone> ChObservable<T> {
+    /// Notifies the observers like `notify_deadline` and waits until at
+    /// least `min` observers accepted the value. A plain observer accepts
+    /// the value when it's passed to its channel, an observer of
+    /// `register_tracked` when it marked the value as processed. It fails
+    /// with `Error::Timeout` if not enough observers accepted the value
+    /// within the timeout. It fails with `Error::Closed` after `drain` was
+    /// called, and with `Error::Registration` if fewer than `min` observers
+    /// are registered. In both cases the value is not passed to the
+    /// observers.
+    ///
+    /// ## Arguments
+    /// * `data` - data that should be passed to the observers
+    /// * `min` - number of observers that have to accept the value
+    /// * `timeout` - max time for the delivery and the processing
+    ///
+    pub async fn notify_quorum(&self, data: &T, min: usize, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        {
+            let state = self.state.read().await;
+            if state.closed {
+                return Err(Error::Closed);
+            }
+            if state.observers.len() < min {
+                self.log(format_args!("quorum of {} observers can't be reached", min));
+                return Err(Error::Registration(
+                    "quorum larger than observer count".to_string(),
+                ));
+            }
+        }
+        let report = self.notify_deadline(data, deadline).await?;
+        let mut accepted = 0;
+        let mut pending: Vec<(Arc<Progress>, u64)> = Vec::new();
+        {
+            let state = self.state.read().await;
+            for o in state.observers.iter().filter(|o| report.delivered.contains(&o.id)) {
+                match &o.progress {
+                    Some(p) => pending.push((p.clone(), p.delivered_count())),
+                    None => accepted += 1,
+                }
+            }
+        }
+        let mut waits: Vec<Pin<Box<dyn Future<Output = ()> + Send + '_>>> = pending
+            .iter()
+            .map(|(p, count)| Box::pin(p.wait_processed_count(*count)) as _)
+            .collect();
+        let total = accepted + waits.len();
+        let reached = poll_fn(|cx| {
+            waits.retain_mut(|w| w.as_mut().poll(cx).is_pending());
+            let accepted = total - waits.len();
+            if accepted >= min {
+                Poll::Ready(true)
+            } else if waits.is_empty() {
+                Poll::Ready(false)
+            } else {
+                Poll::Pending
+            }
+        });
+        match timeout_at(deadline, reached).await {
+            Ok(true) => Ok(()),
+            _ => {
+                self.log(format_args!("quorum of {} observers not reached", min));
+                Err(Error::Timeout)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::chobservable::ChObservable;
+    use crate::error::Error;
+    use crate::testing::TestScheduler;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_notify_quorum() {
+        let scheduler = TestScheduler::new();
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (_, mut rx1, tracker1) = cho.register_tracked().await;
+        let (_, _rx2, _tracker2) = cho.register_tracked().await;
+        let (_, _rx3) = cho.register().await;
+
+        // the plain observer is the only one that accepts in time
+        let res = cho.notify_quorum(&1, 2, Duration::from_secs(1)).await;
+        assert_eq!(res, Err(Error::Timeout));
+        assert!(scheduler.elapsed() >= Duration::from_secs(1));
+        assert_eq!(cho.notify_quorum(&2, 1, Duration::from_secs(1)).await, Ok(()));
+
+        let replica = tokio::spawn(async move {
+            while rx1.recv().await.is_some() {
+                tracker1.mark_processed();
+            }
+        });
+        assert_eq!(cho.notify_quorum(&3, 2, Duration::from_secs(1)).await, Ok(()));
+        assert_eq!(
+            cho.notify_quorum(&4, 3, Duration::from_secs(1)).await,
+            Err(Error::Timeout)
+        );
+        assert_eq!(
+            cho.notify_quorum(&5, 4, Duration::from_secs(1)).await,
+            Err(Error::Registration("quorum larger than observer count".to_string()))
+        );
+
+        cho.drain(Duration::from_secs(1)).await;
+        assert_eq!(
+            cho.notify_quorum(&6, 1, Duration::from_secs(1)).await,
+            Err(Error::Closed)
+        );
+        drop(cho);
+        replica.await.unwrap();
+    }
+}
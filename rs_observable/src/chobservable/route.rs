@@ -0,0 +1,93 @@
+//! Routing of the variants of an enum observable to typed observables
+
+/// Routes the values of a `ChObservable` of an enum to one observable per
+/// variant. Every variant must have one field, it is the value that is
+/// notified on the observable of its route. The generated dispatch matches
+/// all variants without a fallback, so a variant without a route is a
+/// build error.
+///
+/// `route!(input, Event { Sensor => sensor_bus, Ui => ui_bus })` can be used
+/// in async code, it registers an observer on `input` and returns the
+/// `JoinHandle` of the routing task, that is spawned by the spawner of
+/// `input`. The target observables are moved into the task, so they are
+/// usually `Arc<ChObservable<_>>`. Failed notifications of the targets are
+/// reported over their error channels. The task ends, when `input` is
+/// dropped.
+///
+/// ```compile_fail,E0004
+/// use std::sync::Arc;
+/// use rs_observable::{route, ChObservable};
+///
+/// #[derive(Clone)]
+/// enum Event {
+///     Sensor(f64),
+///     Ui(String),
+/// }
+///
+/// async fn wire(input: &ChObservable<Event>, sensor_bus: Arc<ChObservable<f64>>) {
+///     // fails to build, because there is no route for `Event::Ui`
+///     route!(input, Event { Sensor => sensor_bus });
+/// }
+/// ```
+#[macro_export]
+macro_rules! route {
+    ($input:expr, $enum:ident { $($variant:ident => $target:expr),+ $(,)? }) => {
+        {
+            let spawner = $input.spawner().clone();
+            let (_, mut rx) = $input.register().await;
+            spawner.spawn(async move {
+                while let Some(value) = rx.recv().await {
+                    match value {
+                        $(
+                            $enum::$variant(v) => {
+                                let _ = $target.notify(&v).await;
+                            }
+                        )+
+                    }
+                }
+            })
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::chobservable::ChObservable;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Event {
+        Sensor(f64),
+        Ui(String),
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_route() {
+        let input: ChObservable<Event> = ChObservable::new();
+        let sensor_bus: ChObservable<f64> = ChObservable::new();
+        let ui_bus: ChObservable<String> = ChObservable::new();
+        let (_, mut sensor_rx) = sensor_bus.register().await;
+        let (_, mut ui_rx) = ui_bus.register().await;
+        let sensor_bus = Arc::new(sensor_bus);
+        let ui_bus = Arc::new(ui_bus);
+
+        let router = route!(input, Event {
+            Sensor => sensor_bus,
+            Ui => ui_bus,
+        });
+        input.notify(&Event::Sensor(1.5)).await.unwrap();
+        input.notify(&Event::Ui("click".to_string())).await.unwrap();
+        input.notify(&Event::Sensor(2.5)).await.unwrap();
+
+        assert_eq!(sensor_rx.recv().await, Some(1.5));
+        assert_eq!(sensor_rx.recv().await, Some(2.5));
+        assert_eq!(ui_rx.recv().await, Some("click".to_string()));
+
+        // the targets are dropped with the routing task
+        drop(input);
+        router.await.unwrap();
+        assert_eq!(sensor_rx.recv().await, None);
+        assert_eq!(ui_rx.recv().await, None);
+    }
+}
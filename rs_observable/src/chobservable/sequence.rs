@@ -0,0 +1,131 @@
+//! Observers that get numbered values, so they can detect the values they
+//! missed because of full channels
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::sync::mpsc::{self, Receiver};
+
+use super::ChObservable;
+use crate::observer_id::ObserverId;
+
+/// Value together with its position in the sequence of values, that the
+/// observable passed to one observer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sequenced<T> {
+    /// Position of the value, starts with 0
+    pub seq: u64,
+    /// Notified value
+    pub value: T,
+}
+
+/// Item returned by `SequencedReceiver::recv`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeqItem<T> {
+    /// The next value
+    Value(T),
+    /// Values were lost before the next value
+    Gap {
+        /// Number of lost values
+        missed: u64,
+    },
+}
+
+/// Receiver of numbered values, that reports the gaps in the sequence
+#[derive(Debug)]
+pub struct SequencedReceiver<T> {
+    rx: Receiver<Sequenced<T>>,
+    /// Expected position of the next value
+    next_seq: u64,
+    /// Value that follows a reported gap
+    pending: Option<T>,
+}
+
+impl<T> SequencedReceiver<T> {
+    /// Creates a new object that checks the values of the given receiver
+    ///
+    /// ## Arguments
+    /// * `rx` - receiver of numbered values
+    ///
+    pub fn new(rx: Receiver<Sequenced<T>>) -> Self {
+        SequencedReceiver {
+            rx,
+            next_seq: 0,
+            pending: None,
+        }
+    }
+
+    /// Receives the next item. If values were lost, a `SeqItem::Gap` is
+    /// returned before the next value. It returns `None` if the channel is
+    /// closed.
+    pub async fn recv(&mut self) -> Option<SeqItem<T>> {
+        if let Some(v) = self.pending.take() {
+            return Some(SeqItem::Value(v));
+        }
+        let s = self.rx.recv().await?;
+        let missed = s.seq.saturating_sub(self.next_seq);
+        self.next_seq = s.seq + 1;
+        if missed > 0 {
+            self.pending = Some(s.value);
+            return Some(SeqItem::Gap { missed });
+        }
+        Some(SeqItem::Value(s.value))
+    }
+}
+
+impl<T: Clone + Send + 'static> ChObservable<T> {
+    /// This function registers a new observer, that gets numbered values.
+    /// The values are passed without waiting, independent of the overflow
+    /// policy. If the channel is full, the value is dropped and the
+    /// receiver reports a gap before the next value. It returns the ID of
+    /// the registered observer and the receiver.
+    pub async fn register_sequenced(&self) -> (ObserverId, SequencedReceiver<T>) {
+        let (tx, rx) = mpsc::channel(self.config.capacity);
+        let next_seq = AtomicU64::new(0);
+        let probe = tx.clone();
+        let send = move |value: T| {
+            let seq = next_seq.fetch_add(1, Ordering::Relaxed);
+            let _ = tx.try_send(Sequenced { seq, value });
+        };
+        let id = self.add_forwarder(send, move || probe.is_closed()).await;
+        (id, SequencedReceiver::new(rx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::{ChObservable, SeqItem};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_sequenced() {
+        let cho: ChObservable<u32> = ChObservable::builder().capacity(2).build().unwrap();
+        let (_, mut rx) = cho.register_sequenced().await;
+        for i in 0..5 {
+            cho.notify(&i).await.unwrap();
+        }
+        assert_eq!(rx.recv().await, Some(SeqItem::Value(0)));
+        assert_eq!(rx.recv().await, Some(SeqItem::Value(1)));
+        cho.notify(&5).await.unwrap();
+        assert_eq!(rx.recv().await, Some(SeqItem::Gap { missed: 3 }));
+        assert_eq!(rx.recv().await, Some(SeqItem::Value(5)));
+        cho.notify(&6).await.unwrap();
+        assert_eq!(rx.recv().await, Some(SeqItem::Value(6)));
+        drop(cho);
+        assert_eq!(rx.recv().await, None);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_prune_dropped_sequenced() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (_, rx) = cho.register_sequenced().await;
+        drop(rx);
+        assert_eq!(cho.prune_closed().await, 1);
+        assert!(cho.state.read().await.observers.is_empty());
+
+        // the notification skips and prunes it as well
+        let (_, rx) = cho.register_sequenced().await;
+        drop(rx);
+        let report = cho.notify(&1).await.unwrap();
+        assert!(report.delivered.is_empty());
+        assert!(cho.state.read().await.observers.is_empty());
+    }
+}
@@ -0,0 +1,159 @@
+//! Consistent reads of several observed values
+//!
+//! The value locks are always taken in the same order, given by the
+//! address of the values, so concurrent snapshots can't deadlock. All locks
+//! are held until every value is copied, so there are no torn reads.
+
+use std::any::Any;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use tokio::sync::OwnedMutexGuard;
+
+use super::ChObservedValue;
+
+/// Type erased lock guard or value
+type Erased = Box<dyn Any + Send>;
+
+/// Future that returns the lock guard of a value
+type LockFuture<'a> = Pin<Box<dyn Future<Output = Erased> + Send + 'a>>;
+
+/// Value that can be part of a snapshot, used by `read_many!`
+#[doc(hidden)]
+pub trait SnapshotSource: Sync {
+    /// Key that gives the order of the locks
+    fn lock_key(&self) -> usize;
+    /// Locks the value and returns the guard
+    fn lock(&self) -> LockFuture<'_>;
+    /// Copies the value behind a guard returned by `lock`
+    fn read(&self, guard: &Erased) -> Erased;
+}
+
+impl<T: Clone + Send + 'static> SnapshotSource for ChObservedValue<T> {
+    fn lock_key(&self) -> usize {
+        Arc::as_ptr(&self.value) as usize
+    }
+
+    fn lock(&self) -> LockFuture<'_> {
+        let value = self.value.clone();
+        Box::pin(async move { Box::new(value.lock_owned().await) as Erased })
+    }
+
+    fn read(&self, guard: &Erased) -> Erased {
+        let g = guard
+            .downcast_ref::<OwnedMutexGuard<Option<T>>>()
+            .expect("guard of another value");
+        Box::new(Option::<T>::clone(g))
+    }
+}
+
+/// Locks all values in the canonical order and returns copies of them, in
+/// the order of the sources. A value that is given more than once is only
+/// locked once.
+#[doc(hidden)]
+pub async fn __read_many(sources: &[&dyn SnapshotSource]) -> Vec<Box<dyn Any + Send>> {
+    let mut order: Vec<&dyn SnapshotSource> = sources.to_vec();
+    order.sort_by_key(|s| s.lock_key());
+    order.dedup_by_key(|s| s.lock_key());
+    let mut guards: Vec<(usize, Erased)> = Vec::with_capacity(order.len());
+    for s in order {
+        guards.push((s.lock_key(), s.lock().await));
+    }
+    sources
+        .iter()
+        .map(|s| {
+            let i = guards
+                .binary_search_by_key(&s.lock_key(), |(k, _)| *k)
+                .expect("all sources are locked");
+            s.read(&guards[i].1)
+        })
+        .collect()
+}
+
+/// Converts one copy returned by `__read_many` back to the type of its
+/// source
+#[doc(hidden)]
+pub fn __read_many_value<T: Clone + 'static>(
+    _source: &ChObservedValue<T>,
+    value: Option<Box<dyn Any + Send>>,
+) -> Option<T> {
+    *value
+        .expect("one copy per source")
+        .downcast::<Option<T>>()
+        .expect("copy of another type")
+}
+
+impl<T: Clone + Send + 'static> ChObservedValue<T> {
+    /// Returns copies of the contents of several observed values, that were
+    /// read at the same time. Use `read_many!` for values of different types.
+    ///
+    /// ## Arguments
+    /// * `values` - observed values to read
+    ///
+    pub async fn snapshot(values: &[&ChObservedValue<T>]) -> Vec<Option<T>> {
+        let sources: Vec<&dyn SnapshotSource> =
+            values.iter().map(|v| *v as &dyn SnapshotSource).collect();
+        __read_many(&sources)
+            .await
+            .into_iter()
+            .zip(values.iter())
+            .map(|(c, v)| __read_many_value(v, Some(c)))
+            .collect()
+    }
+}
+
+/// Reads several `ChObservedValue`s, that may have different types, at
+/// the same time. The returned future resolves to a tuple with copies of
+/// the contents, e.g. `let (a, b) = read_many!(config, status).await;`
+/// returns an `(Option<Config>, Option<Status>)`.
+#[macro_export]
+macro_rules! read_many {
+    ($($v:expr),+ $(,)?) => {
+        async {
+            let copies = $crate::__read_many(&[$(&$v as &dyn $crate::SnapshotSource),+]).await;
+            let mut copies = copies.into_iter();
+            ($($crate::__read_many_value(&$v, copies.next()),)+)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::chobservable::ChObservedValue;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_read_many() {
+        let mut a: ChObservedValue<u32> = ChObservedValue::new();
+        let mut b: ChObservedValue<String> = ChObservedValue::new();
+        let c: ChObservedValue<u32> = ChObservedValue::new();
+        a.set_value(&1).await.unwrap();
+        b.set_value(&"one".to_string()).await.unwrap();
+
+        let (va, vb, vc) = read_many!(a, b, c).await;
+        assert_eq!(va, Some(1));
+        assert_eq!(vb, Some("one".to_string()));
+        assert_eq!(vc, None);
+        // the same value can be given twice
+        let (va1, va2) = read_many!(a, a).await;
+        assert_eq!((va1, va2), (Some(1), Some(1)));
+        assert_eq!(ChObservedValue::snapshot(&[&c, &a]).await, vec![None, Some(1)]);
+
+        // snapshots with different orders of the same values don't block
+        // each other
+        let a = std::sync::Arc::new(a);
+        let c = std::sync::Arc::new(c);
+        let (a2, c2) = (a.clone(), c.clone());
+        let t = tokio::spawn(async move {
+            for _ in 0..200 {
+                ChObservedValue::snapshot(&[&*a2, &*c2]).await;
+            }
+        });
+        for _ in 0..200 {
+            ChObservedValue::snapshot(&[&*c, &*a]).await;
+        }
+        tokio::time::timeout(Duration::from_secs(5), t).await.unwrap().unwrap();
+    }
+}
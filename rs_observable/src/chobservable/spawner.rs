@@ -0,0 +1,182 @@
+//! Control where the tasks are running, that are spawned by an observable
+
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+
+use log::warn;
+use tokio::runtime::Handle;
+use tokio::task::JoinHandle;
+
+use super::ChObservable;
+use crate::error::{Error, Result};
+
+/// Place where an observable spawns its internal tasks, e.g. the
+/// forwarding tasks of `join` and `route!` or the delayed removal of a
+/// dropped `Subscription`. It's set with `ChObservableBuilder::spawn_on`.
+#[derive(Debug, Clone, Default)]
+pub enum Spawner {
+    /// Runtime of the task that uses the observable
+    #[default]
+    Current,
+    /// Given runtime, e.g. to isolate the forwarding from the application
+    Handle(Handle),
+    /// `LocalSet` of the task that uses the observable, the tasks stay on
+    /// its thread. The observable has to be used inside of the `LocalSet`.
+    /// Only this spawner runs tasks that aren't `Send`, see `spawn_local`.
+    Local,
+}
+
+impl Spawner {
+    /// Spawns a task
+    ///
+    /// ## Arguments
+    /// * `task` - future to run
+    ///
+    pub fn spawn<F>(&self, task: F) -> JoinHandle<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        match self {
+            Spawner::Current => tokio::spawn(task),
+            Spawner::Handle(h) => h.spawn(task),
+            Spawner::Local => tokio::task::spawn_local(task),
+        }
+    }
+
+    /// Spawns a task, that isn't `Send`, on the `LocalSet` of the current
+    /// thread. It fails with `Error::Spawn` if the spawner isn't
+    /// `Spawner::Local` or if no `LocalSet` is active.
+    ///
+    /// ## Arguments
+    /// * `task` - future to run
+    ///
+    pub fn spawn_local<F>(&self, task: F) -> Result<JoinHandle<()>>
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        match self {
+            Spawner::Local => try_spawn_local(task),
+            _ => Err(Error::Spawn("tasks that aren't Send need Spawner::Local".to_string())),
+        }
+    }
+
+    /// Spawns a task like `spawn`, but doesn't panic outside of a runtime
+    /// or, with `Spawner::Local`, outside of a `LocalSet`. It returns false
+    /// if the task wasn't spawned.
+    pub(super) fn try_spawn<F>(&self, task: F) -> bool
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let spawned = match self {
+            Spawner::Handle(h) => {
+                h.spawn(task);
+                Ok(())
+            }
+            Spawner::Current => Handle::try_current()
+                .map(|h| drop(h.spawn(task)))
+                .map_err(|e| Error::Spawn(e.to_string())),
+            Spawner::Local => try_spawn_local(task).map(drop),
+        };
+        if let Err(e) = &spawned {
+            warn!("{}", e);
+        }
+        spawned.is_ok()
+    }
+}
+
+/// Spawns a task on the `LocalSet` of the current thread. tokio can't be
+/// asked for an active `LocalSet`, so the panic of `spawn_local` outside
+/// of it is turned into `Error::Spawn`.
+fn try_spawn_local<F>(task: F) -> Result<JoinHandle<()>>
+where
+    F: Future<Output = ()> + 'static,
+{
+    panic::catch_unwind(AssertUnwindSafe(|| tokio::task::spawn_local(task)))
+        .map_err(|_| Error::Spawn("no LocalSet is active".to_string()))
+}
+
+impl<T: Clone> ChObservable<T> {
+    /// Returns where the observable spawns its internal tasks
+    pub fn spawner(&self) -> &Spawner {
+        &self.config.spawner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::task::LocalSet;
+
+    use crate::chobservable::{join, ChObservable, Spawner};
+    use crate::error::Error;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Event {
+        Value(u32),
+    }
+
+    #[test]
+    fn test_spawn_on() {
+        let isolated = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .unwrap();
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let input: ChObservable<Event> = ChObservable::builder()
+            .spawn_on(Spawner::Handle(isolated.handle().clone()))
+            .build()
+            .unwrap();
+        let bus: ChObservable<u32> = ChObservable::new();
+        let (_, mut rx) = rt.block_on(bus.register());
+        let bus = Arc::new(bus);
+        rt.block_on(async {
+            let _router = crate::route!(input, Event { Value => bus });
+            input.notify(&Event::Value(1)).await.unwrap();
+            assert_eq!(rx.recv().await, Some(1));
+        });
+        // the routing task ends with its runtime, not with the input
+        isolated.shutdown_timeout(Duration::from_secs(1));
+        rt.block_on(async {
+            assert_eq!(rx.recv().await, None);
+        });
+
+        // joins on the thread of a LocalSet
+        let local = LocalSet::new();
+        local.block_on(&rt, async {
+            let a: ChObservable<u32> = ChObservable::builder()
+                .spawn_on(Spawner::Local)
+                .build()
+                .unwrap();
+            let b: ChObservable<u32> = ChObservable::new();
+            let mut pairs = join(&a, &b, |v| *v, |v| *v, Duration::from_secs(1)).await;
+            a.notify(&7).await.unwrap();
+            b.notify(&7).await.unwrap();
+            assert_eq!(pairs.recv().await, Some((7, 7)));
+
+            // tasks that aren't Send run on the LocalSet only
+            let shared = Rc::new(1);
+            let task = a.spawner().spawn_local(async move { assert_eq!(*shared, 1) });
+            task.unwrap().await.unwrap();
+            assert!(matches!(
+                b.spawner().spawn_local(async {}),
+                Err(Error::Spawn(_))
+            ));
+        });
+
+        // no fallback to the runtime without a LocalSet
+        rt.block_on(async {
+            assert!(matches!(
+                Spawner::Local.spawn_local(async {}),
+                Err(Error::Spawn(_))
+            ));
+            assert!(!Spawner::Local.try_spawn(async {}));
+        });
+    }
+}
@@ -0,0 +1,154 @@
+//! Separate handles to publish to and to subscribe at an observable, that
+//! can be passed to different tasks
+
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::mpsc::{Receiver, UnboundedReceiver};
+
+use super::{ChObservable, DeliveryReport};
+use crate::error::Result;
+use crate::observer_id::ObserverId;
+
+/// Handle to notify the observers of a split `ChObservable`, it's created
+/// by `ChObservable::split`. Clones notify the same observers.
+pub struct Notifier<T: Clone>(Arc<ChObservable<T>>);
+
+/// Handle to register observers at a split `ChObservable`, it's created by
+/// `ChObservable::split`. Clones register at the same observable.
+pub struct SubscriberHandle<T: Clone>(Arc<ChObservable<T>>);
+
+impl<T: Clone> Clone for Notifier<T> {
+    fn clone(&self) -> Self {
+        Notifier(self.0.clone())
+    }
+}
+
+impl<T: Clone> Clone for SubscriberHandle<T> {
+    fn clone(&self) -> Self {
+        SubscriberHandle(self.0.clone())
+    }
+}
+
+impl<T: Clone> Debug for Notifier<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Notifier").field(&self.0).finish()
+    }
+}
+
+impl<T: Clone> Debug for SubscriberHandle<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SubscriberHandle").field(&self.0).finish()
+    }
+}
+
+impl<T: Clone> ChObservable<T> {
+    /// Splits the observable into a handle to notify and a handle to
+    /// register observers. Both can be cloned and passed to other tasks,
+    /// without an extra `Arc<Mutex<..>>`. The observable is dropped with the
+    /// last handle.
+    pub fn split(self) -> (Notifier<T>, SubscriberHandle<T>) {
+        let shared = Arc::new(self);
+        (Notifier(shared.clone()), SubscriberHandle(shared))
+    }
+}
+
+impl<T: Clone> Notifier<T> {
+    /// Notifies the observers, like `ChObservable::notify`
+    ///
+    /// ## Arguments
+    /// * `data` - data that should be passed to the observers
+    pub async fn notify(&self, data: &T) -> Result<DeliveryReport> {
+        self.0.notify(data).await
+    }
+
+    /// Notifies the observers without waiting, like
+    /// `ChObservable::try_notify`
+    ///
+    /// ## Arguments
+    /// * `data` - data that should be passed to the observers
+    pub fn try_notify(&self, data: &T) -> Result<DeliveryReport> {
+        self.0.try_notify(data)
+    }
+
+    /// Notifies the observers with a time limit per observer, like
+    /// `ChObservable::notify_timeout`
+    ///
+    /// ## Arguments
+    /// * `data` - data that should be passed to the observers
+    /// * `send_timeout` - max time to wait for one observer
+    ///
+    pub async fn notify_timeout(&self, data: &T, send_timeout: Duration) -> Result<DeliveryReport> {
+        self.0.notify_timeout(data, send_timeout).await
+    }
+}
+
+impl<T: Clone> SubscriberHandle<T> {
+    /// This function registers a new observer, like `ChObservable::register`
+    pub async fn register(&self) -> (ObserverId, Receiver<T>) {
+        self.0.register().await
+    }
+
+    /// This function registers a new observer with an unbounded channel,
+    /// like `ChObservable::register_unbounded`
+    pub async fn register_unbounded(&self) -> (ObserverId, UnboundedReceiver<T>) {
+        self.0.register_unbounded().await
+    }
+
+    /// This function unregisters an observer. It fails if the ID was
+    /// created by another observable.
+    ///
+    /// ## Arguments
+    /// * `observer_id` - ID returned after the registration of an observer
+    ///
+    pub async fn unregister(&self, observer_id: ObserverId) -> Result<()> {
+        self.0.unregister(observer_id).await
+    }
+}
+
+impl<T: Clone + Send + 'static> SubscriberHandle<T> {
+    /// This function registers a callback as observer, like
+    /// `ChObservable::register_callback`
+    ///
+    /// ## Arguments
+    /// * `callback` - function that is called with every notified value
+    ///
+    pub async fn register_callback<F>(&self, callback: F) -> ObserverId
+    where
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        self.0.register_callback(callback).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::{ChObservable, Lifecycle};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_split() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (_, mut events) = cho.lifecycle().register().await;
+        let (notifier, subscribers) = cho.split();
+
+        let s = subscribers.clone();
+        let (id, mut rx) = tokio::spawn(async move { s.register().await }).await.unwrap();
+        let n = notifier.clone();
+        let report = tokio::spawn(async move { n.notify(&1).await }).await.unwrap();
+        assert_eq!(report.unwrap().delivered, vec![id]);
+        assert_eq!(rx.recv().await, Some(1));
+        notifier.try_notify(&2).unwrap();
+        assert_eq!(rx.recv().await, Some(2));
+        subscribers.unregister(id).await.unwrap();
+
+        // the observable ends with the last handle
+        drop(notifier);
+        assert_eq!(events.recv().await, Some(Lifecycle::Subscribed(id)));
+        assert_eq!(events.recv().await, Some(Lifecycle::Unsubscribed(id)));
+        assert!(events.try_recv().is_err());
+        drop(subscribers);
+        assert_eq!(events.recv().await, Some(Lifecycle::Closed));
+        assert_eq!(rx.recv().await, None);
+    }
+}
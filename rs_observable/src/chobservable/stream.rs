@@ -0,0 +1,261 @@
+//! Byte stream of the encoded state of a ChObservedValue, to connect the
+//! value with byte oriented IO like sockets or files
+
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Weak};
+use std::task::{ready, Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::mpsc::Receiver;
+use tokio::sync::Mutex;
+
+use super::{poison::Status, ChObservable, ChObservedValue};
+use crate::error::{Error, Result};
+
+/// Size of the length prefix of a frame
+const FRAME_HEADER_LEN: usize = 4;
+
+/// Converts values to bytes and back, e.g. with serde
+pub trait Codec<T> {
+    /// Returns the bytes of a value
+    fn encode(&self, value: &T) -> Vec<u8>;
+
+    /// Creates a value from its bytes. It fails if the bytes don't
+    /// contain a valid value.
+    fn decode(&self, bytes: &[u8]) -> Result<T>;
+}
+
+/// Applies written values to the observed value
+type SetFuture = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+/// `AsyncRead` and `AsyncWrite` adapter of a `ChObservedValue`, it's
+/// created by `ChObservedValue::byte_stream`. Every value is a frame of a
+/// 4 byte big endian length, followed by the encoded value.
+///
+/// Reading returns the current value and later every new value, resets
+/// are skipped. The stream ends, when the observed value is dropped.
+/// Every written frame sets the value, a frame that can't be decoded
+/// fails with `io::ErrorKind::InvalidData` and writes after the drop of
+/// the observed value fail with `io::ErrorKind::BrokenPipe`.
+pub struct ValueStream<T: Clone, C> {
+    /// State of the observed value, the stream doesn't keep it alive
+    value: Weak<Mutex<Option<T>>>,
+    observable: Weak<Mutex<ChObservable<Option<T>>>>,
+    status: Weak<Status<T>>,
+    codec: C,
+    rx: Receiver<Option<T>>,
+    /// Encoded frames that are not read yet
+    read_buf: Vec<u8>,
+    /// Written bytes that are not a complete frame yet
+    write_buf: Vec<u8>,
+    /// Sets the values of the last written frames
+    pending_set: Option<SetFuture>,
+}
+
+impl<T: Clone, C> Debug for ValueStream<T, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValueStream")
+            .field("unread", &self.read_buf.len())
+            .field("unwritten", &self.write_buf.len())
+            .field("pending_set", &self.pending_set.is_some())
+            .finish()
+    }
+}
+
+fn encode_frame<T, C: Codec<T>>(codec: &C, value: &T, buf: &mut Vec<u8>) {
+    let bytes = codec.encode(value);
+    buf.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    buf.extend_from_slice(&bytes);
+}
+
+impl<T: Clone + Send + Sync + 'static> ChObservedValue<T> {
+    /// Returns a byte stream to read the encoded value and its changes and
+    /// to set the value by writing encoded values
+    ///
+    /// ## Arguments
+    /// * `codec` - converts the values to bytes and back
+    ///
+    pub async fn byte_stream<C: Codec<T>>(&mut self, codec: C) -> ValueStream<T, C> {
+        let (_, rx) = self.register().await;
+        let mut read_buf = Vec::new();
+        if let Some(v) = self.value.lock().await.as_ref() {
+            encode_frame(&codec, v, &mut read_buf);
+        }
+        ValueStream {
+            value: Arc::downgrade(&self.value),
+            observable: Arc::downgrade(&self.observable),
+            status: Arc::downgrade(&self.status),
+            codec,
+            rx,
+            read_buf,
+            write_buf: Vec::new(),
+            pending_set: None,
+        }
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static, C> ValueStream<T, C> {
+    /// Returns a handle of the observed value, if it's still alive
+    fn upgrade(&self) -> Option<ChObservedValue<T>> {
+        Some(ChObservedValue {
+            value: self.value.upgrade()?,
+            observable: self.observable.upgrade()?,
+            history: None,
+            status: self.status.upgrade()?,
+            comparator: None,
+        })
+    }
+
+    /// Waits until the written values are set
+    fn poll_pending_set(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let Some(f) = &mut self.pending_set {
+            let res = ready!(f.as_mut().poll(cx));
+            self.pending_set = None;
+            res.map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T, C> AsyncRead for ValueStream<T, C>
+where
+    T: Clone + Send + Sync + 'static,
+    C: Codec<T> + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        while this.read_buf.is_empty() {
+            match ready!(this.rx.poll_recv(cx)) {
+                Some(Some(v)) => encode_frame(&this.codec, &v, &mut this.read_buf),
+                Some(None) => (),
+                None => return Poll::Ready(Ok(())),
+            }
+        }
+        let n = this.read_buf.len().min(buf.remaining());
+        buf.put_slice(&this.read_buf[..n]);
+        this.read_buf.drain(..n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T, C> AsyncWrite for ValueStream<T, C>
+where
+    T: Clone + Send + Sync + 'static,
+    C: Codec<T> + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending_set(cx))?;
+        this.write_buf.extend_from_slice(buf);
+        let mut values = Vec::new();
+        while this.write_buf.len() >= FRAME_HEADER_LEN {
+            let mut header = [0; FRAME_HEADER_LEN];
+            header.copy_from_slice(&this.write_buf[..FRAME_HEADER_LEN]);
+            let end = FRAME_HEADER_LEN + u32::from_be_bytes(header) as usize;
+            if this.write_buf.len() < end {
+                break;
+            }
+            let v = this.codec.decode(&this.write_buf[FRAME_HEADER_LEN..end]);
+            this.write_buf.drain(..end);
+            values.push(v.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?);
+        }
+        if !values.is_empty() {
+            let Some(mut value) = this.upgrade() else {
+                let e = Error::Transport("observed value was dropped".to_string());
+                return Poll::Ready(Err(io::Error::new(io::ErrorKind::BrokenPipe, e)));
+            };
+            this.pending_set = Some(Box::pin(async move {
+                for v in values {
+                    value.set_value(&v).await?;
+                }
+                Ok(())
+            }));
+            // starts the update, the result is awaited with the next write
+            // or flush, unless it's already done
+            if let Poll::Ready(Err(e)) = this.poll_pending_set(cx) {
+                return Poll::Ready(Err(e));
+            }
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_pending_set(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use crate::chobservable::{ChObservedValue, Codec};
+    use crate::error::{Error, Result};
+
+    struct TextCodec;
+
+    impl Codec<u32> for TextCodec {
+        fn encode(&self, value: &u32) -> Vec<u8> {
+            value.to_string().into_bytes()
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Result<u32> {
+            std::str::from_utf8(bytes)
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .ok_or_else(|| Error::Validation("no number".to_string()))
+        }
+    }
+
+    async fn read_frame<R: AsyncReadExt + Unpin>(r: &mut R) -> String {
+        let len = r.read_u32().await.unwrap();
+        let mut bytes = vec![0; len as usize];
+        r.read_exact(&mut bytes).await.unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_byte_stream() {
+        let mut value: ChObservedValue<u32> = ChObservedValue::new();
+        value.set_value(&1).await.unwrap();
+        let mut stream = value.byte_stream(TextCodec).await;
+        assert_eq!(read_frame(&mut stream).await, "1");
+        value.reset_value().await.unwrap();
+        value.set_value(&22).await.unwrap();
+        assert_eq!(read_frame(&mut stream).await, "22");
+
+        // a frame written in two parts
+        stream.write_all(&[0, 0, 0, 3, b'3']).await.unwrap();
+        stream.write_all(b"33").await.unwrap();
+        stream.flush().await.unwrap();
+        assert_eq!(*value.value_ref().unwrap().lock().await, Some(333));
+        assert_eq!(read_frame(&mut stream).await, "333");
+
+        let r = stream.write_all(&[0, 0, 0, 1, b'x']).await;
+        assert_eq!(r.unwrap_err().kind(), ErrorKind::InvalidData);
+        assert_eq!(*value.value_ref().unwrap().lock().await, Some(333));
+
+        drop(value);
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+        let r = stream.write_all(&[0, 0, 0, 1, b'4']).await;
+        assert_eq!(r.unwrap_err().kind(), ErrorKind::BrokenPipe);
+    }
+}
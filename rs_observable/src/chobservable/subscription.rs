@@ -0,0 +1,189 @@
+//! Registrations that end, when their guard is dropped
+
+use std::fmt::{self, Debug, Formatter};
+use std::sync::{Arc, OnceLock, Weak};
+
+use tokio::sync::mpsc::{self, Receiver};
+use tokio::sync::RwLock;
+
+use super::{ChObservable, Lifecycle, Spawner, State, Target};
+use crate::error::{Error, Result};
+use crate::observer_id::ObserverId;
+
+/// Registration of an observer, that is unregistered when the guard is
+/// dropped. It's created by `ChObservable::register_guarded` and contains
+/// the receiver of the observer.
+pub struct Subscription<T: Send + 'static> {
+    id: ObserverId,
+    rx: Receiver<T>,
+    state: Weak<RwLock<State<T>>>,
+    lifecycle: Weak<OnceLock<ChObservable<Lifecycle>>>,
+    spawner: Spawner,
+}
+
+impl<T: Send + 'static> Debug for Subscription<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription").field("id", &self.id).finish()
+    }
+}
+
+impl<T: Send + 'static> Subscription<T> {
+    /// Returns the ID of the registered observer
+    pub fn id(&self) -> ObserverId {
+        self.id
+    }
+
+    /// Receives the next value, like `Receiver::recv`
+    pub async fn recv(&mut self) -> Option<T> {
+        self.rx.recv().await
+    }
+
+    /// Waits for the next value like `recv`, but fails with
+    /// `Error::Closed` after the observer was removed, so it can be used
+    /// with `?`, e.g. in `tokio::select!`
+    pub async fn changed(&mut self) -> Result<T> {
+        self.rx.recv().await.ok_or(Error::Closed)
+    }
+
+    /// Returns the receiver of the observer
+    pub fn receiver(&mut self) -> &mut Receiver<T> {
+        &mut self.rx
+    }
+}
+
+impl<T: Send + 'static> Drop for Subscription<T> {
+    /// Closes the channel and removes the observer, the lifecycle
+    /// observers get `Lifecycle::Unsubscribed` like after `unregister`. If
+    /// the observers are locked at the moment, e.g. by a running
+    /// notification, the removal is done by a task of the spawner of the
+    /// observable. The event is passed by such a task as well, without a
+    /// runtime it's passed without waiting.
+    fn drop(&mut self) {
+        self.rx.close();
+        let Some(state) = self.state.upgrade() else {
+            return;
+        };
+        let id = self.id;
+        let lifecycle = self.lifecycle.upgrade();
+        let removed = match state.try_write() {
+            Ok(mut s) => Some(s.observers.remove(id).is_some()),
+            Err(_) => None,
+        };
+        let Some(removed) = removed else {
+            self.spawner.try_spawn(remove(state, lifecycle, id));
+            return;
+        };
+        let Some(l) = lifecycle.filter(|l| removed && l.get().is_some()) else {
+            return;
+        };
+        if !self.spawner.try_spawn(unsubscribed(l.clone(), id)) {
+            if let Some(l) = l.get() {
+                l.notify_now(&Lifecycle::Unsubscribed(id));
+            }
+        }
+    }
+}
+
+async fn remove<T: Send + 'static>(
+    state: Arc<RwLock<State<T>>>,
+    lifecycle: Option<Arc<OnceLock<ChObservable<Lifecycle>>>>,
+    id: ObserverId,
+) {
+    let removed = state.write().await.observers.remove(id).is_some();
+    if let Some(l) = lifecycle.filter(|_| removed) {
+        unsubscribed(l, id).await;
+    }
+}
+
+async fn unsubscribed(lifecycle: Arc<OnceLock<ChObservable<Lifecycle>>>, id: ObserverId) {
+    if let Some(l) = lifecycle.get() {
+        // fails only for a closed lifecycle observable
+        let _ = l.notify(&Lifecycle::Unsubscribed(id)).await;
+    }
+}
+
+impl<T: Clone + Send + 'static> ChObservable<T> {
+    /// This function registers a new observer and returns its guard. The
+    /// observer is unregistered when the guard is dropped, the lifecycle
+    /// observers get a `Lifecycle::Unsubscribed` event for it.
+    pub async fn register_guarded(&self) -> Subscription<T> {
+        let (tx, rx) = mpsc::channel(self.config.capacity);
+        let id = self.add_observer(Target::Channel(tx)).await;
+        Subscription {
+            id,
+            rx,
+            state: Arc::downgrade(&self.state),
+            lifecycle: Arc::downgrade(&self.lifecycle),
+            spawner: self.config.spawner.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::{ChObservable, Lifecycle};
+    use crate::error::Error;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_guarded() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let mut sub = cho.register_guarded().await;
+        let (_, mut rx) = cho.register().await;
+        cho.notify(&1).await.unwrap();
+        assert_eq!(sub.recv().await, Some(1));
+        assert!(format!("{:?}", cho).contains(&format!("id: {}", sub.id().value())));
+
+        let id = sub.id();
+        drop(sub);
+        assert!(!format!("{:?}", cho).contains(&format!("id: {}", id.value())));
+        // no delivery errors for the dropped subscription
+        cho.notify(&2).await.unwrap();
+        assert_eq!(rx.recv().await, Some(1));
+        assert_eq!(rx.recv().await, Some(2));
+
+        let mut sub = cho.register_guarded().await;
+        cho.notify(&3).await.unwrap();
+        assert_eq!(sub.changed().await, Ok(3));
+        cho.unregister(sub.id()).await.unwrap();
+        assert_eq!(sub.changed().await, Err(Error::Closed));
+
+        // the guard may outlive the observable
+        let sub = cho.register_guarded().await;
+        drop(cho);
+        drop(sub);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_guarded_lifecycle() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (_, mut events) = cho.lifecycle().register().await;
+        let sub = cho.register_guarded().await;
+        let id = sub.id();
+        assert_eq!(events.recv().await, Some(Lifecycle::Subscribed(id)));
+        drop(sub);
+        assert_eq!(events.recv().await, Some(Lifecycle::Unsubscribed(id)));
+        assert_eq!(cho.observer_count().await, 0);
+
+        // removed by a task while the observers are locked
+        let sub = cho.register_guarded().await;
+        let id = sub.id();
+        assert_eq!(events.recv().await, Some(Lifecycle::Subscribed(id)));
+        let g = cho.state.write().await;
+        drop(sub);
+        tokio::task::yield_now().await;
+        assert!(events.try_recv().is_err());
+        drop(g);
+        assert_eq!(events.recv().await, Some(Lifecycle::Unsubscribed(id)));
+        assert_eq!(cho.observer_count().await, 0);
+
+        // no second event after an unregister
+        let sub = cho.register_guarded().await;
+        let id = sub.id();
+        assert_eq!(events.recv().await, Some(Lifecycle::Subscribed(id)));
+        cho.unregister(id).await.unwrap();
+        assert_eq!(events.recv().await, Some(Lifecycle::Unsubscribed(id)));
+        drop(sub);
+        tokio::task::yield_now().await;
+        assert!(events.try_recv().is_err());
+    }
+}
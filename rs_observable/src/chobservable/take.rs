@@ -0,0 +1,87 @@
+//! Observers that are removed after a number of values
+
+use std::sync::atomic::AtomicUsize;
+
+use tokio::sync::mpsc::{self, Receiver};
+
+use super::{ChObservable, State, Target};
+use crate::observer_id::ObserverId;
+
+impl<T: Clone> ChObservable<T> {
+    /// This function registers a new observer, that is unregistered after
+    /// the first value, e.g. to wait for the next event. It returns the ID
+    /// of the registered observer and a channel receiver, that ends after
+    /// the value.
+    pub async fn register_once(&self) -> (ObserverId, Receiver<T>) {
+        self.register_take(1).await
+    }
+
+    /// This function registers a new observer, that is unregistered after
+    /// the given number of values. Replayed values are counted, dropped
+    /// values of a full channel are not. The lifecycle observers get
+    /// `Lifecycle::Unsubscribed` for the removal. It returns the ID of the
+    /// registered observer and a channel receiver, that ends after the
+    /// last value.
+    ///
+    /// ## Arguments
+    /// * `n` - number of values the observer gets
+    ///
+    pub async fn register_take(&self, n: usize) -> (ObserverId, Receiver<T>) {
+        let (tx, rx) = mpsc::channel(self.config.capacity);
+        let id = self
+            .add_observer_with(Target::Channel(tx), |o| o.remaining = Some(AtomicUsize::new(n)))
+            .await;
+        (id, rx)
+    }
+
+    /// Removes the observers that got all values of their limit and
+    /// returns their IDs
+    pub(super) fn remove_exhausted(&self, state: &mut State<T>) -> Vec<ObserverId> {
+        let mut removed = Vec::new();
+        if state.observers.iter().all(|o| o.remaining.is_none()) {
+            return removed;
+        }
+        state.observers.retain(|o| {
+            if o.is_exhausted() {
+                self.log(format_args!("remove observer after its last value: id={}", o.label()));
+                removed.push(o.id);
+            }
+            !o.is_exhausted()
+        });
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::{ChObservable, Lifecycle};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_take() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (_, mut events) = cho.lifecycle().register().await;
+        let (once, mut rx_once) = cho.register_once().await;
+        let (two, mut rx_two) = cho.register_take(2).await;
+        for i in 1..=3 {
+            cho.notify(&i).await.unwrap();
+        }
+        assert_eq!(rx_once.recv().await, Some(1));
+        assert_eq!(rx_once.recv().await, None);
+        assert_eq!(rx_two.recv().await, Some(1));
+        assert_eq!(rx_two.recv().await, Some(2));
+        assert_eq!(rx_two.recv().await, None);
+        assert_eq!(cho.observer_count().await, 0);
+        assert_eq!(events.recv().await, Some(Lifecycle::Subscribed(once)));
+        assert_eq!(events.recv().await, Some(Lifecycle::Subscribed(two)));
+        assert_eq!(events.recv().await, Some(Lifecycle::Unsubscribed(once)));
+        assert_eq!(events.recv().await, Some(Lifecycle::Unsubscribed(two)));
+
+        // replayed values are counted
+        let cho: ChObservable<u32> = ChObservable::builder().replay(2).build().unwrap();
+        cho.notify(&1).await.unwrap();
+        cho.notify(&2).await.unwrap();
+        let (_, mut rx) = cho.register_once().await;
+        assert_eq!(rx.recv().await, Some(1));
+        assert_eq!(rx.recv().await, None);
+    }
+}
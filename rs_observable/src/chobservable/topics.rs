@@ -0,0 +1,160 @@
+//! Event buses with a fixed set of typed topics, that are declared by the
+//! `topics!` macro
+
+use tokio::sync::mpsc::Receiver;
+
+use super::ChObservable;
+
+/// Topic of a bus, it's implemented by the topic types of `topics!`
+pub trait Topic: 'static {
+    /// Type of the values that are published on the topic
+    type Value: Clone + Send + 'static;
+}
+
+/// Access to the observable of a topic, it's implemented by the buses of
+/// `topics!` for each of their topics
+pub trait HasTopic<Tp: Topic> {
+    /// Returns the observable of the topic
+    fn topic(&self) -> &ChObservable<Tp::Value>;
+}
+
+/// Receiver of a topic subscription
+pub type TopicReceiver<Tp> = Receiver<<Tp as Topic>::Value>;
+
+/// Declares a bus with a fixed set of topics. Every topic is a zero sized
+/// type with the type of its values, the bus has one `ChObservable` per
+/// topic. `publish::<Topic>(&value)` and `subscribe::<Topic>()` of the bus
+/// are checked at compile time, a topic of another bus or a value of the
+/// wrong type is a build error.
+///
+/// ```
+/// use rs_observable::topics;
+///
+/// topics! {
+///     pub struct EventBus {
+///         SensorTemp: f64,
+///         UiClick: String,
+///     }
+/// }
+///
+/// async fn wire(bus: &EventBus) {
+///     let (_, mut rx) = bus.subscribe::<SensorTemp>().await;
+///     bus.publish::<SensorTemp>(&21.5).await.unwrap();
+///     assert_eq!(rx.recv().await, Some(21.5));
+/// }
+/// ```
+///
+/// ```compile_fail,E0277
+/// use rs_observable::topics;
+///
+/// topics! {
+///     pub struct EventBus {
+///         SensorTemp: f64,
+///     }
+/// }
+///
+/// pub struct Other;
+///
+/// impl rs_observable::Topic for Other {
+///     type Value = f64;
+/// }
+///
+/// async fn wire(bus: &EventBus) {
+///     // fails to build, because `Other` is no topic of the bus
+///     bus.publish::<Other>(&1.0).await.unwrap();
+/// }
+/// ```
+#[macro_export]
+macro_rules! topics {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $bus:ident {
+            $($(#[$tmeta:meta])* $topic:ident: $t:ty),+ $(,)?
+        }
+    ) => {
+        $(
+            $(#[$tmeta])*
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            $vis struct $topic;
+
+            impl $crate::Topic for $topic {
+                type Value = $t;
+            }
+        )+
+
+        $(#[$meta])*
+        #[derive(Debug)]
+        #[allow(non_snake_case)]
+        $vis struct $bus {
+            $($topic: $crate::ChObservable<$t>,)+
+        }
+
+        impl Default for $bus {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl $bus {
+            /// Creates a bus with a new observable per topic
+            pub fn new() -> Self {
+                $bus {
+                    $($topic: $crate::ChObservable::new(),)+
+                }
+            }
+
+            /// Notifies the observers of the topic, like `ChObservable::notify`
+            pub async fn publish<Tp: $crate::Topic>(
+                &self,
+                value: &Tp::Value,
+            ) -> $crate::Result<$crate::DeliveryReport>
+            where
+                Self: $crate::HasTopic<Tp>,
+            {
+                $crate::HasTopic::<Tp>::topic(self).notify(value).await
+            }
+
+            /// Registers a new observer of the topic, like `ChObservable::register`
+            pub async fn subscribe<Tp: $crate::Topic>(
+                &self,
+            ) -> ($crate::ObserverId, $crate::TopicReceiver<Tp>)
+            where
+                Self: $crate::HasTopic<Tp>,
+            {
+                $crate::HasTopic::<Tp>::topic(self).register().await
+            }
+        }
+
+        $(
+            impl $crate::HasTopic<$topic> for $bus {
+                fn topic(&self) -> &$crate::ChObservable<$t> {
+                    &self.$topic
+                }
+            }
+        )+
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    crate::topics! {
+        /// Bus of the test
+        struct TestBus {
+            /// Temperature in degree
+            SensorTemp: f64,
+            UiClick: String,
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_topics() {
+        let bus = TestBus::new();
+        let (_, mut temp) = bus.subscribe::<SensorTemp>().await;
+        let (_, mut clicks) = bus.subscribe::<UiClick>().await;
+        bus.publish::<SensorTemp>(&21.5).await.unwrap();
+        bus.publish::<UiClick>(&"ok".to_string()).await.unwrap();
+        assert_eq!(temp.recv().await, Some(21.5));
+        assert_eq!(clicks.recv().await, Some("ok".to_string()));
+        assert!(temp.try_recv().is_err());
+    }
+}
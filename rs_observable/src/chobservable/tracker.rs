@@ -0,0 +1,181 @@
+//! Acknowledgment of processed values, to tell apart values that were
+//! passed to the channel of an observer from values the observer handled
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::mpsc::{self, Receiver};
+use tokio::sync::Notify;
+
+use super::{ChObservable, Target};
+use crate::error::Result;
+use crate::observer_id::ObserverId;
+
+/// Counters of one tracked observer
+#[derive(Debug, Default)]
+pub(super) struct Progress {
+    /// Number of values passed to the channel
+    delivered: AtomicU64,
+    /// Number of values marked as processed by the observer
+    processed: AtomicU64,
+    /// Wakes the tasks that wait for the processing
+    changed: Notify,
+}
+
+impl Progress {
+    pub(super) fn add_delivered(&self, n: u64) {
+        self.delivered.fetch_add(n, Ordering::SeqCst);
+    }
+
+    fn unprocessed(&self) -> u64 {
+        let delivered = self.delivered.load(Ordering::SeqCst);
+        delivered.saturating_sub(self.processed.load(Ordering::SeqCst))
+    }
+
+    pub(super) fn delivered_count(&self) -> u64 {
+        self.delivered.load(Ordering::SeqCst)
+    }
+
+    /// Waits until the given number of values was marked as processed
+    pub(super) async fn wait_processed_count(&self, count: u64) {
+        loop {
+            let changed = self.changed.notified();
+            if self.processed.load(Ordering::SeqCst) >= count {
+                return;
+            }
+            changed.await;
+        }
+    }
+
+    async fn wait_processed(&self) {
+        loop {
+            let changed = self.changed.notified();
+            if self.unprocessed() == 0 {
+                return;
+            }
+            changed.await;
+        }
+    }
+}
+
+/// Handle of an observer to acknowledge its processed values, it's created
+/// by `ChObservable::register_tracked`. Clones share the counters.
+#[derive(Debug, Clone)]
+pub struct ProcessedTracker {
+    progress: Arc<Progress>,
+}
+
+impl ProcessedTracker {
+    /// Marks one received value as processed
+    pub fn mark_processed(&self) {
+        self.mark_processed_n(1);
+    }
+
+    /// Marks several received values as processed
+    ///
+    /// ## Arguments
+    /// * `n` - number of processed values
+    ///
+    pub fn mark_processed_n(&self, n: u64) {
+        self.progress.processed.fetch_add(n, Ordering::SeqCst);
+        self.progress.changed.notify_waiters();
+    }
+
+    /// Returns the number of values that were passed to the channel, but
+    /// not marked as processed yet
+    pub fn unprocessed_count(&self) -> u64 {
+        self.progress.unprocessed()
+    }
+}
+
+impl<T: Clone> ChObservable<T> {
+    /// This function registers a new observer, that acknowledges its
+    /// processed values. It returns the ID of the registered observer, a
+    /// channel receiver to get the new values and the tracker to mark the
+    /// values as processed.
+    pub async fn register_tracked(&self) -> (ObserverId, Receiver<T>, ProcessedTracker) {
+        let (tx, rx) = mpsc::channel(self.config.capacity);
+        let progress = Arc::new(Progress::default());
+        let p = progress.clone();
+        let id = self
+            .add_observer_with(Target::Channel(tx), |o| o.progress = Some(p))
+            .await;
+        (id, rx, ProcessedTracker { progress })
+    }
+
+    /// Returns the number of values that were passed to a tracked
+    /// observer, but not marked as processed yet. It returns `None` if
+    /// the observer isn't registered or not tracked. It fails if the ID was
+    /// created by another observable.
+    ///
+    /// ## Arguments
+    /// * `observer_id` - ID returned after the registration of an observer
+    ///
+    pub async fn unprocessed_count(&self, observer_id: ObserverId) -> Result<Option<u64>> {
+        observer_id.check_tag(self.tag)?;
+        let state = self.state.read().await;
+        Ok(state
+            .observers
+            .get(observer_id)
+            .and_then(|o| o.progress.as_ref())
+            .map(|p| p.unprocessed()))
+    }
+
+    /// Waits until all tracked observers have processed the values, that
+    /// were passed to them. Observers that are unregistered in between are
+    /// still waited for.
+    pub async fn wait_all_processed(&self) {
+        let progress: Vec<Arc<Progress>> = {
+            let state = self.state.read().await;
+            state
+                .observers
+                .iter()
+                .filter_map(|o| o.progress.clone())
+                .collect()
+        };
+        for p in progress {
+            p.wait_processed().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::chobservable::ChObservable;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_processed_tracker() {
+        let cho: ChObservable<u32> = ChObservable::builder().replay(1).build().unwrap();
+        cho.notify(&0).await.unwrap();
+        let (id, mut rx, tracker) = cho.register_tracked().await;
+        let (plain, _rx) = cho.register().await;
+        for i in 1..=3 {
+            cho.notify(&i).await.unwrap();
+        }
+        // the replayed value is tracked as well
+        assert_eq!(cho.unprocessed_count(id).await, Ok(Some(4)));
+        assert_eq!(cho.unprocessed_count(plain).await, Ok(None));
+
+        // delivered is not processed, the values are only marked after
+        // their handling
+        assert_eq!(rx.recv().await, Some(0));
+        assert_eq!(tracker.unprocessed_count(), 4);
+        tracker.mark_processed();
+        assert_eq!(cho.unprocessed_count(id).await, Ok(Some(3)));
+
+        let worker = tokio::spawn(async move {
+            while let Some(_v) = rx.recv().await {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                tracker.mark_processed();
+            }
+        });
+        tokio::time::timeout(Duration::from_secs(5), cho.wait_all_processed())
+            .await
+            .unwrap();
+        assert_eq!(cho.unprocessed_count(id).await, Ok(Some(0)));
+        drop(cho);
+        worker.await.unwrap();
+    }
+}
@@ -0,0 +1,82 @@
+//! Notification without waiting, for sync code
+
+use super::{ChObservable, DeliveryReport, State};
+use crate::error::{Error, Result};
+
+impl<T: Clone> ChObservable<T> {
+    /// Notifies the observers without waiting, so it can be called from
+    /// sync code, e.g. in `Drop` implementations. Observers with a full
+    /// channel are skipped, with `OverflowPolicy::Error` they fail. Linked
+    /// observables that are locked at the moment don't get the value. It
+    /// fails with `Error::WouldBlock` if the observers are locked by
+    /// another notification or registration, and with `Error::Closed`
+    /// after `drain` was called.
+    ///
+    /// ## Arguments
+    /// * `data` - data that should be passed to the observers
+    pub fn try_notify(&self, data: &T) -> Result<DeliveryReport> {
+        self.log_value(format_args!("received try notify request"), data);
+        let mut g = self.state.try_write().map_err(|_| Error::WouldBlock)?;
+        if g.closed {
+            return Err(Error::Closed);
+        }
+        let (report, parent, children) = self.notify_now_locked(&mut g, data);
+        drop(g);
+        if parent.is_some() || !children.is_empty() {
+            self.propagate_now(data, parent, children);
+        }
+        self.log(format_args!(
+            "try notified: delivered={}, skipped={}, failed={}",
+            report.delivered.len(),
+            report.skipped.len(),
+            report.failed.len()
+        ));
+        Ok(report)
+    }
+
+    /// Passes a value to the observers that aren't paused, without
+    /// waiting. Observers with a dropped receiver are removed afterwards.
+    pub(super) fn deliver_now(&self, state: &mut State<T>, data: &T) -> DeliveryReport {
+        let mut report = DeliveryReport::default();
+        for o in state.observers.iter().filter(|o| self.admits(state, o, data)) {
+            report.push_result(o, &self.try_deliver(o, data));
+        }
+        self.prune_now(state, &report.failed);
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::ChObservable;
+    use crate::error::Error;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_try_notify() {
+        let cho: ChObservable<u32> = ChObservable::builder().capacity(1).build().unwrap();
+        let (id1, mut rx1) = cho.register().await;
+        let (id2, mut rx2) = cho.register().await;
+        let report = cho.try_notify(&1).unwrap();
+        assert_eq!(report.delivered, vec![id1, id2]);
+
+        // the second channel is full, it's skipped without waiting
+        assert_eq!(rx1.recv().await, Some(1));
+        let report = cho.try_notify(&2).unwrap();
+        assert_eq!(report.delivered, vec![id1]);
+        assert_eq!(report.skipped, vec![id2]);
+        assert_eq!(rx1.recv().await, Some(2));
+
+        // a dropped receiver is removed without waiting
+        let (id3, rx3) = cho.register().await;
+        drop(rx3);
+        assert_eq!(cho.try_notify(&3).unwrap().failed, vec![id3]);
+        assert_eq!(cho.prune_closed().await, 0);
+        assert_eq!(rx1.recv().await, Some(3));
+
+        // the permit locks the observers
+        assert_eq!(rx2.recv().await, Some(1));
+        let permit = cho.reserve().await.unwrap();
+        assert_eq!(cho.try_notify(&4), Err(Error::WouldBlock));
+        drop(permit);
+    }
+}
@@ -0,0 +1,115 @@
+//! Queries of the former states of a ChObservedValue
+
+use std::sync::{Arc, Mutex};
+
+use tokio::time::Instant;
+
+use super::ChObservedValue;
+use crate::history::History;
+
+impl<T: Clone + Send + 'static> ChObservedValue<T> {
+    /// Starts to keep the changes of the value together with their time.
+    /// The current value is the first entry. Calling it again restarts the
+    /// history with the new depth, the changes are recorded by the same
+    /// observer.
+    ///
+    /// ## Arguments
+    /// * `depth` - max number of kept changes, the oldest are dropped first
+    ///
+    pub async fn enable_history(&mut self, depth: usize) {
+        let (current, observable) = self.lock_current().await;
+        let mut restarted = History::new(depth);
+        restarted.record(Instant::now(), current);
+        if let Some(history) = &self.history {
+            *history.lock().unwrap() = restarted;
+            return;
+        }
+        let history = Arc::new(Mutex::new(restarted));
+        let h = history.clone();
+        observable
+            .register_callback(move |v| h.lock().unwrap().record(Instant::now(), v))
+            .await;
+        drop(observable);
+        self.history = Some(history);
+    }
+
+    /// Returns the value that was valid at the given time. It returns `None`
+    /// if the value was unset, the time is older than the kept history or
+    /// the history isn't enabled.
+    ///
+    /// ## Arguments
+    /// * `at` - time to look up
+    ///
+    pub fn value_at(&self, at: Instant) -> Option<T> {
+        self.history.as_ref()?.lock().unwrap().value_at(at)
+    }
+
+    /// Returns the kept changes in the time range with the time of the
+    /// change, both ends are included. `None` stands for a reset.
+    ///
+    /// ## Arguments
+    /// * `from` - start of the range
+    /// * `to` - end of the range
+    ///
+    pub fn changes_between(&self, from: Instant, to: Instant) -> Vec<(Instant, Option<T>)> {
+        match &self.history {
+            Some(h) => h.lock().unwrap().changes_between(from, to),
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::chobservable::ChObservedValue;
+    use crate::testing::TestScheduler;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_value_history() {
+        let scheduler = TestScheduler::new();
+        let mut config: ChObservedValue<u32> = ChObservedValue::new();
+        config.set_value(&1).await.unwrap();
+        assert_eq!(config.value_at(scheduler.now()), None);
+        config.enable_history(3).await;
+        let start = scheduler.now();
+
+        scheduler.advance(Duration::from_secs(10)).await;
+        config.set_value(&2).await.unwrap();
+        scheduler.advance(Duration::from_secs(10)).await;
+        config.reset_value().await.unwrap();
+        let incident = scheduler.now() - Duration::from_secs(5);
+        assert_eq!(config.value_at(start), Some(1));
+        assert_eq!(config.value_at(incident), Some(2));
+        assert_eq!(config.value_at(scheduler.now()), None);
+        assert_eq!(
+            config.changes_between(start + Duration::from_secs(1), scheduler.now()),
+            vec![(start + Duration::from_secs(10), Some(2)), (scheduler.now(), None)]
+        );
+
+        // the oldest change is dropped
+        scheduler.advance(Duration::from_secs(10)).await;
+        config.set_value(&3).await.unwrap();
+        assert_eq!(config.value_at(start), None);
+        assert_eq!(config.value_at(incident), Some(2));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_restart_history() {
+        let scheduler = TestScheduler::new();
+        let mut config: ChObservedValue<u32> = ChObservedValue::new();
+        config.enable_history(3).await;
+        config.set_value(&1).await.unwrap();
+        scheduler.advance(Duration::from_secs(10)).await;
+        config.enable_history(1).await;
+        let start = scheduler.now();
+        assert_eq!(config.observable.lock().await.state.read().await.observers.len(), 1);
+
+        scheduler.advance(Duration::from_secs(10)).await;
+        config.set_value(&2).await.unwrap();
+        assert_eq!(config.value_at(scheduler.now()), Some(2));
+        // depth 1 keeps only the latest change
+        assert_eq!(config.value_at(start), None);
+    }
+}
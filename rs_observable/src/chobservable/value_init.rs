@@ -0,0 +1,193 @@
+//! Async observable wrapper around a value, that is always set
+
+use std::fmt::{self, Debug, Formatter};
+use std::ops::{Add, Sub};
+
+use tokio::sync::mpsc::{self, Receiver};
+use tokio::sync::Mutex;
+
+use super::{ChObservable, DeliveryReport, Target, TryLocked};
+use crate::error::Result;
+use crate::observer_id::ObserverId;
+
+/// Observable wrapper around a specific value. Other than `ChObservedValue`
+/// it starts with a value and can't be reset, so the observers get the
+/// plain value.
+pub struct ChObservedValueInit<T: Clone> {
+    pub(super) value: Mutex<T>,
+    pub(super) observable: Mutex<ChObservable<T>>,
+}
+
+impl<T: Clone + Debug> Debug for ChObservedValueInit<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChObservedValueInit")
+            .field("value", &TryLocked(&self.value))
+            .field("observable", &TryLocked(&self.observable))
+            .finish()
+    }
+}
+
+impl<T: Clone> ChObservedValueInit<T> {
+    /// Creates an new object
+    ///
+    /// ## Arguments
+    /// * `value` - initial value
+    ///
+    pub fn new(value: T) -> Self {
+        ChObservedValueInit {
+            value: Mutex::new(value),
+            observable: Mutex::new(ChObservable::new()),
+        }
+    }
+
+    /// Set a new value to the object. All registered observers are
+    /// called to get notified. The value is set in any case, the
+    /// notification fails like `ChObservable::notify`.
+    ///
+    /// ## Arguments
+    /// * `v` - value to set
+    ///
+    pub async fn set_value(&self, v: &T) -> Result<DeliveryReport> {
+        *self.value.lock().await = v.clone();
+        self.observable.lock().await.notify(v).await
+    }
+
+    /// Changes the value with a closure and notifies the observers with
+    /// the result, like `ChObservedValue::update`
+    ///
+    /// ## Arguments
+    /// * `f` - closure that changes the current value
+    ///
+    pub async fn update<F: FnOnce(&mut T)>(&self, f: F) -> Result<DeliveryReport> {
+        let new_v = {
+            let mut current = self.value.lock().await;
+            f(&mut current);
+            current.clone()
+        };
+        self.observable.lock().await.notify(&new_v).await
+    }
+
+    /// Returns a copy of the current value
+    pub async fn get(&self) -> T {
+        self.value.lock().await.clone()
+    }
+
+    /// This function registers a new observer. It returns the ID of the registered
+    /// observer and a channel receiver to get the new values
+    pub async fn register(&self) -> (ObserverId, Receiver<T>) {
+        self.observable.lock().await.register().await
+    }
+
+    /// This function registers a new observer, whose receiver gets the
+    /// current value as first message, like
+    /// `ChObservedValue::register_with_current`
+    pub async fn register_with_current(&self) -> (ObserverId, Receiver<T>) {
+        let observable = self.observable.lock().await;
+        let current = self.value.lock().await.clone();
+        let (tx, rx) = mpsc::channel(observable.config.capacity);
+        let _ = tx.try_send(current);
+        let id = observable.add_observer(Target::Channel(tx)).await;
+        (id, rx)
+    }
+
+    /// This function unregisters an observer. It fails if the ID was
+    /// created by another observable.
+    ///
+    /// ## Arguments
+    /// * `observer_id` - ID returned after the registration of an observer
+    ///
+    pub async fn unregister(&self, observer_id: ObserverId) -> Result<()> {
+        self.observable.lock().await.unregister(observer_id).await
+    }
+
+    /// Returns the number of registered observers, see
+    /// `ChObservable::observer_count`
+    pub async fn observer_count(&self) -> usize {
+        self.observable.lock().await.observer_count().await
+    }
+
+    /// Returns the IDs of the registered observers, see
+    /// `ChObservable::observer_ids`
+    pub async fn observer_ids(&self) -> Vec<ObserverId> {
+        self.observable.lock().await.observer_ids().await
+    }
+}
+
+impl<T: Clone + PartialEq> ChObservedValueInit<T> {
+    /// Sets a new value like `set_value`, but only if it differs from the
+    /// current one, like `ChObservedValue::set_value_if_changed`
+    ///
+    /// ## Arguments
+    /// * `v` - value to set
+    ///
+    pub async fn set_value_if_changed(&self, v: &T) -> Result<bool> {
+        {
+            let mut current = self.value.lock().await;
+            if *current == *v {
+                return Ok(false);
+            }
+            *current = v.clone();
+        }
+        self.observable.lock().await.notify(v).await?;
+        Ok(true)
+    }
+}
+
+impl<T: Clone + Add<Output = T> + Sub<Output = T>> ChObservedValueInit<T> {
+    /// Adds to the value and notifies the observers like `update`, for counters and
+    /// gauges. It returns the new value.
+    ///
+    /// ## Arguments
+    /// * `by` - amount to add
+    ///
+    pub async fn increment(&self, by: T) -> Result<T> {
+        self.step(|v| v + by).await
+    }
+
+    /// Subtracts from the value and notifies the observers, like
+    /// `increment`. It returns the new value.
+    ///
+    /// ## Arguments
+    /// * `by` - amount to subtract
+    ///
+    pub async fn decrement(&self, by: T) -> Result<T> {
+        self.step(|v| v - by).await
+    }
+
+    async fn step<F: FnOnce(T) -> T>(&self, f: F) -> Result<T> {
+        let mut new_v = None;
+        self.update(|v| {
+            *v = f(v.clone());
+            new_v = Some(v.clone());
+        })
+        .await?;
+        Ok(new_v.expect("update runs the closure"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::ChObservedValueInit;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_observed_value_init() {
+        let value = ChObservedValueInit::new(1u32);
+        let (_, mut current) = value.register_with_current().await;
+        let (id, mut rx) = value.register().await;
+        value.set_value(&2).await.unwrap();
+        assert_eq!(value.set_value_if_changed(&2).await, Ok(false));
+        value.update(|v| *v += 1).await.unwrap();
+        assert_eq!(value.get().await, 3);
+        assert_eq!(value.increment(2).await, Ok(5));
+        assert_eq!(value.decrement(1).await, Ok(4));
+
+        assert_eq!(current.recv().await, Some(1));
+        assert_eq!(current.recv().await, Some(2));
+        assert_eq!(rx.recv().await, Some(2));
+        assert_eq!(rx.recv().await, Some(3));
+        assert_eq!(rx.recv().await, Some(5));
+        assert_eq!(rx.recv().await, Some(4));
+        value.unregister(id).await.unwrap();
+        assert_eq!(value.observer_count().await, 1);
+    }
+}
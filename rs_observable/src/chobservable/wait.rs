@@ -0,0 +1,174 @@
+//! Futures that wait for a state of an observed value
+
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{oneshot, MutexGuard};
+
+use super::{ChObservable, ChObservedValue, ChObservedValueInit};
+use crate::error::{Error, Result};
+
+impl<T: Clone + Send + Sync + 'static> ChObservedValue<T> {
+    /// Waits until the value matches the predicate and returns it. It
+    /// returns right away if the current value matches, otherwise with the
+    /// first matching change. No change is skipped, even if several changes
+    /// happen before the waiting task runs. An unset value never matches.
+    /// It fails with `Error::Closed` if the waiting observer is removed
+    /// before, e.g. with `unregister`.
+    ///
+    /// ## Arguments
+    /// * `pred` - returns true for the value to wait for
+    ///
+    pub async fn wait_for<F>(&self, pred: F) -> Result<T>
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        let (current, observable) = self.lock_current().await;
+        if let Some(v) = current.filter(|v| pred(v)) {
+            return Ok(v);
+        }
+        let v = first_match(&self.observable, observable, move |v: &Option<T>| {
+            v.as_ref().is_some_and(&pred)
+        })
+        .await?;
+        // an unset value doesn't pass the predicate
+        v.ok_or(Error::Closed)
+    }
+
+    /// Waits for the next change of the value and returns the new state,
+    /// `None` after a reset. Only changes after the first poll of the
+    /// future are seen. The future can be dropped at any time, e.g. in
+    /// `tokio::select!`. It fails like `wait_for`.
+    pub async fn changed(&self) -> Result<Option<T>> {
+        let observable = self.observable.lock().await;
+        first_match(&self.observable, observable, |_| true).await
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> ChObservedValueInit<T> {
+    /// Waits until the value matches the predicate and returns it, like
+    /// `ChObservedValue::wait_for`
+    ///
+    /// ## Arguments
+    /// * `pred` - returns true for the value to wait for
+    ///
+    pub async fn wait_for<F>(&self, pred: F) -> Result<T>
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        let observable = self.observable.lock().await;
+        let current = self.value.lock().await.clone();
+        if pred(&current) {
+            return Ok(current);
+        }
+        first_match(&self.observable, observable, pred).await
+    }
+}
+
+/// Registers an observer at the locked observable, that fires once with
+/// the first value matching the predicate, and waits for it. The
+/// observable is unlocked while waiting. It fails with `Error::Closed` if
+/// the observer is removed without a matching value, e.g. by `clear`,
+/// `close` or the drop of the observable.
+///
+/// ## Arguments
+/// * `shared` - observable, to unregister the observer after it fired
+/// * `observable` - lock of the observable
+/// * `pred` - returns true for the value to wait for
+///
+async fn first_match<T, F>(
+    shared: &tokio::sync::Mutex<ChObservable<T>>,
+    observable: MutexGuard<'_, ChObservable<T>>,
+    pred: F,
+) -> Result<T>
+where
+    T: Clone + Send + 'static,
+    F: Fn(&T) -> bool + Send + Sync + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    let tx = Arc::new(Mutex::new(Some(tx)));
+    let probe = tx.clone();
+    let id = observable
+        .add_forwarder(
+            move |v| {
+                if pred(&v) {
+                    if let Some(tx) = tx.lock().unwrap().take() {
+                        let _ = tx.send(v);
+                    }
+                }
+            },
+            move || probe.lock().unwrap().as_ref().is_none_or(|tx| tx.is_closed()),
+        )
+        .await;
+    drop(observable);
+    let v = rx.await.map_err(|_| Error::Closed)?;
+    let _ = shared.lock().await.unregister(id).await;
+    Ok(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::{ChObservedValue, ChObservedValueInit};
+    use crate::error::Error;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_wait_for() {
+        let mut value: ChObservedValue<u32> = ChObservedValue::new();
+        value.set_value(&1).await.unwrap();
+        assert_eq!(value.wait_for(|v| *v == 1).await, Ok(1));
+
+        let mut setter = value.handle();
+        let waiting = tokio::spawn(async move { value.wait_for(|v| *v > 2).await });
+        tokio::task::yield_now().await;
+        assert_eq!(setter.observer_count().await, 1);
+        for i in 2..=4 {
+            setter.set_value(&i).await.unwrap();
+        }
+        assert_eq!(waiting.await.unwrap(), Ok(3));
+        assert_eq!(setter.observer_count().await, 0);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_wait_for_removed() {
+        // the waiting observer is removed before the value matches
+        let value: ChObservedValue<u32> = ChObservedValue::new();
+        let mut setter = value.handle();
+        let waiting = tokio::spawn(async move { value.wait_for(|v| *v > 2).await });
+        tokio::task::yield_now().await;
+        let ids = setter.observer_ids().await;
+        setter.unregister(ids[0]).await.unwrap();
+        assert_eq!(waiting.await.unwrap(), Err(Error::Closed));
+
+        let value = std::sync::Arc::new(ChObservedValueInit::new(1));
+        let v = value.clone();
+        let waiting = tokio::spawn(async move { v.wait_for(|v| *v > 2).await });
+        tokio::task::yield_now().await;
+        let id = value.observable.lock().await.observer_ids().await[0];
+        value.unregister(id).await.unwrap();
+        assert_eq!(waiting.await.unwrap(), Err(Error::Closed));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_changed() {
+        let value: ChObservedValue<u32> = ChObservedValue::new();
+        let mut setter = value.handle();
+        setter.set_value(&1).await.unwrap();
+        let waiting = tokio::spawn(async move {
+            let first = value.changed().await;
+            (first, value.changed().await)
+        });
+        tokio::task::yield_now().await;
+        setter.set_value(&2).await.unwrap();
+        tokio::task::yield_now().await;
+        setter.reset_value().await.unwrap();
+        assert_eq!(waiting.await.unwrap(), (Ok(Some(2)), Ok(None)));
+
+        // a dropped future is pruned like a dropped receiver
+        let value = setter.handle();
+        tokio::select! {
+            _ = value.changed() => panic!("value didn't change"),
+            _ = tokio::task::yield_now() => {}
+        }
+        setter.set_value(&3).await.unwrap();
+        assert_eq!(setter.observer_count().await, 0);
+    }
+}
@@ -0,0 +1,68 @@
+//! Observers of a ChObservedValue, that only see the latest value
+
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+use super::ChObservedValue;
+use crate::observer_id::ObserverId;
+
+impl<T: Clone + Send + Sync + 'static> ChObservedValue<T> {
+    /// This function registers a new observer, that gets the value over a
+    /// tokio watch channel. The receiver starts with the current value and
+    /// keeps only the latest one, so a slow observer skips the values in
+    /// between and never slows down `set_value`. Clones of the receiver
+    /// share the registration. It returns the ID of the registered
+    /// observer and the receiver.
+    pub async fn register_watch(&mut self) -> (ObserverId, watch::Receiver<Option<T>>) {
+        let (current, observable) = self.lock_current().await;
+        let (tx, rx) = watch::channel(current);
+        let tx = Arc::new(tx);
+        let probe = tx.clone();
+        let id = observable
+            .add_forwarder(
+                move |v| {
+                    tx.send_replace(v);
+                },
+                move || probe.is_closed(),
+            )
+            .await;
+        (id, rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chobservable::ChObservedValue;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_register_watch() {
+        let mut config: ChObservedValue<u32> = ChObservedValue::new();
+        config.set_value(&1).await.unwrap();
+        let (id, mut rx) = config.register_watch().await;
+        assert_eq!(*rx.borrow(), Some(1));
+
+        // the slow observer sees only the latest value
+        for i in 2..=5 {
+            config.set_value(&i).await.unwrap();
+        }
+        rx.changed().await.unwrap();
+        assert_eq!(*rx.borrow_and_update(), Some(5));
+        config.reset_value().await.unwrap();
+        rx.changed().await.unwrap();
+        assert_eq!(*rx.borrow(), None);
+
+        config.unregister(id).await.unwrap();
+        assert!(rx.changed().await.is_err());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_prune_dropped_watch() {
+        let mut config: ChObservedValue<u32> = ChObservedValue::new();
+        let (_, rx) = config.register_watch().await;
+        drop(rx);
+        let report = config.set_value(&1).await.unwrap();
+        assert!(report.delivered.is_empty());
+        assert!(config.observable.lock().await.state.read().await.observers.is_empty());
+    }
+}
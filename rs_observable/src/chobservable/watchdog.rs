@@ -0,0 +1,350 @@
+//! Watchdog over the delivery of one or more observables, that publishes
+//! its findings as an observable of health events
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use tokio::sync::RwLock;
+use tokio::time::Instant;
+
+use super::{ChObservable, Config, OverflowPolicy, State, Stats, Target};
+use crate::error::Result;
+use crate::observer_id::ObserverId;
+
+/// Finding of a `Watchdog` about a watched observable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealthEvent {
+    /// The channel of an observer was full at all checks for the stall
+    /// duration, so the observer doesn't keep up or stopped to receive
+    ObserverStalled {
+        /// Name of the observable
+        observable: Option<String>,
+        observer_id: ObserverId,
+        /// Time since the channel is full
+        since: Duration,
+    },
+    /// The channel of an observer became full
+    QueueSaturated {
+        /// Name of the observable
+        observable: Option<String>,
+        observer_id: ObserverId,
+        capacity: usize,
+    },
+    /// The part of the failed deliveries since the last check exceeded
+    /// the configured rate
+    DeliveryErrorRate {
+        /// Name of the observable
+        observable: Option<String>,
+        /// Failed deliveries since the last check
+        failed: u64,
+        /// Attempted deliveries since the last check
+        attempts: u64,
+    },
+}
+
+/// State of the watched observer channels at one check
+struct Sample {
+    delivered: u64,
+    failed: u64,
+    /// Observers with a full channel and the capacity of it, `None` if
+    /// the observers were locked by a notification
+    full: Option<Vec<(ObserverId, usize)>>,
+}
+
+/// Watched observable, it takes a sample at every check and returns
+/// `None` after the observable was dropped
+struct Source {
+    name: Option<String>,
+    sample: Box<dyn Fn() -> Option<Sample> + Send + Sync>,
+    delivered: u64,
+    failed: u64,
+    full_since: HashMap<ObserverId, (Instant, bool)>,
+}
+
+/// Watchdog over the delivery of observables. It's a `ChObservable` of
+/// `HealthEvent` itself, so alerts are registered like every other
+/// observer. The observables are checked with every call of `check`,
+/// e.g. from a tokio interval. The events are passed without waiting, so a
+/// slow alert observer doesn't stall the checks, it loses the events that
+/// don't fit into its channel.
+pub struct Watchdog {
+    observable: ChObservable<HealthEvent>,
+    sources: Vec<Source>,
+    stall_after: Duration,
+    max_error_rate: f64,
+}
+
+impl Debug for Watchdog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watchdog")
+            .field("sources", &self.sources.len())
+            .field("stall_after", &self.stall_after)
+            .field("max_error_rate", &self.max_error_rate)
+            .finish()
+    }
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for Watchdog {
+    type Target = ChObservable<HealthEvent>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.observable
+    }
+}
+
+impl DerefMut for Watchdog {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.observable
+    }
+}
+
+impl Watchdog {
+    /// Creates a watchdog, that reports observers as stalled after 5
+    /// seconds and error rates above 10%
+    pub fn new() -> Self {
+        Watchdog {
+            observable: ChObservable::with_config(Config {
+                overflow_policy: OverflowPolicy::DropNewest,
+                ..Config::default()
+            }),
+            sources: Vec::new(),
+            stall_after: Duration::from_secs(5),
+            max_error_rate: 0.1,
+        }
+    }
+
+    /// Sets the time, after which an observer with a full channel is
+    /// reported as stalled
+    ///
+    /// ## Arguments
+    /// * `stall_after` - time with a full channel
+    ///
+    pub fn stall_after(mut self, stall_after: Duration) -> Self {
+        self.stall_after = stall_after;
+        self
+    }
+
+    /// Sets the part of failed deliveries between two checks, above
+    /// which `HealthEvent::DeliveryErrorRate` is published
+    ///
+    /// ## Arguments
+    /// * `max_error_rate` - rate between 0.0 and 1.0
+    ///
+    pub fn max_error_rate(mut self, max_error_rate: f64) -> Self {
+        self.max_error_rate = max_error_rate;
+        self
+    }
+
+    /// Adds an observable to the watched ones. It's removed from the
+    /// watchdog after it was dropped.
+    ///
+    /// ## Arguments
+    /// * `observable` - observable to watch
+    ///
+    pub fn watch<T: Clone + Send + 'static>(&mut self, observable: &ChObservable<T>) {
+        let state = Arc::downgrade(&observable.state);
+        let stats = observable.stats.clone();
+        let (delivered, failed) = totals(&stats);
+        self.sources.push(Source {
+            name: observable.config.name.clone(),
+            sample: Box::new(move || take_sample(&state, &stats)),
+            delivered,
+            failed,
+            full_since: HashMap::new(),
+        });
+    }
+
+    /// Checks the watched observables and publishes the findings. The
+    /// channels of an observable that is locked by a notification are
+    /// checked next time. It returns the number of published events and
+    /// fails like `ChObservable::notify`.
+    pub async fn check(&mut self) -> Result<usize> {
+        let now = Instant::now();
+        let mut events = Vec::new();
+        self.sources.retain_mut(|source| match (source.sample)() {
+            Some(sample) => {
+                source.check(sample, now, self.stall_after, self.max_error_rate, &mut events);
+                true
+            }
+            None => false,
+        });
+        for e in events.iter() {
+            self.observable.notify(e).await?;
+        }
+        Ok(events.len())
+    }
+}
+
+impl Source {
+    fn check(
+        &mut self,
+        sample: Sample,
+        now: Instant,
+        stall_after: Duration,
+        max_error_rate: f64,
+        events: &mut Vec<HealthEvent>,
+    ) {
+        let failed = sample.failed - self.failed;
+        let attempts = sample.delivered - self.delivered + failed;
+        (self.delivered, self.failed) = (sample.delivered, sample.failed);
+        if failed > 0 && failed as f64 > attempts as f64 * max_error_rate {
+            events.push(HealthEvent::DeliveryErrorRate {
+                observable: self.name.clone(),
+                failed,
+                attempts,
+            });
+        }
+
+        let Some(full) = sample.full else {
+            return;
+        };
+        self.full_since.retain(|id, _| full.iter().any(|(f, _)| f == id));
+        for (observer_id, capacity) in full {
+            let (since, reported) = self.full_since.entry(observer_id).or_insert_with(|| {
+                events.push(HealthEvent::QueueSaturated {
+                    observable: self.name.clone(),
+                    observer_id,
+                    capacity,
+                });
+                (now, false)
+            });
+            if !*reported && now - *since >= stall_after {
+                *reported = true;
+                events.push(HealthEvent::ObserverStalled {
+                    observable: self.name.clone(),
+                    observer_id,
+                    since: now - *since,
+                });
+            }
+        }
+    }
+}
+
+/// Returns the delivered and failed values of an observable
+fn totals(stats: &Stats) -> (u64, u64) {
+    (
+        stats.delivered.load(Ordering::Relaxed),
+        stats.failed.load(Ordering::Relaxed),
+    )
+}
+
+/// Takes a sample of an observable, it returns `None` after the
+/// observable was dropped
+fn take_sample<T>(state: &Weak<RwLock<State<T>>>, stats: &Stats) -> Option<Sample> {
+    let state = state.upgrade()?;
+    let (delivered, failed) = totals(stats);
+    let full = state.try_read().ok().map(|state| {
+        state
+            .observers
+            .iter()
+            .filter_map(|o| match &o.target {
+                Target::Channel(tx) if !tx.is_closed() && tx.capacity() == 0 => {
+                    Some((o.id, tx.max_capacity()))
+                }
+                _ => None,
+            })
+            .collect()
+    });
+    Some(Sample {
+        delivered,
+        failed,
+        full,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use crate::chobservable::{ChObservable, HealthEvent, OverflowPolicy, Watchdog};
+    use crate::testing::TestScheduler;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_watchdog() {
+        let scheduler = TestScheduler::new();
+        let cho: ChObservable<u32> = ChObservable::builder()
+            .name("sensor")
+            .capacity(1)
+            .overflow_policy(OverflowPolicy::DropNewest)
+            .build()
+            .unwrap();
+        let mut watchdog = Watchdog::new().stall_after(Duration::from_secs(2));
+        watchdog.watch(&cho);
+        let (_, mut health) = watchdog.register().await;
+        let (id, _rx) = cho.register().await;
+        let name = Some("sensor".to_string());
+
+        cho.notify(&1).await.unwrap();
+        assert_eq!(watchdog.check().await.unwrap(), 1);
+        let saturated = HealthEvent::QueueSaturated {
+            observable: name.clone(),
+            observer_id: id,
+            capacity: 1,
+        };
+        assert_eq!(health.recv().await, Some(saturated));
+
+        scheduler.advance(Duration::from_secs(2)).await;
+        assert_eq!(watchdog.check().await.unwrap(), 1);
+        let stalled = HealthEvent::ObserverStalled {
+            observable: name.clone(),
+            observer_id: id,
+            since: Duration::from_secs(2),
+        };
+        assert_eq!(health.recv().await, Some(stalled));
+        // reported only once
+        assert_eq!(watchdog.check().await.unwrap(), 0);
+
+        let (_, rx2) = cho.register().await;
+        drop(rx2);
+        cho.notify(&2).await.unwrap();
+        assert_eq!(watchdog.check().await.unwrap(), 1);
+        let errors = HealthEvent::DeliveryErrorRate {
+            observable: name,
+            failed: 1,
+            attempts: 1,
+        };
+        assert_eq!(health.recv().await, Some(errors));
+
+        drop(cho);
+        assert_eq!(watchdog.check().await.unwrap(), 0);
+        assert!(format!("{:?}", watchdog).contains("sources: 0"));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_watchdog_slow_alerts() {
+        let cho: ChObservable<u32> = ChObservable::builder()
+            .capacity(1)
+            .overflow_policy(OverflowPolicy::DropNewest)
+            .build()
+            .unwrap();
+        let mut watchdog = Watchdog::new();
+        watchdog.watch(&cho);
+        let (_, mut health) = watchdog.register().await;
+        let mut receivers = Vec::new();
+        for _ in 0..20 {
+            receivers.push(cho.register().await.1);
+        }
+        cho.notify(&1).await.unwrap();
+
+        // more events than the alert channel takes, the check doesn't wait
+        let checked = timeout(Duration::from_secs(1), watchdog.check()).await;
+        assert_eq!(checked, Ok(Ok(20)));
+        let mut received = 0;
+        while health.try_recv().is_ok() {
+            received += 1;
+        }
+        assert_eq!(received, 10);
+    }
+}
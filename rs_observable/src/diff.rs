@@ -0,0 +1,197 @@
+//! Notifications that contain only the changed parts of an observed value
+
+use crate::change::Change;
+use crate::observable::Observer;
+use crate::observed_value::ObservedValue;
+use crate::observer_id::ObserverId;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Trait to implement, to notify observers only about the changed fields
+/// of a value, e.g. of a large config struct
+pub trait Diffable: Clone {
+    /// Description of the changed fields
+    type Diff: Clone;
+
+    /// Returns the changes from `old` to this value, `None` if nothing
+    /// changed
+    ///
+    /// ## Arguments
+    /// * `old` - former value
+    ///
+    fn diff(&self, old: &Self) -> Option<Self::Diff>;
+}
+
+/// Change of an observed value, as it's passed to diff observers
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueDiff<T: Diffable> {
+    /// The value was set, there was no former value to compare with
+    Set(T),
+    /// Changed fields of the value
+    Changed(T::Diff),
+    /// The value was reset
+    Reset,
+}
+
+/// Observer that keeps the former value to compute the diff
+struct DiffObserver<T, F> {
+    last: Option<T>,
+    f: F,
+}
+
+impl<T, F> Observer<Option<T>> for DiffObserver<T, F>
+where
+    T: Diffable,
+    F: FnMut(ValueDiff<T>),
+{
+    fn notify(&mut self, data: Option<T>) {
+        let change = match (&self.last, &data) {
+            (None, None) => None,
+            (Some(_), None) => Some(ValueDiff::Reset),
+            (None, Some(v)) => Some(ValueDiff::Set(v.clone())),
+            (Some(old), Some(v)) => v.diff(old).map(ValueDiff::Changed),
+        };
+        self.last = data;
+        if let Some(c) = change {
+            (self.f)(c);
+        }
+    }
+}
+
+/// Observer that keeps the former value, to pass it with every change
+struct ChangeObserver<T, F> {
+    last: Option<T>,
+    f: F,
+}
+
+impl<T: Clone, F: FnMut(Change<T>)> Observer<Option<T>> for ChangeObserver<T, F> {
+    fn notify(&mut self, data: Option<T>) {
+        (self.f)(Change::advance(&mut self.last, data));
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> ObservedValue<T> {
+    /// This function registers a new observer, that gets the former value
+    /// together with the new one. The current value is the first former
+    /// value. It returns the ID of the registered observer.
+    ///
+    /// ## Arguments
+    /// * `observer` - closure that is called with every change of the value
+    ///
+    pub fn register_change<F>(&mut self, observer: F) -> ObserverId
+    where
+        F: FnMut(Change<T>) + Send + Sync + 'static,
+    {
+        let o = ChangeObserver {
+            last: (**self).clone(),
+            f: observer,
+        };
+        self.register(Rc::new(RefCell::new(o)))
+    }
+}
+
+impl<T: Diffable + Send + Sync + 'static> ObservedValue<T> {
+    /// This function registers a new observer, that gets the changed fields
+    /// instead of the whole value. Changes without a difference are not
+    /// passed. The diff is computed from the value of the former
+    /// notification, the current value is the starting point. It returns
+    /// the ID of the registered observer.
+    ///
+    /// ## Arguments
+    /// * `observer` - closure that is called with every change of the value
+    ///
+    pub fn register_diff<F>(&mut self, observer: F) -> ObserverId
+    where
+        F: FnMut(ValueDiff<T>) + Send + Sync + 'static,
+    {
+        let o = DiffObserver {
+            last: (**self).clone(),
+            f: observer,
+        };
+        self.register(Rc::new(RefCell::new(o)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::change::Change;
+    use crate::diff::{Diffable, ValueDiff};
+    use crate::observed_value::ObservedValue;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Config {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Default)]
+    struct ConfigDiff {
+        host: Option<String>,
+        port: Option<u16>,
+    }
+
+    impl Diffable for Config {
+        type Diff = ConfigDiff;
+
+        fn diff(&self, old: &Self) -> Option<ConfigDiff> {
+            let d = ConfigDiff {
+                host: (self.host != old.host).then(|| self.host.clone()),
+                port: (self.port != old.port).then_some(self.port),
+            };
+            (d != ConfigDiff::default()).then_some(d)
+        }
+    }
+
+    #[test]
+    fn test_register_change() {
+        let mut value: ObservedValue<u32> = ObservedValue::new();
+        value.set_value(&1);
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let r = received.clone();
+        value.register_change(move |c| r.lock().unwrap().push(c));
+
+        value.set_value(&2);
+        value.reset_value();
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![
+                Change { old: Some(1), new: Some(2) },
+                Change { old: Some(2), new: None },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_register_diff() {
+        let mut config: ObservedValue<Config> = ObservedValue::new();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let r = received.clone();
+        config.register_diff(move |d| r.lock().unwrap().push(d));
+
+        let mut c = Config {
+            host: "localhost".to_string(),
+            port: 80,
+        };
+        config.set_value(&c);
+        c.port = 8080;
+        config.set_value(&c);
+        config.set_value(&c);
+        config.reset_value();
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![
+                ValueDiff::Set(Config {
+                    host: "localhost".to_string(),
+                    port: 80
+                }),
+                ValueDiff::Changed(ConfigDiff {
+                    host: None,
+                    port: Some(8080)
+                }),
+                ValueDiff::Reset,
+            ]
+        );
+    }
+}
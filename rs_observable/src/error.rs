@@ -0,0 +1,113 @@
+//! Crate wide error type
+
+use thiserror::Error as ThisError;
+
+use crate::observer_id::ObserverId;
+
+/// Errors returned by the fallible functions of this crate
+#[derive(Debug, Clone, PartialEq, Eq, ThisError)]
+pub enum Error {
+    /// An observer could not be registered or unregistered
+    #[error("registration failed: {0}")]
+    Registration(String),
+    /// The given observer ID was created by another observable
+    #[error("observer {0} belongs to another observable")]
+    ForeignObserverId(ObserverId),
+    /// A value could not be passed to an observer, e.g. because its
+    /// receiver was dropped
+    #[error("delivery to observer {observer_id} failed")]
+    Delivery {
+        /// ID of the observer that was not reached
+        observer_id: ObserverId,
+    },
+    /// An observer was removed, because its receiver was dropped
+    #[error("observer {observer_id} was pruned")]
+    Pruned {
+        /// ID of the removed observer
+        observer_id: ObserverId,
+    },
+    /// The interceptor of the observable vetoed the delivery of a value
+    /// to an observer
+    #[error("delivery to observer {observer_id} was vetoed")]
+    Vetoed {
+        /// ID of the observer that didn't get the value
+        observer_id: ObserverId,
+    },
+    /// The channel of an observer was full and the observable is
+    /// configured to fail in this case
+    #[error("channel of observer {observer_id} is full")]
+    Overflow {
+        /// ID of the observer with the full channel
+        observer_id: ObserverId,
+    },
+    /// An observer didn't take a value in the time, that was given to it
+    #[error("send to observer {observer_id} timed out")]
+    SendTimeout {
+        /// ID of the observer that was skipped
+        observer_id: ObserverId,
+    },
+    /// An operation didn't finish in the given time
+    #[error("operation timed out")]
+    Timeout,
+    /// The observable, or the channel used by the operation, is closed
+    #[error("observable is closed")]
+    Closed,
+    /// The value is leased by another task
+    #[error("value is leased")]
+    Busy,
+    /// The operation would have to wait, e.g. because the observers are
+    /// locked by another notification
+    #[error("operation would block")]
+    WouldBlock,
+    /// The value is marked as invalid
+    #[error("value is poisoned: {0}")]
+    Poisoned(String),
+    /// Arguments or values were rejected
+    #[error("validation failed: {0}")]
+    Validation(String),
+    /// A bridge or transport to another system failed
+    #[error("bridge/transport failure: {0}")]
+    Transport(String),
+    /// An observer could not process a notified value, it's returned by
+    /// a `TryObserver`
+    #[error("observer failed: {0}")]
+    Observer(String),
+    /// A task could not be spawned, e.g. because no `LocalSet` is active
+    #[error("task could not be spawned: {0}")]
+    Spawn(String),
+}
+
+/// Result type used by the fallible functions of this crate. The error
+/// type can be given, so the alias doesn't break `Result<T, E>` in code
+/// that imports it with the prelude.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{Error, Result};
+    use crate::observer_id::ObserverId;
+
+    fn delivery() -> Result<()> {
+        Err(Error::Delivery { observer_id: ObserverId::new(1, 3) })
+    }
+
+    fn forward() -> Result<u32> {
+        delivery()?;
+        Ok(1)
+    }
+
+    #[test]
+    fn test_error_display() {
+        let id = ObserverId::new(1, 3);
+        assert_eq!(forward(), Err(Error::Delivery { observer_id: id }));
+        assert_eq!(
+            Error::Delivery { observer_id: id }.to_string(),
+            "delivery to observer 3 failed"
+        );
+        assert_eq!(Error::Closed.to_string(), "observable is closed");
+        assert_eq!(
+            Error::Validation("empty name".to_string()).to_string(),
+            "validation failed: empty name"
+        );
+    }
+}
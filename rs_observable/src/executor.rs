@@ -0,0 +1,231 @@
+//! Executors that control where the callbacks of callback based
+//! observers are running
+
+use log::debug;
+use std::sync::mpsc;
+use tokio::runtime::Handle;
+
+/// Unit of work that is passed to an executor. It wraps the call of one
+/// observer callback with the notified value
+pub type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Trait to implement, to control where observer callbacks are executed
+pub trait NotifyExecutor: Send + Sync {
+    /// This function is called for every notification of a callback observer.
+    /// It has to run the job or to schedule it for later execution.
+    ///
+    /// ## Arguments
+    /// * `job` - callback call that should be executed
+    ///
+    fn execute(&self, job: Job);
+}
+
+/// Allows to use plain closures as executor, e.g. to hand the jobs over
+/// to the event loop of a GUI toolkit
+impl<F> NotifyExecutor for F
+where
+    F: Fn(Job) + Send + Sync,
+{
+    fn execute(&self, job: Job) {
+        self(job)
+    }
+}
+
+/// Runs the callback directly inside the notify call
+#[derive(Debug, Default, Clone, Copy)]
+pub struct InlineExecutor;
+
+impl NotifyExecutor for InlineExecutor {
+    fn execute(&self, job: Job) {
+        job();
+    }
+}
+
+/// Spawns every callback call as a new tokio task
+#[derive(Debug, Default, Clone)]
+pub struct TokioExecutor {
+    /// Runtime to spawn on, if not set the runtime of the notifying task is used
+    handle: Option<Handle>,
+}
+
+impl TokioExecutor {
+    /// Creates a new executor that spawns on the current runtime
+    pub fn new() -> Self {
+        TokioExecutor { handle: None }
+    }
+
+    /// Creates a new executor that spawns on the given runtime
+    ///
+    /// ## Arguments
+    /// * `handle` - handle of the runtime to use
+    ///
+    pub fn with_handle(handle: Handle) -> Self {
+        TokioExecutor {
+            handle: Some(handle),
+        }
+    }
+}
+
+impl NotifyExecutor for TokioExecutor {
+    fn execute(&self, job: Job) {
+        match &self.handle {
+            Some(h) => h.spawn(async move { job() }),
+            None => tokio::spawn(async move { job() }),
+        };
+    }
+}
+
+/// Runs every callback call on the blocking thread pool of tokio, suitable
+/// for observers that do blocking IO or heavy computations
+#[derive(Debug, Default, Clone)]
+pub struct BlockingExecutor {
+    /// Runtime to spawn on, if not set the runtime of the notifying task is used
+    handle: Option<Handle>,
+}
+
+impl BlockingExecutor {
+    /// Creates a new executor that uses the blocking pool of the current runtime
+    pub fn new() -> Self {
+        BlockingExecutor { handle: None }
+    }
+
+    /// Creates a new executor that uses the blocking pool of the given runtime
+    ///
+    /// ## Arguments
+    /// * `handle` - handle of the runtime to use
+    ///
+    pub fn with_handle(handle: Handle) -> Self {
+        BlockingExecutor {
+            handle: Some(handle),
+        }
+    }
+}
+
+impl NotifyExecutor for BlockingExecutor {
+    fn execute(&self, job: Job) {
+        match &self.handle {
+            Some(h) => h.spawn_blocking(job),
+            None => tokio::task::spawn_blocking(job),
+        };
+    }
+}
+
+/// Executor that puts the callback calls into a queue. The queue is processed
+/// by the thread that owns the related `MainThreadQueue`, e.g. the UI thread
+#[derive(Debug, Clone)]
+pub struct QueueExecutor {
+    tx: mpsc::Sender<Job>,
+}
+
+impl NotifyExecutor for QueueExecutor {
+    fn execute(&self, job: Job) {
+        if self.tx.send(job).is_err() {
+            debug!("queue of executor is gone, drop callback call");
+        }
+    }
+}
+
+/// Receiving side of a `QueueExecutor`. The jobs are only executed when
+/// the owner of this object calls `run_pending` or `run_next`
+#[derive(Debug)]
+pub struct MainThreadQueue {
+    rx: mpsc::Receiver<Job>,
+}
+
+impl MainThreadQueue {
+    /// Creates a new queue and the executor that feeds it
+    pub fn new() -> (QueueExecutor, MainThreadQueue) {
+        let (tx, rx) = mpsc::channel();
+        (QueueExecutor { tx }, MainThreadQueue { rx })
+    }
+
+    /// Executes all queued callback calls without waiting for new ones. It
+    /// returns the number of executed calls.
+    pub fn run_pending(&self) -> usize {
+        let mut count = 0;
+        while let Ok(job) = self.rx.try_recv() {
+            job();
+            count += 1;
+        }
+        count
+    }
+
+    /// Waits for the next queued callback call and executes it. It returns
+    /// false if all executors are dropped and no more calls can arrive.
+    pub fn run_next(&self) -> bool {
+        match self.rx.recv() {
+            Ok(job) => {
+                job();
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use crate::chobservable::ChObservable;
+    use crate::executor::{BlockingExecutor, InlineExecutor, MainThreadQueue, TokioExecutor};
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_inline_executor() {
+        let cho: ChObservable<String> = ChObservable::new();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let r = received.clone();
+        cho.register_callback_on(InlineExecutor, move |s: String| {
+            r.lock().unwrap().push(s);
+        })
+        .await;
+        cho.notify(&"test-1".to_string()).await.unwrap();
+        cho.notify(&"test-2".to_string()).await.unwrap();
+        assert_eq!(*received.lock().unwrap(), vec!["test-1", "test-2"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_tokio_and_blocking_executor() {
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let tx2 = tx.clone();
+        cho.register_callback_on(TokioExecutor::new(), move |v: u32| {
+            tx.send(("tokio", v)).unwrap();
+        })
+        .await;
+        cho.register_callback_on(BlockingExecutor::new(), move |v: u32| {
+            tx2.send(("blocking", v)).unwrap();
+        })
+        .await;
+        cho.notify(&42).await.unwrap();
+        let mut got = vec![rx.recv().await.unwrap(), rx.recv().await.unwrap()];
+        got.sort();
+        assert_eq!(got, vec![("blocking", 42), ("tokio", 42)]);
+    }
+
+    #[test]
+    fn test_main_thread_queue() {
+        let (executor, queue) = MainThreadQueue::new();
+        let main_thread = thread::current().id();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let r = received.clone();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let cho: ChObservable<u32> = ChObservable::new();
+            cho.register_callback_on(executor, move |v: u32| {
+                assert_eq!(thread::current().id(), main_thread);
+                r.lock().unwrap().push(v);
+            })
+            .await;
+            cho.notify(&1).await.unwrap();
+            cho.notify(&2).await.unwrap();
+        });
+        // nothing runs until the owning thread processes the queue
+        assert!(received.lock().unwrap().is_empty());
+        assert_eq!(queue.run_pending(), 2);
+        assert_eq!(*received.lock().unwrap(), vec![1, 2]);
+        // all executors are dropped together with the observable
+        assert!(!queue.run_next());
+    }
+}
@@ -0,0 +1,47 @@
+//! Timed history of the changes of an observed value
+
+use std::collections::VecDeque;
+
+/// Latest changes of a value with the time of the change, the oldest
+/// changes are dropped first
+#[derive(Debug)]
+pub(crate) struct History<I, T> {
+    changes: VecDeque<(I, Option<T>)>,
+    /// Max number of kept changes
+    depth: usize,
+}
+
+impl<I: Ord + Copy, T: Clone> History<I, T> {
+    pub(crate) fn new(depth: usize) -> Self {
+        History {
+            changes: VecDeque::new(),
+            depth: depth.max(1),
+        }
+    }
+
+    pub(crate) fn record(&mut self, at: I, value: Option<T>) {
+        if self.changes.len() == self.depth {
+            self.changes.pop_front();
+        }
+        self.changes.push_back((at, value));
+    }
+
+    /// Returns the value that was valid at the given time. It returns
+    /// `None` if the value was unset or the time is older than the history.
+    pub(crate) fn value_at(&self, at: I) -> Option<T> {
+        self.changes
+            .iter()
+            .rev()
+            .find(|(t, _)| *t <= at)
+            .and_then(|(_, v)| v.clone())
+    }
+
+    /// Returns the changes in the time range, both ends are included
+    pub(crate) fn changes_between(&self, from: I, to: I) -> Vec<(I, Option<T>)> {
+        self.changes
+            .iter()
+            .filter(|(t, _)| from <= *t && *t <= to)
+            .cloned()
+            .collect()
+    }
+}
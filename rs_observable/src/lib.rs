@@ -1,12 +1,101 @@
+// without any observable flavor the shared helpers are unused
+#![cfg_attr(
+    not(any(feature = "single", feature = "threaded", feature = "tokio")),
+    allow(dead_code)
+)]
+
+mod error;
+#[cfg(any(feature = "single", feature = "tokio"))]
+mod change;
+#[cfg(any(feature = "single", feature = "threaded"))]
+mod notified;
+#[cfg(feature = "threaded")]
+mod lock;
+mod observer_id;
+mod recording;
+// every observable flavor uses another part of the observer storage
+#[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+mod slab;
+#[cfg(any(feature = "single", feature = "tokio"))]
+mod history;
+pub mod prelude;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+#[cfg(feature = "single")]
 mod observable;
+#[cfg(feature = "single")]
 mod observed_value;
+#[cfg(feature = "single")]
+mod observed_value_init;
+#[cfg(feature = "single")]
+mod observed_flag;
+#[cfg(feature = "single")]
+mod macros;
+#[cfg(feature = "single")]
+mod diff;
+#[cfg(feature = "threaded")]
+mod aobservable;
+#[cfg(feature = "threaded")]
+mod aobserved_value;
+#[cfg(feature = "tokio")]
 mod chobservable;
+#[cfg(feature = "tokio")]
+mod executor;
+
+pub use error::{Error, Result};
+pub use observer_id::ObserverId;
+pub use recording::{Recorded, RecordingObserver};
+
+#[cfg(any(feature = "single", feature = "tokio"))]
+pub use change::Change;
+
+#[cfg(any(feature = "single", feature = "threaded"))]
+pub use notified::Notified;
+
+#[cfg(feature = "single")]
+pub use observable::{Observable, Observer, ObserverGuard, TryObserver};
 
 #[cfg(feature = "single")]
-pub use observable::{Observer, Observable};
+pub use observed_value::{ChangeGuard, ObservedValue};
 
 #[cfg(feature = "single")]
-pub use observed_value::ObservedValue;
+pub use observed_value_init::ObservedValueInit;
+
+#[cfg(feature = "single")]
+pub use observed_flag::ObservedFlag;
+
+#[cfg(feature = "single")]
+pub use diff::{Diffable, ValueDiff};
+
+#[cfg(feature = "single")]
+#[doc(hidden)]
+pub use macros::__observed_value_register_fn;
+
+#[cfg(feature = "threaded")]
+pub use aobservable::AObservable;
+
+#[cfg(feature = "threaded")]
+pub use aobserved_value::AObservedValue;
+
+#[cfg(feature = "tokio")]
+pub use chobservable::{
+    join, AdaptiveReceiver, AsyncFnHandle, ChKeyedObservable, ChObservable, ChObservableBuilder,
+    ChObservableMap, ChObservedFlag, ChObservedValue, ChObservedValueInit, Codec, DeliveryReport,
+    HasTopic, HealthEvent, IdleClose, Lifecycle, MapChange, MapSnapshot, MapUpdate, Notifier,
+    NotifyPermit, OverflowPolicy, Pipeline, ProcessedTracker, Propagation, QueueReceiver, SeqItem,
+    Sequenced, SequencedReceiver, Spawner, SubscriberHandle, Subscription, SyncHandle, Topic,
+    TopicReceiver, ValueLease, ValueState, ValueStream, Watchdog,
+};
+
+#[cfg(all(feature = "single", feature = "tokio"))]
+pub use chobservable::SyncMirror;
+
+#[cfg(feature = "tokio")]
+#[doc(hidden)]
+pub use chobservable::{SnapshotSource, __read_many, __read_many_value};
 
 #[cfg(feature = "tokio")]
-pub use chobservable::{ChObservable, ChObservedValue};
+pub use executor::{
+    BlockingExecutor, InlineExecutor, Job, MainThreadQueue, NotifyExecutor, QueueExecutor,
+    TokioExecutor,
+};
@@ -0,0 +1,47 @@
+//! Locks of the thread safe observables. With the `parking_lot` feature
+//! they come from the parking_lot crate, otherwise they wrap the std locks.
+//! Both don't know poisoning, a panicking observer doesn't make an
+//! observable unusable.
+
+#[cfg(feature = "parking_lot")]
+pub(crate) use parking_lot::{Mutex, RwLock};
+
+#[cfg(not(feature = "parking_lot"))]
+pub(crate) use self::std_lock::{Mutex, RwLock};
+
+#[cfg(not(feature = "parking_lot"))]
+mod std_lock {
+    use std::sync::{MutexGuard, RwLockReadGuard, RwLockWriteGuard};
+
+    /// std mutex, whose poisoning is ignored
+    #[derive(Debug, Default)]
+    pub(crate) struct Mutex<T>(std::sync::Mutex<T>);
+
+    impl<T> Mutex<T> {
+        pub(crate) fn new(v: T) -> Self {
+            Mutex(std::sync::Mutex::new(v))
+        }
+
+        pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+            self.0.lock().unwrap_or_else(|e| e.into_inner())
+        }
+    }
+
+    /// std read-write lock, whose poisoning is ignored
+    #[derive(Debug, Default)]
+    pub(crate) struct RwLock<T>(std::sync::RwLock<T>);
+
+    impl<T> RwLock<T> {
+        pub(crate) fn new(v: T) -> Self {
+            RwLock(std::sync::RwLock::new(v))
+        }
+
+        pub(crate) fn read(&self) -> RwLockReadGuard<'_, T> {
+            self.0.read().unwrap_or_else(|e| e.into_inner())
+        }
+
+        pub(crate) fn write(&self) -> RwLockWriteGuard<'_, T> {
+            self.0.write().unwrap_or_else(|e| e.into_inner())
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Macros to create single threaded observables together with their
+//! observers
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::observed_value::ObservedValue;
+use crate::observer_id::ObserverId;
+
+/// Creates an `Observable` and registers the given closures as observers.
+///
+/// `observable!(String)` creates an empty observable,
+/// `observable!(String; on_notify = |s| println!("{}", s))` registers one
+/// observer. `on_notify` can be repeated to register more of them.
+#[macro_export]
+macro_rules! observable {
+    ($t:ty) => {
+        $crate::Observable::<$t>::new()
+    };
+    ($t:ty; $(on_notify = $f:expr),+ $(,)?) => {{
+        let o = $crate::Observable::<$t>::new();
+        $(
+            o.register_fn($f);
+        )+
+        o
+    }};
+}
+
+/// Creates an `ObservedValue` that contains the given initial value and
+/// registers the given closures as observers. Setting the initial value
+/// doesn't notify the observers.
+///
+/// `observed_value!(Config::default(); on_change = |c| refresh(c))`
+/// registers one observer, that gets the `Option<Config>` of every
+/// change. `on_change` can be repeated to register more of them.
+#[macro_export]
+macro_rules! observed_value {
+    ($init:expr) => {
+        $crate::ObservedValue::with_value($init)
+    };
+    ($init:expr; $(on_change = $f:expr),+ $(,)?) => {{
+        let mut v = $crate::ObservedValue::with_value($init);
+        $(
+            $crate::__observed_value_register_fn(&mut v, $f);
+        )+
+        v
+    }};
+}
+
+/// Used by `observed_value!`, the `FnMut` bound lets the compiler infer the
+/// closure arguments
+#[doc(hidden)]
+pub fn __observed_value_register_fn<T, F>(v: &mut ObservedValue<T>, f: F) -> ObserverId
+where
+    T: Clone,
+    F: FnMut(Option<T>) + Send + Sync + 'static,
+{
+    v.register(Rc::new(RefCell::new(f)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Clone, PartialEq, Default)]
+    struct Config {
+        level: u32,
+    }
+
+    #[test]
+    fn test_observable_macro() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let r1 = received.clone();
+        let r2 = received.clone();
+        let o = observable!(String;
+            on_notify = move |s| r1.lock().unwrap().push(format!("1:{}", s)),
+            on_notify = move |s| r2.lock().unwrap().push(format!("2:{}", s)),
+        );
+        o.notify_observers("test".to_string());
+        assert_eq!(*received.lock().unwrap(), vec!["1:test", "2:test"]);
+
+        let empty = observable!(u32);
+        empty.notify_observers(1);
+    }
+
+    #[test]
+    fn test_observed_value_macro() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let r = received.clone();
+        let mut v = observed_value!(Config::default(); on_change = move |c| {
+            r.lock().unwrap().push(c);
+        });
+        // the initial value doesn't notify
+        assert_eq!(*v, Some(Config::default()));
+        assert!(received.lock().unwrap().is_empty());
+
+        v.set_value(&Config { level: 2 });
+        v.reset_value();
+        assert_eq!(*received.lock().unwrap(), vec![Some(Config { level: 2 }), None]);
+
+        let plain = observed_value!(42);
+        assert_eq!(*plain, Some(42));
+    }
+}
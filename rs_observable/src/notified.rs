@@ -0,0 +1,53 @@
+//! Result of a notification of the blocking observables
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+
+use log::warn;
+
+use crate::error::{Error, Result};
+use crate::observer_id::ObserverId;
+
+/// Result of a notification of `Observable` or `AObservable`, that tells
+/// how many observers were called and which of them failed or panicked
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Notified {
+    /// Number of called observers, including the panicked ones
+    pub called: usize,
+    /// Observers that panicked within their notify call
+    pub panicked: Vec<ObserverId>,
+    /// Errors that were returned by `TryObserver` implementations
+    pub failed: Vec<(ObserverId, Error)>,
+}
+
+impl Notified {
+    /// Returns true if no observer failed or panicked
+    pub fn is_complete(&self) -> bool {
+        self.panicked.is_empty() && self.failed.is_empty()
+    }
+
+    /// Calls one observer. A panic is caught and the observer is added to
+    /// the panicked ones, so the other observers are called anyway. A
+    /// returned error is added to the failed ones.
+    pub(crate) fn call<F: FnOnce() -> Result<()>>(&mut self, id: ObserverId, f: F) {
+        self.called += 1;
+        match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(Ok(())) => (),
+            Ok(Err(e)) => self.failed.push((id, e)),
+            Err(e) => {
+                warn!("observer panicked: id={}, {}", id, panic_message(&e));
+                self.panicked.push(id);
+            }
+        }
+    }
+}
+
+fn panic_message(e: &Box<dyn Any + Send>) -> &str {
+    if let Some(s) = e.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = e.downcast_ref::<String>() {
+        s
+    } else {
+        "unknown panic"
+    }
+}
@@ -1,7 +1,15 @@
-/// Simple single threaded observer pattern implementation
+//! Simple single threaded observer pattern implementation
 
-use std::rc::Rc;
-use std::cell::RefCell;
+use log::debug;
+use std::rc::{Rc, Weak};
+use std::cell::{Cell, RefCell};
+use std::fmt::{self, Debug, Formatter};
+use std::sync::mpsc;
+
+use crate::error::Result;
+use crate::notified::Notified;
+use crate::observer_id::{next_tag, ObserverId};
+use crate::slab::Slab;
 
 /// Trait to implement, to get informed about changes
 pub trait Observer<T: Clone> {
@@ -10,34 +18,200 @@ pub trait Observer<T: Clone> {
     fn notify(&mut self, data: T);
 }
 
+/// Observer that can fail to process a value. The returned errors are
+/// passed to the producer by `Observable::notify_observers`.
+pub trait TryObserver<T: Clone> {
+    /// This function is called to inform about changed data, it returns
+    /// an error if the data could not be processed
+    fn try_notify(&mut self, data: T) -> Result<()>;
+}
+
+/// Every closure that takes the notified value and returns a result can
+/// be used as fallible observer
+impl<T: Clone, F: FnMut(T) -> Result<()>> TryObserver<T> for F {
+    fn try_notify(&mut self, data: T) -> Result<()> {
+        self(data)
+    }
+}
+
+/// Every closure that takes the notified value can be used as observer
+impl<T: Clone, F: FnMut(T)> Observer<T> for F {
+    fn notify(&mut self, data: T) {
+        self(data);
+    }
+}
+
+/// Forwards the notified values into a std channel
+impl<T: Clone> Observer<T> for mpsc::Sender<T> {
+    fn notify(&mut self, data: T) {
+        if self.send(data).is_err() {
+            debug!("receiver of observer channel is gone, drop value");
+        }
+    }
+}
+
+/// Forwards the notified values into a tokio channel. Since the observer
+/// callback is not async, the value is dropped if the channel is full.
+#[cfg(feature = "tokio")]
+impl<T: Clone> Observer<T> for tokio::sync::mpsc::Sender<T> {
+    fn notify(&mut self, data: T) {
+        if let Err(e) = self.try_send(data) {
+            debug!("can't forward value to observer channel: {}", e);
+        }
+    }
+}
+
+/// Shared observer, as it's passed to the registration
+type SharedObserver<T> = Rc<RefCell<dyn Observer<T> + Send + Sync>>;
+
+/// Reference of the observable to a registered observer
+enum ObserverRef<T: Clone> {
+    /// The observable keeps the observer
+    Owned(SharedObserver<T>),
+    /// The observer is kept by an `ObserverGuard`, the second reference is
+    /// gone when the guard is dropped
+    Scoped(Weak<RefCell<dyn Observer<T> + Send + Sync>>, Weak<()>),
+    /// The observable keeps a fallible observer
+    Fallible(Rc<RefCell<dyn TryObserver<T> + Send + Sync>>),
+}
+
 struct StoredObserver<T: Clone> {
-    pub id: u32,
-    pub observer: Rc<RefCell<dyn Observer<T> + Send + Sync>>,
+    pub id: ObserverId,
+    pub observer: ObserverRef<T>,
 }
 
 impl<T: Clone> StoredObserver<T> {
-    pub fn new(id: u32, observer: Rc<RefCell<dyn Observer<T> + Send + Sync>>) -> Self {
+    pub fn new(id: ObserverId, observer: ObserverRef<T>) -> Self {
         StoredObserver{
             id,
             observer,
         }
     }
+
+    /// Passes a value to the observer, if it still exists. It returns the
+    /// error of a fallible observer.
+    fn call(&self, data: &T) -> Result<()> {
+        let observer = match &self.observer {
+            ObserverRef::Owned(o) => Some(o.clone()),
+            ObserverRef::Scoped(o, guard) => guard.upgrade().and(o.upgrade()),
+            ObserverRef::Fallible(o) => return o.borrow_mut().try_notify(data.clone()),
+        };
+        if let Some(o) = observer {
+            o.borrow_mut().notify(data.clone());
+        }
+        Ok(())
+    }
+
+    fn is_alive(&self) -> bool {
+        match &self.observer {
+            ObserverRef::Owned(_) | ObserverRef::Fallible(_) => true,
+            ObserverRef::Scoped(o, guard) => guard.strong_count() > 0 && o.strong_count() > 0,
+        }
+    }
+}
+
+/// Guard of a scoped registration, it's returned by
+/// `Observable::register_scoped`. The guard keeps the observer, when it's
+/// dropped the observer isn't called anymore.
+pub struct ObserverGuard<T: Clone> {
+    id: ObserverId,
+    _observer: SharedObserver<T>,
+    _alive: Rc<()>,
+}
+
+impl<T: Clone> Debug for ObserverGuard<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObserverGuard").field("id", &self.id).finish()
+    }
 }
 
-/// Type that provides the functions to orchestrate the Observer implementations
+impl<T: Clone> ObserverGuard<T> {
+    /// Returns the ID of the registered observer
+    pub fn id(&self) -> ObserverId {
+        self.id
+    }
+}
+
+/// Change of the observers, that was requested by an observer while the
+/// observers were notified
+enum Change<T: Clone> {
+    Add(StoredObserver<T>),
+    Remove(ObserverId),
+    Clear,
+}
+
+/// Type that provides the functions to orchestrate the Observer implementations.
+///
+/// Observers may register and unregister observers at the same observable
+/// within their notify call. These changes are applied after the running
+/// notification, so it still calls the removed observers and doesn't call
+/// the added ones.
 pub struct Observable<T: Clone> {
     /// List of registered observers
-    observers: Vec<StoredObserver<T>>,
+    observers: RefCell<Slab<StoredObserver<T>>>,
+    /// Number of entries after the last removal of dropped guards
+    swept_len: Cell<usize>,
     /// helper to stores the next ID assigned to a new registered Observer
-    next_id: u32,
+    next_id: Cell<u32>,
+    /// identifies this instance in the created observer IDs
+    tag: u64,
+    /// Number of running notifications, more than one if an observer
+    /// notifies again
+    dispatching: Cell<usize>,
+    /// Changes that wait for the end of the running notifications
+    pending: RefCell<Vec<Change<T>>>,
+}
+
+impl<T: Clone> Debug for Observable<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let ids: Vec<u32> = self.observer_ids().iter().map(|id| id.value()).collect();
+        f.debug_struct("Observable")
+            .field("observer_ids", &ids)
+            .field("next_id", &self.next_id.get())
+            .finish()
+    }
+}
+
+impl<T: Clone> Default for Observable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Marks a running notification, the last one applies the pending changes
+/// when it ends, also if an observer panicked
+struct Dispatch<'a, T: Clone>(&'a Observable<T>);
+
+impl<'a, T: Clone> Dispatch<'a, T> {
+    fn new(o: &'a Observable<T>) -> Self {
+        o.dispatching.set(o.dispatching.get() + 1);
+        Dispatch(o)
+    }
+}
+
+impl<T: Clone> Drop for Dispatch<'_, T> {
+    fn drop(&mut self) {
+        let o = self.0;
+        o.dispatching.set(o.dispatching.get() - 1);
+        if o.dispatching.get() == 0 {
+            let pending = std::mem::take(&mut *o.pending.borrow_mut());
+            for change in pending {
+                o.apply(change);
+            }
+        }
+    }
 }
 
 impl<T: Clone> Observable<T> {
     /// Creates a new Observable object
     pub fn new() -> Self {
         Observable {
-            observers: Vec::new(),
-            next_id: 1,
+            observers: RefCell::new(Slab::new()),
+            swept_len: Cell::new(0),
+            next_id: Cell::new(1),
+            tag: next_tag(),
+            dispatching: Cell::new(0),
+            pending: RefCell::new(Vec::new()),
         }
     }
 
@@ -47,55 +221,170 @@ impl<T: Clone> Observable<T> {
     /// ## Arguments
     /// * `observer` - implementation of the Observer trait that should be registered
     /// 
-    pub fn register(&mut self, observer: Rc<RefCell<dyn Observer<T> + Send + Sync>>) -> u32 {
-        let id = self.next_id;
-        self.next_id += 1;
-        self.observers.push(StoredObserver::new(id, observer));
+    pub fn register(&self, observer: Rc<RefCell<dyn Observer<T> + Send + Sync>>) -> ObserverId {
+        self.add_observer(ObserverRef::Owned(observer))
+    }
+
+    /// This function registers a fallible observer. Its errors are
+    /// returned by the notify functions. It returns the ID of the
+    /// registered observer.
+    ///
+    /// ## Arguments
+    /// * `observer` - implementation of the TryObserver trait that should be registered
+    ///
+    pub fn register_try(&self, observer: Rc<RefCell<dyn TryObserver<T> + Send + Sync>>) -> ObserverId {
+        self.add_observer(ObserverRef::Fallible(observer))
+    }
+
+    /// This function registers a closure as new observer, without the
+    /// `Rc<RefCell<..>>` wrapping. It returns the ID of the registered
+    /// observer.
+    ///
+    /// ## Arguments
+    /// * `observer` - closure that is called with every notified value
+    ///
+    pub fn register_fn<F>(&self, observer: F) -> ObserverId
+    where
+        F: FnMut(T) + Send + Sync + 'static,
+    {
+        self.register(Rc::new(RefCell::new(observer)))
+    }
+
+    /// This function registers a new observer, that is removed when the
+    /// returned guard is dropped. The observable keeps no reference to the
+    /// observer, the guard does.
+    ///
+    /// ## Arguments
+    /// * `observer` - implementation of the Observer trait that should be registered
+    ///
+    pub fn register_scoped(&self, observer: SharedObserver<T>) -> ObserverGuard<T> {
+        let alive = Rc::new(());
+        let scoped = ObserverRef::Scoped(Rc::downgrade(&observer), Rc::downgrade(&alive));
+        let id = self.add_observer(scoped);
+        ObserverGuard {
+            id,
+            _observer: observer,
+            _alive: alive,
+        }
+    }
+
+    fn add_observer(&self, observer: ObserverRef<T>) -> ObserverId {
+        let id = ObserverId::new(self.tag, self.next_id.get());
+        self.next_id.set(self.next_id.get() + 1);
+        self.change(Change::Add(StoredObserver::new(id, observer)));
         id
     }
 
-    /// This function unregisters an observer.
+    /// Applies a change of the observers, during a notification it's
+    /// applied after it
+    fn change(&self, change: Change<T>) {
+        if self.dispatching.get() > 0 {
+            self.pending.borrow_mut().push(change);
+        } else {
+            self.apply(change);
+        }
+    }
+
+    fn apply(&self, change: Change<T>) {
+        let mut observers = self.observers.borrow_mut();
+        match change {
+            Change::Add(o) => {
+                // entries of dropped guards are removed here, each time the
+                // number of entries doubled, so a registration takes
+                // constant time
+                if observers.len() >= 2 * self.swept_len.get() {
+                    observers.retain(|o| o.is_alive());
+                    self.swept_len.set(observers.len());
+                }
+                observers.insert(o.id, o);
+            }
+            Change::Remove(id) => {
+                observers.remove(id);
+            }
+            Change::Clear => {
+                *observers = Slab::new();
+                self.swept_len.set(0);
+            }
+        }
+    }
+
+    /// This function unregisters an observer. It fails if the ID was
+    /// created by another observable.
     /// 
     /// ## Arguments
     /// * `observer_id` - ID returned after the registration of an observer
     /// 
-    pub fn unregister(&mut self, observer_id: u32) {
-        let mut found: Option<usize> = None;
-        for (i, e) in self.observers.iter().enumerate() {
-            if e.id == observer_id {
-                found = Some(i);
-                break;
-            }
-        }
-        if let Some(index_to_remove) = found {
-            self.observers.remove(index_to_remove);
-        }
+    pub fn unregister(&self, observer_id: ObserverId) -> Result<()> {
+        observer_id.check_tag(self.tag)?;
+        self.change(Change::Remove(observer_id));
+        Ok(())
     }
 
     /// Triggers the notification of the restistered observers. This
-    /// function takes ownership of the parameter.
+    /// function takes ownership of the parameter. It returns the number of
+    /// called observers, so a producer can detect that nobody listens. A
+    /// panicking observer doesn't stop the notification, the returned
+    /// `Notified` lists it instead. The errors of fallible observers are
+    /// returned in it as well.
     /// 
     /// ## Arguments
     /// * `data` - data that should be passed to the observers
-    pub fn notify_observers(&self, data: T) {
-        for o in &self.observers {
-            o.observer.borrow_mut().notify(data.clone());
-        }
+    pub fn notify_observers(&self, data: T) -> Notified {
+        self.notify_observers_borrowed(&data)
     }
 
     /// Triggers the notification of the restistered observers. This
-    /// function takes no ownership of the parameter.
+    /// function takes no ownership of the parameter. It returns the number
+    /// of called observers and the failed ones, like `notify_observers`.
     /// 
     /// ## Arguments
     /// * `data` - data that should be passed to the observers
-    pub fn notify_observers_borrowed(&self, data: &T) {
-        for o in &self.observers {
-            o.observer.borrow_mut().notify(data.clone());
+    pub fn notify_observers_borrowed(&self, data: &T) -> Notified {
+        let _dispatch = Dispatch::new(self);
+        let observers = self.observers.borrow();
+        let mut notified = Notified::default();
+        for o in observers.iter().filter(|o| o.is_alive()) {
+            notified.call(o.id, || o.call(data));
         }
+        notified
+    }
+
+    /// Removes all observers, e.g. for a reconfiguration. It returns the
+    /// number of removed observers, observers of dropped guards are not
+    /// counted.
+    pub fn clear(&self) -> usize {
+        let n = self.observer_count();
+        self.change(Change::Clear);
+        n
+    }
+
+    /// Returns the number of registered observers. Observers of dropped
+    /// guards are not counted. Changes that wait for the end of a
+    /// notification are not counted yet.
+    pub fn observer_count(&self) -> usize {
+        self.observers.borrow().iter().filter(|o| o.is_alive()).count()
+    }
+
+    /// Returns the IDs of the registered observers in the order of
+    /// registration, e.g. for debug output. Observers of dropped guards
+    /// are skipped.
+    pub fn observer_ids(&self) -> Vec<ObserverId> {
+        self.observers
+            .borrow()
+            .iter()
+            .filter(|o| o.is_alive())
+            .map(|o| o.id)
+            .collect()
+    }
+
+    /// Returns true if no observer is registered
+    pub fn is_empty(&self) -> bool {
+        self.observer_count() == 0
     }
 
 }
 
+#[cfg(test)]
 mod tests {
     #![allow(dead_code)]
     use std::ops::Deref;
@@ -156,7 +445,7 @@ mod tests {
         use crate::observable::Observable;
 
 
-        let mut o = Observable::<MyString>::new();
+        let o = Observable::<MyString>::new();
         let s1 = Rc::new(RefCell::new(ObserverString::new("test1")));
         let s1_id = o.register(s1.clone());
         let s2 = Rc::new(RefCell::new(ObserverString::new("test2")));
@@ -174,7 +463,7 @@ mod tests {
         assert_eq!(s2.borrow().value, MyString::new("test4"));
         assert_eq!(s3.borrow().value, MyString::new("test4"));
 
-        o.unregister(s1_id);
+        o.unregister(s1_id).unwrap();
 
         o.notify_observers(MyString::new("test5"));
 
@@ -197,4 +486,192 @@ mod tests {
         assert_eq!(s3.borrow().value, MyString::new("test21"));
         assert_eq!(s4.borrow().value, MyString::new("test21"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_blanket_observers() {
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        use std::sync::{mpsc, Arc, Mutex};
+        use crate::observable::Observable;
+
+        let o = Observable::<MyString>::new();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let r = received.clone();
+        o.register(Rc::new(RefCell::new(move |s: MyString| {
+            r.lock().unwrap().push(s.0);
+        })));
+        let (tx, rx) = mpsc::channel();
+        o.register(Rc::new(RefCell::new(tx)));
+
+        o.notify_observers(MyString::new("test1"));
+        o.notify_observers(MyString::new("test2"));
+
+        assert_eq!(*received.lock().unwrap(), vec!["test1", "test2"]);
+        assert_eq!(rx.try_recv().unwrap(), MyString::new("test1"));
+        assert_eq!(rx.try_recv().unwrap(), MyString::new("test2"));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_register_fn() {
+        use std::sync::{Arc, Mutex};
+        use crate::observable::Observable;
+
+        let o = Observable::<MyString>::new();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let r = received.clone();
+        let id = o.register_fn(move |s| r.lock().unwrap().push(s.0));
+        o.notify_observers(MyString::new("test1"));
+        o.unregister(id).unwrap();
+        o.notify_observers(MyString::new("test2"));
+        assert_eq!(*received.lock().unwrap(), vec!["test1"]);
+    }
+
+    #[test]
+    fn test_register_scoped() {
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        use crate::observable::Observable;
+
+        let o = Observable::<MyString>::new();
+        assert!(o.is_empty());
+        let s1 = Rc::new(RefCell::new(ObserverString::new("test1")));
+        let guard = o.register_scoped(s1.clone());
+        let s2 = Rc::new(RefCell::new(ObserverString::new("test2")));
+        let id2 = o.register(s2.clone());
+        assert_eq!(o.notify_observers(MyString::new("test3")).called, 2);
+        assert_eq!(s1.borrow().value, MyString::new("test3"));
+        assert_eq!(Rc::strong_count(&s1), 2);
+
+        drop(guard);
+        // the observable keeps no reference to the observer
+        assert_eq!(Rc::strong_count(&s1), 1);
+        assert!(format!("{:?}", o).contains("observer_ids: [2]"));
+        // observers of dropped guards are not counted
+        assert_eq!(o.observer_count(), 1);
+        assert_eq!(o.observer_ids(), vec![id2]);
+        assert_eq!(o.notify_observers(MyString::new("test4")).called, 1);
+        assert_eq!(s1.borrow().value, MyString::new("test3"));
+        assert_eq!(s2.borrow().value, MyString::new("test4"));
+        assert_eq!(o.clear(), 1);
+        assert_eq!(o.notify_observers(MyString::new("test5")).called, 0);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_tokio_sender_observer() {
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        use crate::observable::Observable;
+
+        let o = Observable::<MyString>::new();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        o.register(Rc::new(RefCell::new(tx)));
+
+        o.notify_observers(MyString::new("test1"));
+        // channel is full, the second value is dropped
+        o.notify_observers(MyString::new("test2"));
+
+        assert_eq!(rx.try_recv().unwrap(), MyString::new("test1"));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_unregister_foreign_id() {
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        use crate::error::Error;
+        use crate::observable::Observable;
+
+        let o1 = Observable::<MyString>::new();
+        let o2 = Observable::<MyString>::new();
+        let s1 = Rc::new(RefCell::new(ObserverString::new("test1")));
+        let s2 = Rc::new(RefCell::new(ObserverString::new("test2")));
+        let s1_id = o1.register(s1.clone());
+        let s2_id = o2.register(s2.clone());
+        // both observables start to count with 1
+        assert_eq!(s1_id.value(), s2_id.value());
+
+        assert_eq!(o2.unregister(s1_id), Err(Error::ForeignObserverId(s1_id)));
+        o2.notify_observers(MyString::new("test3"));
+        assert_eq!(s2.borrow().value, MyString::new("test3"));
+
+        o1.unregister(s1_id).unwrap();
+        o1.notify_observers(MyString::new("test4"));
+        assert_eq!(s1.borrow().value, MyString::new("test1"));
+    }
+
+    #[test]
+    fn test_reentrant_registration() {
+        use std::cell::{Cell, RefCell};
+        use std::rc::Rc;
+        use crate::observable::Observable;
+        use crate::observer_id::ObserverId;
+
+        thread_local! {
+            static OBSERVABLE: Rc<Observable<u32>> = Rc::new(Observable::new());
+            static FIRST: Cell<Option<ObserverId>> = const { Cell::new(None) };
+            static SEEN: RefCell<Vec<(&'static str, u32)>> = const { RefCell::new(Vec::new()) };
+        }
+        let o = OBSERVABLE.with(|o| o.clone());
+        // the first observer replaces itself by another one
+        let first = o.register_fn(|v| {
+            SEEN.with(|s| s.borrow_mut().push(("first", v)));
+            OBSERVABLE.with(|o| {
+                o.unregister(FIRST.with(|f| f.get()).unwrap()).unwrap();
+                o.register_fn(|v| SEEN.with(|s| s.borrow_mut().push(("added", v))));
+            });
+        });
+        FIRST.with(|f| f.set(Some(first)));
+        o.register_fn(|v| SEEN.with(|s| s.borrow_mut().push(("second", v))));
+
+        // the changes are applied after the notification
+        assert_eq!(o.notify_observers(1).called, 2);
+        assert_eq!(o.observer_count(), 2);
+        assert_eq!(o.notify_observers(2).called, 2);
+        let seen = SEEN.with(|s| s.borrow().clone());
+        assert_eq!(seen, vec![("first", 1), ("second", 1), ("second", 2), ("added", 2)]);
+    }
+
+    #[test]
+    fn test_panicking_observer() {
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        use crate::observable::Observable;
+
+        let o = Observable::<MyString>::new();
+        let id = o.register_fn(|_| panic!("observer failed"));
+        let s = Rc::new(RefCell::new(ObserverString::new("test1")));
+        o.register(s.clone());
+
+        let notified = o.notify_observers(MyString::new("test2"));
+        assert_eq!(notified.called, 2);
+        assert_eq!(notified.panicked, vec![id]);
+        assert!(!notified.is_complete());
+        assert_eq!(s.borrow().value, MyString::new("test2"));
+    }
+
+    #[test]
+    fn test_try_observer() {
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        use crate::error::{Error, Result};
+        use crate::observable::Observable;
+
+        let o = Observable::<u32>::new();
+        let odd = |v: u32| -> Result<()> {
+            if v.is_multiple_of(2) {
+                return Err(Error::Observer(format!("{} is even", v)));
+            }
+            Ok(())
+        };
+        let id = o.register_try(Rc::new(RefCell::new(odd)));
+        o.register_fn(|_| ());
+
+        assert!(o.notify_observers(1).is_complete());
+        let notified = o.notify_observers(2);
+        assert_eq!(notified.called, 2);
+        assert_eq!(notified.failed, vec![(id, Error::Observer("2 is even".to_string()))]);
+        assert!(notified.panicked.is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+//! A single threaded observable boolean, e.g. a readiness flag
+
+use crate::error::Result;
+use crate::observable::Observer;
+use crate::observed_value_init::ObservedValueInit;
+use crate::observer_id::ObserverId;
+use std::cell::RefCell;
+use std::fmt::{self, Debug, Formatter};
+use std::ops::Deref;
+use std::rc::Rc;
+
+/// Observable boolean. The observers are only notified, when the flag
+/// changes.
+pub struct ObservedFlag(ObservedValueInit<bool>);
+
+impl Debug for ObservedFlag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ObservedFlag").field(&*self.0).finish()
+    }
+}
+
+impl Default for ObservedFlag {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl ObservedFlag {
+    /// Create a new instance
+    ///
+    /// ## Arguments
+    /// * `value` - initial state
+    ///
+    pub fn new(value: bool) -> Self {
+        ObservedFlag(ObservedValueInit::new(value))
+    }
+
+    /// Sets the flag. It returns true if it was changed.
+    ///
+    /// ## Arguments
+    /// * `v` - new state
+    ///
+    pub fn set(&mut self, v: bool) -> bool {
+        self.0.set_value_if_changed(&v)
+    }
+
+    /// Sets the flag to true. It returns true if it was changed.
+    pub fn set_true(&mut self) -> bool {
+        self.set(true)
+    }
+
+    /// Sets the flag to false. It returns true if it was changed.
+    pub fn set_false(&mut self) -> bool {
+        self.set(false)
+    }
+
+    /// Inverts the flag and returns the new state
+    pub fn toggle(&mut self) -> bool {
+        let v = !*self.0;
+        self.0.set_value(&v);
+        v
+    }
+
+    /// This function registers a new observer. It returns the ID of the registered
+    /// observer.
+    ///
+    /// ## Arguments
+    /// * `observer` - implementation of the Observer trait that should be registered
+    ///
+    pub fn register(&mut self, observer: Rc<RefCell<dyn Observer<bool> + Send + Sync>>) -> ObserverId {
+        self.0.register(observer)
+    }
+
+    /// This function unregisters an observer. It fails if the ID was
+    /// created by another observable.
+    ///
+    /// ## Arguments
+    /// * `observer_id` - ID returned after the registration of an observer
+    ///
+    pub fn unregister(&mut self, observer_id: ObserverId) -> Result<()> {
+        self.0.unregister(observer_id)
+    }
+
+    /// Returns the number of registered observers
+    pub fn observer_count(&self) -> usize {
+        self.0.observer_count()
+    }
+}
+
+impl Deref for ObservedFlag {
+    type Target = bool;
+
+    fn deref(&self) -> &bool {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::mpsc;
+
+    use crate::observed_flag::ObservedFlag;
+
+    #[test]
+    fn test_observed_flag() {
+        let mut ready = ObservedFlag::default();
+        let (tx, rx) = mpsc::channel();
+        ready.register(Rc::new(RefCell::new(tx)));
+        assert!(ready.set_true());
+        assert!(!ready.set_true());
+        assert!(!ready.toggle());
+        assert!(!ready.set_false());
+        assert!(!*ready);
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![true, false]);
+    }
+}
@@ -1,14 +1,41 @@
-/// A single threaded observable wrapper, put around a monitored varlue
+//! A single threaded observable wrapper, put around a monitored varlue
 
+use crate::error::Result;
+use crate::history::History;
 use crate::observable::{Observable, Observer};
-use std::ops::{Deref, DerefMut};
+use crate::observer_id::ObserverId;
+use std::ops::{Add, Deref, DerefMut, Sub};
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::fmt::{self, Debug, Formatter};
+use std::time::Instant;
+
+/// Returns true if two values of an observed value are seen as equal
+type Comparator<T> = Rc<dyn Fn(&T, &T) -> bool>;
 
 /// Object that holds the monitored value and its observers
 pub struct ObservedValue<T: Clone> {
     observable: Observable<Option<T>>,
     value: Option<T>,
+    /// Timed changes of the value, if the history is enabled
+    history: Option<History<Instant, T>>,
+    /// Replaces `PartialEq` in `set_value_if_changed`, if it's set
+    comparator: Option<Comparator<T>>,
+}
+
+impl<T: Clone> Debug for ObservedValue<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObservedValue")
+            .field("is_set", &self.value.is_some())
+            .field("observable", &self.observable)
+            .finish()
+    }
+}
+
+impl<T: Clone> Default for ObservedValue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T: Clone> ObservedValue<T> {
@@ -17,9 +44,54 @@ impl<T: Clone> ObservedValue<T> {
         ObservedValue {
             observable: Observable::<Option<T>>::new(),
             value: None,
+            history: None,
+            comparator: None,
+        }
+    }
+
+    /// Create a new instance, that starts with a value. No observer is
+    /// notified.
+    ///
+    /// ## Arguments
+    /// * `v` - initial value
+    ///
+    pub fn with_value(v: T) -> Self {
+        ObservedValue {
+            value: Some(v),
+            ..Self::new()
         }
     }
 
+    /// Sets the function, that decides in `set_value_if_changed` if a new
+    /// value equals the current one, e.g. to ignore small changes of a
+    /// float. It replaces the `PartialEq` implementation of the value.
+    /// Values without `PartialEq` pass the function to
+    /// `set_value_if_changed_with` instead.
+    ///
+    /// ## Arguments
+    /// * `eq` - returns true if both values are seen as equal
+    ///
+    pub fn set_comparator<F: Fn(&T, &T) -> bool + 'static>(&mut self, eq: F) {
+        self.comparator = Some(Rc::new(eq));
+    }
+
+    /// Sets a new value like `set_value`, but only if the given function
+    /// doesn't see it as equal to the current one. It needs no `PartialEq`
+    /// and ignores the function of `set_comparator`. It returns true if
+    /// the value was set and the observers were notified.
+    ///
+    /// ## Arguments
+    /// * `v` - value to set
+    /// * `eq` - returns true if both values are seen as equal
+    ///
+    pub fn set_value_if_changed_with<F: Fn(&T, &T) -> bool>(&mut self, v: &T, eq: F) -> bool {
+        if self.value.as_ref().is_some_and(|c| eq(c, v)) {
+            return false;
+        }
+        self.set_value(v);
+        true
+    }
+
     /// Set a new value to the object. All registered observers are
     /// called to get notified.
     /// 
@@ -28,6 +100,7 @@ impl<T: Clone> ObservedValue<T> {
     /// 
     pub fn set_value(&mut self, v: &T) {
         self.value = Some(v.clone());
+        self.record();
         self.observable.notify_observers(Some(v.clone()));
     }
 
@@ -36,28 +109,193 @@ impl<T: Clone> ObservedValue<T> {
     ///
     pub fn reset_value(&mut self) {
         self.value = None;
+        self.record();
         self.observable.notify_observers(None);
     }
 
+    /// Changes the value with a closure and notifies the observers with
+    /// the result. Other than a change over `DerefMut`, it's kept in the
+    /// history.
+    ///
+    /// ## Arguments
+    /// * `f` - closure that changes the current value
+    ///
+    pub fn modify<F: FnOnce(&mut Option<T>)>(&mut self, f: F) {
+        f(&mut self.value);
+        self.record();
+        self.observable.notify_observers(self.value.clone());
+    }
+
+    /// Returns a guard with write access to the value. When the guard is
+    /// dropped after a mutable access, the change is kept in the history
+    /// and the observers are notified.
+    pub fn edit(&mut self) -> ChangeGuard<'_, T> {
+        ChangeGuard {
+            value: self,
+            changed: false,
+        }
+    }
+
+    fn record(&mut self) {
+        if let Some(h) = &mut self.history {
+            h.record(Instant::now(), self.value.clone());
+        }
+    }
+
+    /// Starts to keep the changes of `set_value` and `reset_value` together
+    /// with their time, direct changes over `DerefMut` are not kept. The
+    /// current value is the first entry. Calling it again restarts the
+    /// history.
+    ///
+    /// ## Arguments
+    /// * `depth` - max number of kept changes, the oldest are dropped first
+    ///
+    pub fn enable_history(&mut self, depth: usize) {
+        self.history = Some(History::new(depth));
+        self.record();
+    }
+
+    /// Returns the value that was valid at the given time. It returns `None`
+    /// if the value was unset, the time is older than the kept history or
+    /// the history isn't enabled.
+    ///
+    /// ## Arguments
+    /// * `at` - time to look up
+    ///
+    pub fn value_at(&self, at: Instant) -> Option<T> {
+        self.history.as_ref()?.value_at(at)
+    }
+
+    /// Returns the kept changes in the time range with the time of the
+    /// change, both ends are included. `None` stands for a reset.
+    ///
+    /// ## Arguments
+    /// * `from` - start of the range
+    /// * `to` - end of the range
+    ///
+    pub fn changes_between(&self, from: Instant, to: Instant) -> Vec<(Instant, Option<T>)> {
+        match &self.history {
+            Some(h) => h.changes_between(from, to),
+            None => Vec::new(),
+        }
+    }
+
     /// This function registers a new observer. It returns the ID of the registered
     /// observer.
     /// 
     /// ## Arguments
     /// * `observer` - implementation of the Observer trait that should be registered
     /// 
-    pub fn register(&mut self, observer: Rc<RefCell<dyn Observer<Option<T>> + Send + Sync>>) -> u32 {
+    pub fn register(&mut self, observer: Rc<RefCell<dyn Observer<Option<T>> + Send + Sync>>) -> ObserverId {
         self.observable.register(observer)
     }
 
-    /// This function unregisters an observer.
+    /// This function unregisters an observer. It fails if the ID was
+    /// created by another observable.
     /// 
     /// ## Arguments
     /// * `observer_id` - ID returned after the registration of an observer
     /// 
-    pub fn unregister(&mut self, observer_id: u32) {
-        self.observable.unregister(observer_id);
+    pub fn unregister(&mut self, observer_id: ObserverId) -> Result<()> {
+        self.observable.unregister(observer_id)
+    }
+
+    /// Returns the number of registered observers
+    pub fn observer_count(&self) -> usize {
+        self.observable.observer_count()
+    }
+
+    /// Returns the IDs of the registered observers in the order of
+    /// registration
+    pub fn observer_ids(&self) -> Vec<ObserverId> {
+        self.observable.observer_ids()
+    }
+
+}
+
+impl<T: Clone + PartialEq> ObservedValue<T> {
+    /// Sets a new value like `set_value`, but only if it differs from the
+    /// current one, see `set_comparator`. It returns true if the value was set and the observers
+    /// were notified.
+    ///
+    /// ## Arguments
+    /// * `v` - value to set
+    ///
+    pub fn set_value_if_changed(&mut self, v: &T) -> bool {
+        match self.comparator.clone() {
+            Some(eq) => self.set_value_if_changed_with(v, |a, b| eq(a, b)),
+            None => self.set_value_if_changed_with(v, PartialEq::eq),
+        }
+    }
+}
+
+impl<T: Clone + Default + Add<Output = T> + Sub<Output = T>> ObservedValue<T> {
+    /// Adds to the value and notifies the observers, for counters and
+    /// gauges. An unset value counts as the default value. It returns the
+    /// new value.
+    ///
+    /// ## Arguments
+    /// * `by` - amount to add
+    ///
+    pub fn increment(&mut self, by: T) -> T {
+        self.step(|v| v + by)
+    }
+
+    /// Subtracts from the value and notifies the observers, like
+    /// `increment`. It returns the new value.
+    ///
+    /// ## Arguments
+    /// * `by` - amount to subtract
+    ///
+    pub fn decrement(&mut self, by: T) -> T {
+        self.step(|v| v - by)
     }
 
+    fn step<F: FnOnce(T) -> T>(&mut self, f: F) -> T {
+        let v = f(self.value.take().unwrap_or_default());
+        self.set_value(&v);
+        v
+    }
+}
+
+/// Write access to an observed value, it's created by
+/// `ObservedValue::edit`. The observers are notified when the guard is
+/// dropped, if the value was mutably accessed.
+pub struct ChangeGuard<'a, T: Clone> {
+    value: &'a mut ObservedValue<T>,
+    /// True after a mutable access to the value
+    changed: bool,
+}
+
+impl<T: Clone> Debug for ChangeGuard<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChangeGuard")
+            .field("changed", &self.changed)
+            .finish()
+    }
+}
+
+impl<T: Clone> Deref for ChangeGuard<'_, T> {
+    type Target = Option<T>;
+
+    fn deref(&self) -> &Option<T> {
+        &self.value.value
+    }
+}
+
+impl<T: Clone> DerefMut for ChangeGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Option<T> {
+        self.changed = true;
+        &mut self.value.value
+    }
+}
+
+impl<T: Clone> Drop for ChangeGuard<'_, T> {
+    fn drop(&mut self) {
+        if self.changed {
+            self.value.modify(|_| ());
+        }
+    }
 }
 
 impl<T: Clone> Deref for ObservedValue<T> {
@@ -74,6 +312,7 @@ impl<T: Clone> DerefMut for ObservedValue<T> {
     }
 }
 
+#[cfg(test)]
 mod tests {
     //#![allow(dead_code)]
     use crate::observed_value::ObservedValue;
@@ -124,7 +363,6 @@ mod tests {
     fn test_01() {
         use std::rc::Rc;
         use std::cell::RefCell;
-        use crate::observable::Observable;
 
         let mut o = ObservedValue::<MyString>::new();
 
@@ -164,7 +402,7 @@ mod tests {
         assert_eq!(*s3.borrow().value.as_ref().unwrap(), v2);
         assert_eq!(*s4.borrow().value.as_ref().unwrap(), v2);
 
-        o.unregister(s1_id);
+        o.unregister(s1_id).unwrap();
 
         o.reset_value();
 
@@ -173,4 +411,98 @@ mod tests {
         assert!(s3.borrow().value.is_none());
         assert!(s4.borrow().value.is_none());
     }
+
+    #[test]
+    fn test_value_history() {
+        use std::thread::sleep;
+        use std::time::{Duration, Instant};
+
+        let mut o: ObservedValue<u32> = ObservedValue::new();
+        o.set_value(&1);
+        o.enable_history(10);
+        sleep(Duration::from_millis(2));
+        let before = Instant::now();
+        sleep(Duration::from_millis(2));
+        o.set_value(&2);
+        o.reset_value();
+
+        assert_eq!(o.value_at(before), Some(1));
+        assert_eq!(o.value_at(Instant::now()), None);
+        let changes = o.changes_between(before, Instant::now());
+        let values: Vec<Option<u32>> = changes.into_iter().map(|(_, v)| v).collect();
+        assert_eq!(values, vec![Some(2), None]);
+    }
+
+    #[test]
+    fn test_modify() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        use std::sync::mpsc;
+
+        let mut o: ObservedValue<Vec<u32>> = ObservedValue::new();
+        let (tx, rx) = mpsc::channel();
+        o.register(Rc::new(RefCell::new(tx)));
+        o.modify(|v| v.get_or_insert_with(Vec::new).push(1));
+        {
+            let mut guard = o.edit();
+            guard.as_mut().unwrap().push(2);
+            guard.as_mut().unwrap().push(3);
+        }
+        // read only access doesn't notify
+        assert_eq!(*o.edit(), Some(vec![1, 2, 3]));
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![Some(vec![1]), Some(vec![1, 2, 3])]);
+    }
+
+    #[test]
+    fn test_increment() {
+        let mut o: ObservedValue<i32> = ObservedValue::new();
+        assert_eq!(o.increment(2), 2);
+        assert_eq!(o.decrement(5), -3);
+        assert_eq!(*o, Some(-3));
+    }
+
+    #[test]
+    fn test_with_value() {
+        let o = ObservedValue::with_value(1u32);
+        assert_eq!(*o, Some(1));
+    }
+
+    #[test]
+    fn test_set_value_if_changed() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        use std::sync::mpsc;
+
+        let mut o: ObservedValue<u32> = ObservedValue::new();
+        let (tx, rx) = mpsc::channel();
+        o.register(Rc::new(RefCell::new(tx)));
+        assert!(o.set_value_if_changed(&1));
+        assert!(!o.set_value_if_changed(&1));
+        assert!(o.set_value_if_changed(&2));
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![Some(1), Some(2)]);
+
+        let mut f: ObservedValue<f64> = ObservedValue::new();
+        f.set_comparator(|a, b| (a - b).abs() < 0.1);
+        assert!(f.set_value_if_changed(&1.0));
+        assert!(!f.set_value_if_changed(&1.05));
+        assert!(f.set_value_if_changed(&1.2));
+        assert_eq!(*f, Some(1.2));
+    }
+
+    #[test]
+    fn test_set_value_if_changed_with() {
+        // no PartialEq, only the name is compared
+        #[derive(Clone)]
+        struct Item {
+            name: &'static str,
+            _hits: u32,
+        }
+
+        let same_name = |a: &Item, b: &Item| a.name == b.name;
+        let mut o: ObservedValue<Item> = ObservedValue::new();
+        assert!(o.set_value_if_changed_with(&Item { name: "a", _hits: 1 }, same_name));
+        assert!(!o.set_value_if_changed_with(&Item { name: "a", _hits: 2 }, same_name));
+        assert!(o.set_value_if_changed_with(&Item { name: "b", _hits: 3 }, same_name));
+        assert_eq!(o.as_ref().map(|i| i.name), Some("b"));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,161 @@
+//! A single threaded observable wrapper around a value, that is always set
+
+use crate::error::Result;
+use crate::observable::{Observable, Observer};
+use crate::observer_id::ObserverId;
+use std::cell::RefCell;
+use std::fmt::{self, Debug, Formatter};
+use std::ops::{Add, Deref, Sub};
+use std::rc::Rc;
+
+/// Object that holds the monitored value and its observers. Other than
+/// `ObservedValue` it starts with a value and can't be reset, so the
+/// observers get the plain value.
+pub struct ObservedValueInit<T: Clone> {
+    observable: Observable<T>,
+    value: T,
+}
+
+impl<T: Clone + Debug> Debug for ObservedValueInit<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObservedValueInit")
+            .field("value", &self.value)
+            .field("observable", &self.observable)
+            .finish()
+    }
+}
+
+impl<T: Clone> ObservedValueInit<T> {
+    /// Create a new instance
+    ///
+    /// ## Arguments
+    /// * `value` - initial value
+    ///
+    pub fn new(value: T) -> Self {
+        ObservedValueInit {
+            observable: Observable::new(),
+            value,
+        }
+    }
+
+    /// Set a new value to the object. All registered observers are
+    /// called to get notified.
+    ///
+    /// ## Arguments
+    /// * `v` - value to set
+    ///
+    pub fn set_value(&mut self, v: &T) {
+        self.value = v.clone();
+        self.observable.notify_observers(v.clone());
+    }
+
+    /// This function registers a new observer. It returns the ID of the registered
+    /// observer.
+    ///
+    /// ## Arguments
+    /// * `observer` - implementation of the Observer trait that should be registered
+    ///
+    pub fn register(&mut self, observer: Rc<RefCell<dyn Observer<T> + Send + Sync>>) -> ObserverId {
+        self.observable.register(observer)
+    }
+
+    /// This function unregisters an observer. It fails if the ID was
+    /// created by another observable.
+    ///
+    /// ## Arguments
+    /// * `observer_id` - ID returned after the registration of an observer
+    ///
+    pub fn unregister(&mut self, observer_id: ObserverId) -> Result<()> {
+        self.observable.unregister(observer_id)
+    }
+
+    /// Returns the number of registered observers
+    pub fn observer_count(&self) -> usize {
+        self.observable.observer_count()
+    }
+
+    /// Returns the IDs of the registered observers in the order of
+    /// registration
+    pub fn observer_ids(&self) -> Vec<ObserverId> {
+        self.observable.observer_ids()
+    }
+}
+
+impl<T: Clone + PartialEq> ObservedValueInit<T> {
+    /// Sets a new value like `set_value`, but only if it differs from the
+    /// current one. It returns true if the value was set and the observers
+    /// were notified.
+    ///
+    /// ## Arguments
+    /// * `v` - value to set
+    ///
+    pub fn set_value_if_changed(&mut self, v: &T) -> bool {
+        if self.value == *v {
+            return false;
+        }
+        self.set_value(v);
+        true
+    }
+}
+
+impl<T: Clone + Add<Output = T> + Sub<Output = T>> ObservedValueInit<T> {
+    /// Adds to the value and notifies the observers, for counters and
+    /// gauges. It returns the new value.
+    ///
+    /// ## Arguments
+    /// * `by` - amount to add
+    ///
+    pub fn increment(&mut self, by: T) -> T {
+        let v = self.value.clone() + by;
+        self.set_value(&v);
+        v
+    }
+
+    /// Subtracts from the value and notifies the observers, like
+    /// `increment`. It returns the new value.
+    ///
+    /// ## Arguments
+    /// * `by` - amount to subtract
+    ///
+    pub fn decrement(&mut self, by: T) -> T {
+        let v = self.value.clone() - by;
+        self.set_value(&v);
+        v
+    }
+}
+
+/// Read access to the value, it's changed only by `set_value`, so no
+/// change bypasses the observers
+impl<T: Clone> Deref for ObservedValueInit<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::mpsc;
+
+    use crate::observed_value_init::ObservedValueInit;
+
+    #[test]
+    fn test_observed_value_init() {
+        let mut o = ObservedValueInit::new(1u32);
+        assert_eq!(*o, 1);
+        let (tx, rx) = mpsc::channel();
+        let id = o.register(Rc::new(RefCell::new(tx)));
+        o.set_value(&2);
+        assert!(!o.set_value_if_changed(&2));
+        assert!(o.set_value_if_changed(&3));
+        assert_eq!(*o, 3);
+        assert_eq!(o.increment(2), 5);
+        assert_eq!(o.decrement(1), 4);
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![2, 3, 5, 4]);
+        o.unregister(id).unwrap();
+        assert_eq!(o.observer_count(), 0);
+    }
+}
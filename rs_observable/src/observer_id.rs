@@ -0,0 +1,51 @@
+//! Typed IDs of registered observers
+
+use std::fmt::{self, Display, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::error::{Error, Result};
+
+/// Source for the tags that identify the observable instances
+static NEXT_TAG: AtomicU64 = AtomicU64::new(1);
+
+/// Returns a new tag, that is unique for the running process
+pub(crate) fn next_tag() -> u64 {
+    NEXT_TAG.fetch_add(1, Ordering::Relaxed)
+}
+
+/// ID of a registered observer. Beside the number of the registration it
+/// carries the tag of the observable that created it, so it can't be
+/// confused with the ID of an observer of another observable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObserverId {
+    /// Tag of the observable that assigned the ID
+    tag: u64,
+    /// Number of the registration, unique per observable
+    id: u32,
+}
+
+impl ObserverId {
+    pub(crate) fn new(tag: u64, id: u32) -> Self {
+        ObserverId { tag, id }
+    }
+
+    /// Returns the number of the registration, without the observable tag
+    pub fn value(&self) -> u32 {
+        self.id
+    }
+
+    /// Checks that the ID was created by the observable with the given tag
+    pub(crate) fn check_tag(&self, tag: u64) -> Result<()> {
+        if self.tag == tag {
+            Ok(())
+        } else {
+            Err(Error::ForeignObserverId(*self))
+        }
+    }
+}
+
+impl Display for ObserverId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
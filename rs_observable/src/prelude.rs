@@ -0,0 +1,39 @@
+//! Re-exports of the commonly used types and traits
+//!
+//! ```
+//! use rs_observable::prelude::*;
+//!
+//! // `Result` defaults to the error of this crate, other errors can be given
+//! fn parse(s: &str) -> Result<u32, std::num::ParseIntError> {
+//!     s.parse()
+//! }
+//!
+//! fn check(id: u32) -> Result<u32> {
+//!     parse(&id.to_string()).map_err(|e| Error::Validation(e.to_string()))
+//! }
+//! # assert_eq!(check(1), Ok(1));
+//! ```
+
+pub use crate::error::{Error, Result};
+pub use crate::observer_id::ObserverId;
+
+#[cfg(feature = "single")]
+pub use crate::observable::{Observable, Observer, TryObserver};
+
+#[cfg(feature = "single")]
+pub use crate::observed_value::ObservedValue;
+
+#[cfg(feature = "single")]
+pub use crate::observed_value_init::ObservedValueInit;
+
+#[cfg(feature = "threaded")]
+pub use crate::aobservable::AObservable;
+
+#[cfg(feature = "threaded")]
+pub use crate::aobserved_value::AObservedValue;
+
+#[cfg(feature = "tokio")]
+pub use crate::chobservable::{ChObservable, ChObservedValue, ChObservedValueInit, OverflowPolicy};
+
+#[cfg(feature = "tokio")]
+pub use crate::executor::NotifyExecutor;
@@ -0,0 +1,301 @@
+//! Observer that records every notification together with a timestamp
+//!
+//! The recorded sequence can be exported as JSON or CSV, so it can be used
+//! in tests as well as a lightweight diagnostic in production.
+
+use std::fmt::{Debug, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "single")]
+use crate::observable::Observer;
+
+#[cfg(feature = "tokio")]
+use crate::chobservable::ChObservable;
+#[cfg(feature = "tokio")]
+use crate::observer_id::ObserverId;
+
+/// One recorded notification
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recorded<T> {
+    /// Position in the sequence of all received notifications, starts with 0
+    pub seq: u64,
+    /// Wall clock time of the notification
+    pub timestamp: SystemTime,
+    /// Time since the creation of the recorder
+    pub elapsed: Duration,
+    /// Notified value
+    pub value: T,
+}
+
+#[derive(Debug)]
+struct RecorderState<T> {
+    entries: Vec<Recorded<T>>,
+    next_seq: u64,
+    /// Max number of kept entries, the oldest entries are dropped first
+    limit: Option<usize>,
+}
+
+/// Observer that records the notified values with timestamps. Clones
+/// share the recorded values.
+#[derive(Debug)]
+pub struct RecordingObserver<T> {
+    state: Arc<Mutex<RecorderState<T>>>,
+    start: Instant,
+}
+
+impl<T> Clone for RecordingObserver<T> {
+    fn clone(&self) -> Self {
+        RecordingObserver {
+            state: self.state.clone(),
+            start: self.start,
+        }
+    }
+}
+
+impl<T> Default for RecordingObserver<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> RecordingObserver<T> {
+    /// Creates a new recorder that keeps all values
+    pub fn new() -> Self {
+        Self::create(None)
+    }
+
+    /// Creates a new recorder that keeps only the latest values, suitable
+    /// for long running processes
+    ///
+    /// ## Arguments
+    /// * `limit` - max number of kept entries
+    ///
+    pub fn with_limit(limit: usize) -> Self {
+        Self::create(Some(limit))
+    }
+
+    fn create(limit: Option<usize>) -> Self {
+        RecordingObserver {
+            state: Arc::new(Mutex::new(RecorderState {
+                entries: Vec::new(),
+                next_seq: 0,
+                limit,
+            })),
+            start: Instant::now(),
+        }
+    }
+
+    /// Records a value, the function is called by the observer implementations
+    pub fn record(&self, value: T) {
+        let mut s = self.state.lock().unwrap();
+        let seq = s.next_seq;
+        s.next_seq += 1;
+        s.entries.push(Recorded {
+            seq,
+            timestamp: SystemTime::now(),
+            elapsed: self.start.elapsed(),
+            value,
+        });
+        if let Some(limit) = s.limit {
+            if s.entries.len() > limit {
+                let n = s.entries.len() - limit;
+                s.entries.drain(..n);
+            }
+        }
+    }
+
+    /// Returns the number of kept entries
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    /// Returns true if there are no kept entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all kept entries, the sequence numbers continue
+    pub fn clear(&self) {
+        self.state.lock().unwrap().entries.clear();
+    }
+
+    /// Exports the kept entries as JSON array. Every entry is an object
+    /// with the fields `seq`, `timestamp_us` (since UNIX epoch), `elapsed_us`
+    /// and `value`.
+    ///
+    /// ## Arguments
+    /// * `format` - creates the string representation of a value
+    ///
+    pub fn to_json_with<F: Fn(&T) -> String>(&self, format: F) -> String {
+        let s = self.state.lock().unwrap();
+        let mut out = String::from("[");
+        for (i, e) in s.entries.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let _ = write!(
+                out,
+                "{{\"seq\":{},\"timestamp_us\":{},\"elapsed_us\":{},\"value\":\"{}\"}}",
+                e.seq,
+                epoch_micros(e.timestamp),
+                e.elapsed.as_micros(),
+                json_escape(&format(&e.value))
+            );
+        }
+        out.push(']');
+        out
+    }
+
+    /// Exports the kept entries as CSV, with the header line
+    /// `seq,timestamp_us,elapsed_us,value`
+    ///
+    /// ## Arguments
+    /// * `format` - creates the string representation of a value
+    ///
+    pub fn to_csv_with<F: Fn(&T) -> String>(&self, format: F) -> String {
+        let s = self.state.lock().unwrap();
+        let mut out = String::from("seq,timestamp_us,elapsed_us,value\n");
+        for e in s.entries.iter() {
+            let _ = writeln!(
+                out,
+                "{},{},{},\"{}\"",
+                e.seq,
+                epoch_micros(e.timestamp),
+                e.elapsed.as_micros(),
+                format(&e.value).replace('"', "\"\"")
+            );
+        }
+        out
+    }
+}
+
+impl<T: Clone> RecordingObserver<T> {
+    /// Returns a copy of the kept entries
+    pub fn records(&self) -> Vec<Recorded<T>> {
+        self.state.lock().unwrap().entries.clone()
+    }
+
+    /// Returns a closure that records the values, e.g. to use it with
+    /// `ChObservable::register_callback`
+    pub fn callback(&self) -> impl Fn(T) + Send + Sync + 'static
+    where
+        T: Send + 'static,
+    {
+        let me = self.clone();
+        move |value: T| me.record(value)
+    }
+}
+
+impl<T: Debug> RecordingObserver<T> {
+    /// Exports the kept entries as JSON, the values are written with
+    /// their Debug representation
+    pub fn to_json(&self) -> String {
+        self.to_json_with(|v| format!("{:?}", v))
+    }
+
+    /// Exports the kept entries as CSV, the values are written with
+    /// their Debug representation
+    pub fn to_csv(&self) -> String {
+        self.to_csv_with(|v| format!("{:?}", v))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Clone + Send + 'static> RecordingObserver<T> {
+    /// Registers the recorder as callback observer on the given observable
+    ///
+    /// ## Arguments
+    /// * `observable` - observable to record
+    ///
+    pub async fn attach(&self, observable: &ChObservable<T>) -> ObserverId {
+        observable.register_callback(self.callback()).await
+    }
+}
+
+#[cfg(feature = "single")]
+impl<T: Clone> Observer<T> for RecordingObserver<T> {
+    fn notify(&mut self, data: T) {
+        self.record(data);
+    }
+}
+
+fn epoch_micros(t: SystemTime) -> u128 {
+    t.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros())
+        .unwrap_or(0)
+}
+
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::recording::RecordingObserver;
+
+    #[test]
+    fn test_recording_export() {
+        let rec: RecordingObserver<String> = RecordingObserver::new();
+        rec.record("a \"quoted\" value".to_string());
+        rec.record("line\nbreak".to_string());
+
+        let records = rec.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].seq, 0);
+        assert_eq!(records[1].seq, 1);
+        assert!(records[0].elapsed <= records[1].elapsed);
+
+        let json = rec.to_json_with(|v| v.clone());
+        assert!(json.starts_with("[{\"seq\":0,\"timestamp_us\":"));
+        assert!(json.contains("\"value\":\"a \\\"quoted\\\" value\"}"));
+        assert!(json.contains("\"value\":\"line\\nbreak\"}]"));
+
+        let csv = rec.to_csv_with(|v| v.clone());
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("seq,timestamp_us,elapsed_us,value"));
+        assert!(lines.next().unwrap().ends_with(",\"a \"\"quoted\"\" value\""));
+    }
+
+    #[test]
+    fn test_recording_limit() {
+        let rec: RecordingObserver<u32> = RecordingObserver::with_limit(2);
+        for i in 0..5 {
+            rec.record(i);
+        }
+        let records = rec.records();
+        assert_eq!(records.iter().map(|r| r.value).collect::<Vec<_>>(), vec![3, 4]);
+        assert_eq!(records[0].seq, 3);
+        assert!(rec.to_csv().ends_with(",\"4\"\n"));
+        rec.clear();
+        assert!(rec.is_empty());
+        assert_eq!(rec.to_json(), "[]");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_recording_attach() {
+        use crate::chobservable::ChObservable;
+
+        let cho: ChObservable<u32> = ChObservable::new();
+        let rec: RecordingObserver<u32> = RecordingObserver::new();
+        rec.attach(&cho).await;
+        cho.notify(&1).await.unwrap();
+        cho.notify(&2).await.unwrap();
+        assert_eq!(rec.records().iter().map(|r| r.value).collect::<Vec<_>>(), vec![1, 2]);
+    }
+}
@@ -0,0 +1,162 @@
+//! Storage of the registered observers, with constant time access by the
+//! observer ID
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::ops::{Index, IndexMut};
+
+use crate::observer_id::ObserverId;
+
+/// Observers in the order of their registration. A removed observer
+/// leaves an empty slot, so the others keep their slot. The slots are
+/// compacted when more than half of them are empty.
+pub(crate) struct Slab<V> {
+    slots: Vec<Option<(ObserverId, V)>>,
+    /// Slot of each stored observer
+    index: HashMap<ObserverId, usize>,
+}
+
+impl<V> Default for Slab<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Slab<V> {
+    pub(crate) fn new() -> Self {
+        Slab {
+            slots: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of stored observers
+    pub(crate) fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Adds an observer after the existing ones, an observer with the same
+    /// ID is replaced
+    pub(crate) fn insert(&mut self, id: ObserverId, v: V) {
+        self.remove(id);
+        self.index.insert(id, self.slots.len());
+        self.slots.push(Some((id, v)));
+    }
+
+    /// Removes an observer and returns it
+    pub(crate) fn remove(&mut self, id: ObserverId) -> Option<V> {
+        let slot = self.index.remove(&id)?;
+        let (_, v) = self.slots[slot].take()?;
+        while matches!(self.slots.last(), Some(None)) {
+            self.slots.pop();
+        }
+        if self.slots.len() > 2 * self.index.len() {
+            self.compact();
+        }
+        Some(v)
+    }
+
+    pub(crate) fn get(&self, id: ObserverId) -> Option<&V> {
+        let slot = *self.index.get(&id)?;
+        self.slots[slot].as_ref().map(|(_, v)| v)
+    }
+
+    pub(crate) fn get_mut(&mut self, id: ObserverId) -> Option<&mut V> {
+        let slot = *self.index.get(&id)?;
+        self.slots[slot].as_mut().map(|(_, v)| v)
+    }
+
+    /// Returns the observers in the order of registration
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &V> {
+        self.slots.iter().flatten().map(|(_, v)| v)
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.slots.iter_mut().flatten().map(|(_, v)| v)
+    }
+
+    /// Returns the IDs of the observers in the order of registration
+    pub(crate) fn ids(&self) -> impl Iterator<Item = ObserverId> + '_ {
+        self.slots.iter().flatten().map(|(id, _)| *id)
+    }
+
+    /// Removes all observers and returns them in the order of registration
+    pub(crate) fn drain(&mut self) -> impl Iterator<Item = (ObserverId, V)> + '_ {
+        self.index.clear();
+        self.slots.drain(..).flatten()
+    }
+
+    /// Keeps only the observers, for which `keep` returns true
+    pub(crate) fn retain<F: FnMut(&V) -> bool>(&mut self, mut keep: F) {
+        for slot in self.slots.iter_mut() {
+            if slot.as_ref().is_some_and(|(_, v)| !keep(v)) {
+                *slot = None;
+            }
+        }
+        self.compact();
+    }
+
+    /// Removes the empty slots
+    fn compact(&mut self) {
+        self.slots.retain(Option::is_some);
+        self.index.clear();
+        for (slot, (id, _)) in self.slots.iter().flatten().enumerate() {
+            self.index.insert(*id, slot);
+        }
+    }
+}
+
+impl<V> Index<ObserverId> for Slab<V> {
+    type Output = V;
+
+    fn index(&self, id: ObserverId) -> &V {
+        self.get(id).expect("observer is stored")
+    }
+}
+
+impl<V> IndexMut<ObserverId> for Slab<V> {
+    fn index_mut(&mut self, id: ObserverId) -> &mut V {
+        self.get_mut(id).expect("observer is stored")
+    }
+}
+
+impl<V: Debug> Debug for Slab<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::observer_id::ObserverId;
+    use crate::slab::Slab;
+
+    #[test]
+    fn test_slab() {
+        let id = |n| ObserverId::new(1, n);
+        let mut slab = Slab::new();
+        for n in 1..=5 {
+            slab.insert(id(n), n);
+        }
+        assert_eq!(slab.remove(id(2)), Some(2));
+        assert_eq!(slab.remove(id(2)), None);
+        assert_eq!(slab[id(3)], 3);
+        slab[id(3)] = 30;
+        assert_eq!(slab.get(id(2)), None);
+
+        // the order of registration is kept after removals and compactions
+        assert_eq!(slab.remove(id(1)), Some(1));
+        assert_eq!(slab.remove(id(4)), Some(4));
+        slab.insert(id(6), 6);
+        assert_eq!(slab.iter().copied().collect::<Vec<_>>(), vec![30, 5, 6]);
+        slab.retain(|&v| v != 5);
+        assert_eq!(slab.ids().collect::<Vec<_>>(), vec![id(3), id(6)]);
+        assert_eq!(slab.get(id(6)), Some(&6));
+        assert_eq!(slab.len(), 2);
+        assert_eq!(format!("{:?}", slab), "[30, 6]");
+    }
+}
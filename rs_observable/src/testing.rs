@@ -0,0 +1,389 @@
+//! Helpers to test code that is driven by the observables of this crate
+//!
+//! `MockObserver` records the values passed to an `Observer` or callback,
+//! `MockSubscriber` records the values received over the channel of a
+//! `ChObservable` registration. `TestScheduler` controls the virtual time
+//! that is used by all time based functions of the crate.
+
+use std::fmt::Debug;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+#[cfg(feature = "tokio")]
+use tokio::sync::mpsc::Receiver;
+#[cfg(feature = "tokio")]
+use tokio::time::Instant;
+
+#[cfg(feature = "single")]
+use crate::observable::Observer;
+
+#[cfg(feature = "tokio")]
+use crate::chobservable::ChObservable;
+#[cfg(feature = "tokio")]
+use crate::observer_id::ObserverId;
+
+/// Observer that records all received values. Clones share the recorded
+/// values, so one clone can be registered while another one is used for
+/// the assertions.
+#[derive(Debug)]
+pub struct MockObserver<T> {
+    received: Arc<(Mutex<Vec<T>>, Condvar)>,
+}
+
+impl<T> Clone for MockObserver<T> {
+    fn clone(&self) -> Self {
+        MockObserver {
+            received: self.received.clone(),
+        }
+    }
+}
+
+impl<T> Default for MockObserver<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> MockObserver<T> {
+    /// Creates a new object
+    pub fn new() -> Self {
+        MockObserver {
+            received: Arc::new((Mutex::new(Vec::new()), Condvar::new())),
+        }
+    }
+
+    /// Records a value, the function is called by the observer implementations
+    pub fn record(&self, data: T) {
+        let (values, cond) = &*self.received;
+        values.lock().unwrap().push(data);
+        cond.notify_all();
+    }
+
+    /// Returns the number of received values
+    pub fn count(&self) -> usize {
+        self.received.0.lock().unwrap().len()
+    }
+
+    /// Blocks until at least `n` values are received or the timeout expires.
+    /// It returns true if the expected number of values was reached.
+    ///
+    /// ## Arguments
+    /// * `n` - number of values to wait for
+    /// * `timeout` - max time to wait
+    ///
+    pub fn await_count(&self, n: usize, timeout: Duration) -> bool {
+        let (values, cond) = &*self.received;
+        let g = values.lock().unwrap();
+        let (g, _) = cond
+            .wait_timeout_while(g, timeout, |v| v.len() < n)
+            .unwrap();
+        g.len() >= n
+    }
+}
+
+impl<T: Clone> MockObserver<T> {
+    /// Returns a copy of the values received until now
+    pub fn received_so_far(&self) -> Vec<T> {
+        self.received.0.lock().unwrap().clone()
+    }
+
+    /// Returns a closure that records the values, e.g. to use it with
+    /// `ChObservable::register_callback`
+    pub fn callback(&self) -> impl Fn(T) + Send + Sync + 'static
+    where
+        T: Send + 'static,
+    {
+        let me = self.clone();
+        move |data: T| me.record(data)
+    }
+}
+
+impl<T: Clone + PartialEq + Debug> MockObserver<T> {
+    /// Panics if the received values don't match the expected ones
+    ///
+    /// ## Arguments
+    /// * `expected` - all values that should have been received, in order
+    ///
+    pub fn assert_received(&self, expected: &[T]) {
+        assert_eq!(self.received_so_far().as_slice(), expected);
+    }
+}
+
+#[cfg(feature = "single")]
+impl<T: Clone> Observer<T> for MockObserver<T> {
+    fn notify(&mut self, data: T) {
+        self.record(data);
+    }
+}
+
+/// Records the values received over the channel of a `ChObservable`
+/// registration
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct MockSubscriber<T> {
+    rx: Receiver<T>,
+    received: Vec<T>,
+    /// True after the sending side of the channel was closed
+    closed: bool,
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Clone> MockSubscriber<T> {
+    /// Creates a new object that records the values of the given receiver
+    ///
+    /// ## Arguments
+    /// * `rx` - receiver returned by the registration
+    ///
+    pub fn new(rx: Receiver<T>) -> Self {
+        MockSubscriber {
+            rx,
+            received: Vec::new(),
+            closed: false,
+        }
+    }
+
+    /// Registers a new observer on the observable and returns its ID together
+    /// with the subscriber that records the values
+    ///
+    /// ## Arguments
+    /// * `observable` - observable to register on
+    ///
+    pub async fn register(observable: &ChObservable<T>) -> (ObserverId, Self) {
+        let (id, rx) = observable.register().await;
+        (id, MockSubscriber::new(rx))
+    }
+
+    fn collect_pending(&mut self) {
+        while let Ok(v) = self.rx.try_recv() {
+            self.received.push(v);
+        }
+    }
+
+    /// Returns a copy of the values received until now
+    pub fn received_so_far(&mut self) -> Vec<T> {
+        self.collect_pending();
+        self.received.clone()
+    }
+
+    /// Returns true if the sending side of the channel is gone
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Waits until at least `n` values are received or the timeout expires.
+    /// It returns true if the expected number of values was reached.
+    ///
+    /// ## Arguments
+    /// * `n` - number of values to wait for
+    /// * `timeout` - max time to wait
+    ///
+    pub async fn await_count(&mut self, n: usize, timeout: Duration) -> bool {
+        self.collect_pending();
+        let deadline = tokio::time::Instant::now() + timeout;
+        while self.received.len() < n && !self.closed {
+            match tokio::time::timeout_at(deadline, self.rx.recv()).await {
+                Ok(Some(v)) => self.received.push(v),
+                Ok(None) => self.closed = true,
+                Err(_) => break,
+            }
+        }
+        self.received.len() >= n
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: Clone + PartialEq + Debug> MockSubscriber<T> {
+    /// Panics if the received values don't match the expected ones
+    ///
+    /// ## Arguments
+    /// * `expected` - all values that should have been received, in order
+    ///
+    pub fn assert_received(&mut self, expected: &[T]) {
+        assert_eq!(self.received_so_far().as_slice(), expected);
+    }
+}
+
+/// Virtual clock for tests of time based behavior. On creation it pauses
+/// the tokio clock, which is used for all timeouts, delays and expiries of
+/// this crate, so time only moves forward when `advance` is called or when
+/// the runtime has nothing else to do but to wait for a timer.
+///
+/// The tokio clock can only be paused on the `current_thread` runtime.
+/// Timers have a resolution of one millisecond, a timer fires at the
+/// latest one millisecond after its deadline was passed.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct TestScheduler {
+    start: Instant,
+}
+
+#[cfg(feature = "tokio")]
+impl TestScheduler {
+    /// Pauses the tokio clock and creates a new object. It panics if it is
+    /// not called inside a `current_thread` runtime or if the clock is
+    /// already paused.
+    pub fn new() -> Self {
+        tokio::time::pause();
+        TestScheduler {
+            start: Instant::now(),
+        }
+    }
+
+    /// Returns the current virtual time
+    pub fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    /// Returns the virtual time passed since the creation
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Moves the virtual time forward and runs all timers that expired
+    /// in between
+    ///
+    /// ## Arguments
+    /// * `duration` - time to move forward
+    ///
+    pub async fn advance(&self, duration: Duration) {
+        tokio::time::advance(duration).await;
+    }
+
+    /// Moves the virtual time forward to the given instant. It does nothing
+    /// if the instant is already reached.
+    ///
+    /// ## Arguments
+    /// * `instant` - target time
+    ///
+    pub async fn advance_to(&self, instant: Instant) {
+        let now = Instant::now();
+        if instant > now {
+            tokio::time::advance(instant - now).await;
+        }
+    }
+
+    /// Gives the spawned tasks the chance to process all pending work
+    /// without moving the virtual time
+    pub async fn run_pending(&self) {
+        for _ in 0..16 {
+            tokio::task::yield_now().await;
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Default for TestScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Drop for TestScheduler {
+    fn drop(&mut self) {
+        tokio::time::resume();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::testing::MockObserver;
+
+    #[test]
+    fn test_mock_observer_threads() {
+        let mock: MockObserver<u32> = MockObserver::new();
+        let m = mock.clone();
+        let h = std::thread::spawn(move || {
+            for i in 0..3 {
+                m.record(i);
+            }
+        });
+        assert!(mock.await_count(3, Duration::from_secs(5)));
+        h.join().unwrap();
+        mock.assert_received(&[0, 1, 2]);
+        assert!(!mock.await_count(4, Duration::from_millis(10)));
+    }
+
+    #[cfg(feature = "single")]
+    #[test]
+    fn test_mock_observer_observable() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        use crate::observable::Observable;
+
+        let mock: MockObserver<String> = MockObserver::new();
+        let o = Observable::<String>::new();
+        o.register(Rc::new(RefCell::new(mock.clone())));
+        o.notify_observers("test1".to_string());
+        o.notify_observers("test2".to_string());
+        mock.assert_received(&["test1".to_string(), "test2".to_string()]);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_mock_subscriber() {
+        use crate::chobservable::ChObservable;
+        use crate::testing::MockSubscriber;
+
+        let cho: ChObservable<String> = ChObservable::new();
+        let (_, mut sub) = MockSubscriber::register(&cho).await;
+        let mock: MockObserver<String> = MockObserver::new();
+        cho.register_callback(mock.callback()).await;
+
+        let t1 = "test-1".to_string();
+        let t2 = "test-2".to_string();
+        cho.notify(&t1).await.unwrap();
+        assert_eq!(sub.received_so_far(), vec![t1.clone()]);
+        cho.notify(&t2).await.unwrap();
+
+        assert!(sub.await_count(2, Duration::from_secs(5)).await);
+        sub.assert_received(&[t1.clone(), t2.clone()]);
+        mock.assert_received(&[t1, t2]);
+        assert!(!sub.await_count(3, Duration::from_millis(10)).await);
+        assert!(!sub.is_closed());
+
+        drop(cho);
+        assert!(!sub.await_count(3, Duration::from_secs(5)).await);
+        assert!(sub.is_closed());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_scheduler() {
+        use crate::testing::TestScheduler;
+
+        let scheduler = TestScheduler::new();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            tx.send("done").await.unwrap();
+        });
+        scheduler.run_pending().await;
+
+        scheduler.advance(Duration::from_secs(5)).await;
+        scheduler.run_pending().await;
+        assert!(rx.try_recv().is_err());
+
+        scheduler.advance_to(scheduler.now() + Duration::from_millis(5001)).await;
+        scheduler.run_pending().await;
+        assert_eq!(rx.try_recv().unwrap(), "done");
+        assert_eq!(scheduler.elapsed(), Duration::from_millis(10001));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_scheduler_timeouts() {
+        use crate::chobservable::ChObservable;
+        use crate::testing::{MockSubscriber, TestScheduler};
+
+        let scheduler = TestScheduler::new();
+        let cho: ChObservable<u32> = ChObservable::new();
+        let (_, mut sub) = MockSubscriber::register(&cho).await;
+        // the timeout elapses in virtual time, the test doesn't wait an hour
+        assert!(!sub.await_count(1, Duration::from_secs(3600)).await);
+        assert!(scheduler.elapsed() >= Duration::from_secs(3600));
+        assert!(scheduler.elapsed() < Duration::from_secs(3601));
+    }
+}